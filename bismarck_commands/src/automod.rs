@@ -0,0 +1,763 @@
+use std::time::Duration;
+
+use bismarck_core::{
+    context::Context,
+    error::Error,
+    types::{AutomodAction, AutomodSettings, AutomodStrictness},
+};
+use bismarck_utilities::{
+    automod::{
+        delete_automod_domain, delete_automod_exemption, delete_automod_word,
+        insert_automod_domain, insert_automod_exemption, normalize_word, upsert_automod_config,
+        upsert_automod_word, HISTORY_WINDOW,
+    },
+    messages,
+};
+use duration_str::parse;
+use poise::{serenity_prelude as serenity, ChoiceParameter, CreateReply};
+use serenity::{CreateEmbed, GuildChannel, Role};
+use tracing::info;
+
+/// Longest timeout Discord allows.
+const MAX_TIMEOUT: Duration = Duration::from_secs(28 * 24 * 60 * 60);
+
+/// Shows the automod settings of the server.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    subcommands(
+        "enable",
+        "disable",
+        "strictness",
+        "action",
+        "deny",
+        "allow",
+        "unlist",
+        "exempt",
+        "unexempt",
+        "spam",
+        "duplicates",
+        "mentions",
+        "invites",
+        "links",
+        "allow_domain",
+        "remove_domain"
+    )
+)]
+pub async fn automod(context: Context<'_>) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let settings = context
+        .data()
+        .automod
+        .get(&guild_id.get())
+        .map(|settings| settings.clone())
+        .unwrap_or_default();
+
+    // Embed field values are limited to 1024 characters.
+    let list = |items: Vec<String>| {
+        if items.is_empty() {
+            return "None".to_string();
+        }
+
+        let mut value = String::new();
+        for (shown, item) in items.iter().enumerate() {
+            if value.len() + item.len() > 1000 {
+                value.push_str(&format!("and {} more", items.len() - shown));
+                break;
+            }
+            value.push_str(item);
+            value.push_str(", ");
+        }
+
+        value.trim_end_matches(", ").to_string()
+    };
+
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title("Automod")
+        .field("Enabled", if settings.enabled { "Yes" } else { "No" }, true)
+        .field("Strictness", settings.strictness.name(), true)
+        .field("Action", action_description(&settings), true)
+        .field(
+            "Messages",
+            limit_description(
+                settings.message_limit,
+                format!(
+                    "{} per {}s",
+                    settings.message_limit, settings.message_window
+                ),
+            ),
+            true,
+        )
+        .field(
+            "Duplicates",
+            limit_description(
+                settings.duplicate_limit,
+                format!("{} per minute", settings.duplicate_limit),
+            ),
+            true,
+        )
+        .field(
+            "Mentions",
+            limit_description(
+                settings.mention_limit,
+                format!("{} per message", settings.mention_limit),
+            ),
+            true,
+        )
+        .field(
+            "Invites",
+            if settings.filter_invites {
+                "Filtered"
+            } else {
+                "Allowed"
+            },
+            true,
+        )
+        .field(
+            "Links",
+            if settings.filter_links {
+                "Filtered"
+            } else {
+                "Allowed"
+            },
+            true,
+        )
+        .field(
+            "Allowed domains",
+            list(settings.allowed_domains.clone()),
+            false,
+        )
+        .field(
+            "Denied words",
+            list(
+                settings
+                    .denied_words
+                    .iter()
+                    .map(|word| format!("||{word}||"))
+                    .collect(),
+            ),
+            false,
+        )
+        .field("Allowed words", list(settings.allowed_words.clone()), false)
+        .field(
+            "Exempt roles",
+            list(
+                settings
+                    .exempt_roles
+                    .iter()
+                    .map(|id| format!("<@&{id}>"))
+                    .collect(),
+            ),
+            false,
+        )
+        .field(
+            "Exempt channels",
+            list(
+                settings
+                    .exempt_channels
+                    .iter()
+                    .map(|id| format!("<#{id}>"))
+                    .collect(),
+            ),
+            false,
+        );
+
+    context.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+fn action_description(settings: &AutomodSettings) -> String {
+    match settings.action {
+        AutomodAction::Timeout => format!("timeout ({}s)", settings.timeout_duration),
+        action => action.name().to_string(),
+    }
+}
+
+fn limit_description(limit: u32, description: String) -> String {
+    if limit == 0 {
+        "Off".to_string()
+    } else {
+        description
+    }
+}
+
+/// Updates the automod configuration of the guild in memory and in the database.
+async fn update_config(
+    context: Context<'_>,
+    update: impl FnOnce(&mut AutomodSettings),
+) -> Result<AutomodSettings, Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let settings = {
+        let mut settings = context.data().automod.entry(guild_id.get()).or_default();
+        update(&mut settings);
+        settings.clone()
+    };
+
+    upsert_automod_config(&guild_id, &settings, &context.data().sqlite).await?;
+
+    Ok(settings)
+}
+
+/// Turns automod on.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "MANAGE_MESSAGES | MODERATE_MEMBERS | SEND_MESSAGES",
+    guild_only
+)]
+pub async fn enable(context: Context<'_>) -> Result<(), Error> {
+    update_config(context, |settings| settings.enabled = true).await?;
+
+    info!("Automod enabled in guild {}", context.guild_id().unwrap());
+
+    let reply = messages::info_reply("Automod is now enabled.", false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Turns automod off.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn disable(context: Context<'_>) -> Result<(), Error> {
+    update_config(context, |settings| settings.enabled = false).await?;
+
+    info!("Automod disabled in guild {}", context.guild_id().unwrap());
+
+    let reply = messages::info_reply("Automod is now disabled.", false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets how much language the word filter lets through.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn strictness(
+    context: Context<'_>,
+    #[description = "Low only catches severe language, high catches anything inappropriate."]
+    strictness: AutomodStrictness,
+) -> Result<(), Error> {
+    update_config(context, |settings| settings.strictness = strictness).await?;
+
+    let reply = messages::info_reply(
+        format!("Automod strictness set to {}.", strictness.name()),
+        false,
+    );
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets what automod does with the messages it catches.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn action(
+    context: Context<'_>,
+    #[description = "The message is always deleted, and its author warned or timed out."]
+    action: AutomodAction,
+    #[description = "Duration of the timeout."] duration: Option<String>,
+) -> Result<(), Error> {
+    let duration = match duration.as_deref().map(parse) {
+        Some(Ok(duration)) if duration > MAX_TIMEOUT => {
+            let reply = messages::error_reply("Cannot timeout for longer than 28 days.", true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+        Some(Ok(duration)) => Some(duration),
+        Some(Err(why)) => {
+            let reply = messages::error_reply(why.to_string(), true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+        None => None,
+    };
+
+    let settings = update_config(context, |settings| {
+        settings.action = action;
+        if let Some(duration) = duration {
+            settings.timeout_duration = duration.as_secs();
+        }
+    })
+    .await?;
+
+    let reply = messages::info_reply(
+        format!("Automod action set to {}.", action_description(&settings)),
+        false,
+    );
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Adds a word to the deny list, which is always filtered.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    ephemeral
+)]
+pub async fn deny(
+    context: Context<'_>,
+    #[description = "The word or phrase to deny."]
+    #[max_length = 50]
+    word: String,
+) -> Result<(), Error> {
+    list_word(context, &word, false).await
+}
+
+/// Adds a word to the allow list, which is never filtered unless also denied.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    ephemeral
+)]
+pub async fn allow(
+    context: Context<'_>,
+    #[description = "The word or phrase to allow."]
+    #[max_length = 50]
+    word: String,
+) -> Result<(), Error> {
+    list_word(context, &word, true).await
+}
+
+async fn list_word(context: Context<'_>, word: &str, allowed: bool) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let word = normalize_word(word);
+
+    if word.is_empty() {
+        let reply = messages::error_reply("Words must contain letters or digits.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    upsert_automod_word(&guild_id, &word, allowed, &context.data().sqlite).await?;
+
+    {
+        let mut settings = context.data().automod.entry(guild_id.get()).or_default();
+        settings.allowed_words.retain(|listed| listed != &word);
+        settings.denied_words.retain(|listed| listed != &word);
+
+        if allowed {
+            settings.allowed_words.push(word.clone());
+        } else {
+            settings.denied_words.push(word.clone());
+        }
+    }
+
+    let list = if allowed { "allow" } else { "deny" };
+    let reply = messages::info_reply(format!("Added ||{word}|| to the {list} list."), true);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Removes a word from the allow and deny lists.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    ephemeral
+)]
+pub async fn unlist(
+    context: Context<'_>,
+    #[description = "The word or phrase to remove."]
+    #[max_length = 50]
+    word: String,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let word = normalize_word(&word);
+
+    if !delete_automod_word(&guild_id, &word, &context.data().sqlite).await? {
+        let reply = messages::error_reply(format!("||{word}|| isn't on either list."), true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    if let Some(mut settings) = context.data().automod.get_mut(&guild_id.get()) {
+        settings.allowed_words.retain(|listed| listed != &word);
+        settings.denied_words.retain(|listed| listed != &word);
+    }
+
+    let reply = messages::info_reply(format!("Removed ||{word}|| from the lists."), true);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Exempts a role or channel from automod.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn exempt(
+    context: Context<'_>,
+    #[description = "The role to exempt."] role: Option<Role>,
+    #[description = "The channel to exempt."] channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let database = &context.data().sqlite;
+
+    if role.is_none() && channel.is_none() {
+        let reply = messages::error_reply("Please provide a role or a channel.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let mut exempted = Vec::new();
+
+    if let Some(role) = role {
+        if insert_automod_exemption(&guild_id, role.id.get(), "role", database).await? {
+            let mut settings = context.data().automod.entry(guild_id.get()).or_default();
+            settings.exempt_roles.push(role.id.get());
+        }
+        exempted.push(format!("<@&{}>", role.id));
+    }
+
+    if let Some(channel) = channel {
+        if insert_automod_exemption(&guild_id, channel.id.get(), "channel", database).await? {
+            let mut settings = context.data().automod.entry(guild_id.get()).or_default();
+            settings.exempt_channels.push(channel.id.get());
+        }
+        exempted.push(format!("<#{}>", channel.id));
+    }
+
+    let reply = messages::info_reply(
+        format!("{} will be ignored by automod.", exempted.join(" and ")),
+        false,
+    );
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Removes the automod exemption of a role or channel.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn unexempt(
+    context: Context<'_>,
+    #[description = "The role to stop exempting."] role: Option<Role>,
+    #[description = "The channel to stop exempting."] channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let database = &context.data().sqlite;
+
+    if role.is_none() && channel.is_none() {
+        let reply = messages::error_reply("Please provide a role or a channel.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let mut removed = Vec::new();
+
+    if let Some(role) = role {
+        if delete_automod_exemption(&guild_id, role.id.get(), database).await? {
+            if let Some(mut settings) = context.data().automod.get_mut(&guild_id.get()) {
+                settings.exempt_roles.retain(|id| *id != role.id.get());
+            }
+            removed.push(format!("<@&{}>", role.id));
+        }
+    }
+
+    if let Some(channel) = channel {
+        if delete_automod_exemption(&guild_id, channel.id.get(), database).await? {
+            if let Some(mut settings) = context.data().automod.get_mut(&guild_id.get()) {
+                settings
+                    .exempt_channels
+                    .retain(|id| *id != channel.id.get());
+            }
+            removed.push(format!("<#{}>", channel.id));
+        }
+    }
+
+    let reply = if removed.is_empty() {
+        messages::error_reply("Nothing to remove, as neither was exempt.", true)
+    } else {
+        messages::info_reply(
+            format!(
+                "{} will no longer be ignored by automod.",
+                removed.join(" and ")
+            ),
+            false,
+        )
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets how many messages a user may send within a number of seconds.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn spam(
+    context: Context<'_>,
+    #[description = "Most messages allowed, 0 to turn off."]
+    #[max = 50]
+    messages: u32,
+    #[description = "Within this many seconds."]
+    #[min = 1]
+    #[max = 60]
+    seconds: u64,
+) -> Result<(), Error> {
+    if seconds == 0 || Duration::from_secs(seconds) > HISTORY_WINDOW {
+        let reply = messages::error_reply("Seconds must be between 1 and 60.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    update_config(context, |settings| {
+        settings.message_limit = messages;
+        settings.message_window = seconds;
+    })
+    .await?;
+
+    let description = limit_description(
+        messages,
+        format!("Users may now send up to {messages} messages per {seconds}s."),
+    );
+    let reply = messages::info_reply(format!("Message limit: {description}"), false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets how many identical messages a user may send within a minute.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn duplicates(
+    context: Context<'_>,
+    #[description = "Most identical messages allowed, 0 to turn off."]
+    #[max = 50]
+    limit: u32,
+) -> Result<(), Error> {
+    update_config(context, |settings| settings.duplicate_limit = limit).await?;
+
+    let description = limit_description(
+        limit,
+        format!("Users may now send up to {limit} identical messages per minute."),
+    );
+    let reply = messages::info_reply(format!("Duplicate limit: {description}"), false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets how many users and roles a message may mention.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn mentions(
+    context: Context<'_>,
+    #[description = "Most mentions allowed, 0 to turn off."]
+    #[max = 100]
+    limit: u32,
+) -> Result<(), Error> {
+    update_config(context, |settings| settings.mention_limit = limit).await?;
+
+    let description = limit_description(
+        limit,
+        format!("Messages may now mention up to {limit} users and roles."),
+    );
+    let reply = messages::info_reply(format!("Mention limit: {description}"), false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets whether Discord invites are filtered.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn invites(
+    context: Context<'_>,
+    #[description = "Whether to filter invites."] filter: bool,
+) -> Result<(), Error> {
+    update_config(context, |settings| settings.filter_invites = filter).await?;
+
+    let reply = if filter {
+        messages::info_reply("Discord invites will now be filtered.", false)
+    } else {
+        messages::info_reply("Discord invites are now allowed.", false)
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets whether links to domains that aren't allowed are filtered.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn links(
+    context: Context<'_>,
+    #[description = "Whether to filter links."] filter: bool,
+) -> Result<(), Error> {
+    update_config(context, |settings| settings.filter_links = filter).await?;
+
+    let reply = if filter {
+        messages::info_reply(
+            "Links to domains that aren't allowed will now be filtered.",
+            false,
+        )
+    } else {
+        messages::info_reply("Links are now allowed.", false)
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Allows links to a domain and its subdomains.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn allow_domain(
+    context: Context<'_>,
+    #[description = "The domain to allow, such as example.com."]
+    #[max_length = 100]
+    domain: String,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let domain = normalize_domain(&domain);
+
+    if domain.is_empty() || domain.contains(char::is_whitespace) {
+        let reply = messages::error_reply("Please provide a domain, such as example.com.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    if insert_automod_domain(&guild_id, &domain, &context.data().sqlite).await? {
+        let mut settings = context.data().automod.entry(guild_id.get()).or_default();
+        settings.allowed_domains.push(domain.clone());
+    }
+
+    let reply = messages::info_reply(format!("Links to {domain} are now allowed."), false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Stops allowing links to a domain.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn remove_domain(
+    context: Context<'_>,
+    #[description = "The domain to stop allowing."]
+    #[max_length = 100]
+    domain: String,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let domain = normalize_domain(&domain);
+
+    if !delete_automod_domain(&guild_id, &domain, &context.data().sqlite).await? {
+        let reply = messages::error_reply(format!("{domain} isn't allowed."), true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    if let Some(mut settings) = context.data().automod.get_mut(&guild_id.get()) {
+        settings
+            .allowed_domains
+            .retain(|allowed| allowed != &domain);
+    }
+
+    let reply = messages::info_reply(format!("Links to {domain} are no longer allowed."), false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Strips the scheme, path and `www.` from a domain, and lowercases it.
+fn normalize_domain(domain: &str) -> String {
+    let domain = domain.trim().to_lowercase();
+    let domain = domain.split("://").last().unwrap_or_default();
+    let domain = domain.split('/').next().unwrap_or_default();
+
+    domain.trim_start_matches("www.").to_string()
+}
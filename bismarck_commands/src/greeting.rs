@@ -0,0 +1,184 @@
+use bismarck_core::{
+    context::Context,
+    error::{BismarckError, Error},
+};
+use bismarck_utilities::{
+    greeting::{delete_greeting, render, select_greeting, upsert_greeting, Greeting, GreetingKind},
+    messages,
+};
+use poise::{serenity_prelude as serenity, CreateReply};
+use serenity::{CreateEmbed, GuildChannel, Mentionable, Timestamp};
+use tracing::info;
+
+/// Shows the welcome and goodbye messages of the server.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    subcommands("greeting_set", "greeting_remove", "greeting_test")
+)]
+pub async fn greeting(context: Context<'_>) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let database = &context.data().sqlite;
+
+    let mut embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title("Greetings")
+        .footer(serenity::CreateEmbedFooter::new(
+            "Placeholders: {user} {mention} {guild} {member_count} {account_age}",
+        ));
+
+    for kind in [GreetingKind::Welcome, GreetingKind::Goodbye] {
+        let value = match select_greeting(&guild_id, kind, database).await? {
+            Some(greeting) => {
+                let channel = match greeting.channel_id {
+                    Some(channel_id) => channel_id.mention().to_string(),
+                    None => "No channel".to_string(),
+                };
+
+                let mut options = vec![channel];
+                if greeting.embed {
+                    options.push("embed".to_string());
+                }
+                if greeting.dm {
+                    options.push("DM".to_string());
+                }
+
+                format!("{}\n```{}```", options.join(", "), greeting.template)
+            }
+            None => "Not set".to_string(),
+        };
+
+        let name = match kind {
+            GreetingKind::Welcome => "Welcome",
+            GreetingKind::Goodbye => "Goodbye",
+        };
+
+        embed = embed.field(name, value, false);
+    }
+
+    context.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Sets the welcome or goodbye message.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "set",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn greeting_set(
+    context: Context<'_>,
+    #[description = "Which message to set."] kind: GreetingKind,
+    #[description = "The message, which may use {user} {mention} {guild} {member_count} {account_age}."]
+    #[max_length = 1000]
+    template: String,
+    #[description = "The channel to post to."] channel: Option<GuildChannel>,
+    #[description = "Whether to send the message as an embed."] embed: Option<bool>,
+    #[description = "Whether to also send the message by DM."] dm: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let greeting = Greeting {
+        channel_id: channel.map(|channel| channel.id),
+        template,
+        embed: embed.unwrap_or(false),
+        dm: dm.unwrap_or(false),
+    };
+
+    if greeting.channel_id.is_none() && !greeting.dm {
+        let reply = messages::error_reply("Please provide a channel, or send it by DM.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    upsert_greeting(&guild_id, kind, &greeting, &context.data().sqlite).await?;
+
+    info!("{} message set for guild {guild_id}", kind.as_str());
+
+    let reply = messages::info_reply(format!("The {} message is now set.", kind.as_str()), false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Removes the welcome or goodbye message.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "remove",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn greeting_remove(
+    context: Context<'_>,
+    #[description = "Which message to remove."] kind: GreetingKind,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let reply = if delete_greeting(&guild_id, kind, &context.data().sqlite).await? {
+        messages::info_reply(format!("The {} message is removed.", kind.as_str()), false)
+    } else {
+        messages::error_reply(
+            format!("There's no {} message to remove.", kind.as_str()),
+            true,
+        )
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Previews the welcome or goodbye message with yourself as the member.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "test",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    ephemeral
+)]
+pub async fn greeting_test(
+    context: Context<'_>,
+    #[description = "Which message to preview."] kind: GreetingKind,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let Some(greeting) = select_greeting(&guild_id, kind, &context.data().sqlite).await? else {
+        let reply =
+            messages::error_reply(format!("There's no {} message set.", kind.as_str()), true);
+        context.send(reply).await?;
+        return Ok(());
+    };
+
+    let (guild_name, member_count) = {
+        let guild = context
+            .guild()
+            .ok_or_else(|| BismarckError::NotFound("this server in the cache".to_string()))?;
+        (guild.name.clone(), guild.member_count)
+    };
+
+    let content = render(
+        &greeting.template,
+        context.author(),
+        &guild_name,
+        member_count,
+        Timestamp::now(),
+    );
+
+    context.send(messages::info_reply(content, true)).await?;
+
+    Ok(())
+}
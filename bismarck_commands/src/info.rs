@@ -1,11 +1,27 @@
 use std::sync::atomic::Ordering;
 
-use bismarck_core::{context::Context, error::Error};
-use bismarck_utilities::git::{get_current_branch, get_head_revision, get_absolute_path};
+use bismarck_core::{
+    context::Context,
+    counters::GLOBAL_ID,
+    error::{BismarckError, Error},
+};
+use bismarck_utilities::{
+    chart::{bar_chart, Bar},
+    git::{get_absolute_path, get_current_branch, get_head_revision},
+    messages,
+    usage::select_command_stats,
+};
+use chrono::Utc;
 use git2::Repository;
 use poise::{serenity_prelude as serenity, CreateReply};
 
-use serenity::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, PremiumType};
+use serenity::{CreateAttachment, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, PremiumType};
+
+/// How far back command stats go, in days.
+const STATS_DAYS: i64 = 30;
+
+/// Most commands shown in command stats.
+const TOP_COMMANDS: usize = 10;
 
 /// Returns bot information.
 #[poise::command(
@@ -16,7 +32,8 @@ use serenity::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, PremiumType};
     category = "Info"
 )]
 pub async fn about(context: Context<'_>) -> Result<(), Error> {
-    let repo = Repository::open(get_absolute_path())?;
+    let repo = Repository::open(get_absolute_path())
+        .map_err(|why| BismarckError::Internal(format!("Couldn't open the repository: {why}")))?;
 
     let version = env!("CARGO_PKG_VERSION").to_string();
     let codename = "Graf Zeppelin".to_string();
@@ -26,14 +43,13 @@ pub async fn about(context: Context<'_>) -> Result<(), Error> {
     let current_user = context.cache().current_user().clone();
 
     let bot_name = &current_user.name;
-    let bot_avatar = &current_user.avatar_url().unwrap();
+    let bot_avatar = &current_user.face();
     let bot_owner = context
         .http()
         .get_current_application_info()
         .await?
         .owner
-        .unwrap()
-        .tag();
+        .map_or_else(|| "Unknown".to_string(), |owner| owner.tag());
 
     let num_shards = context.cache().shard_count();
     let num_guilds = context.cache().guilds().len();
@@ -93,24 +109,36 @@ pub async fn user_info(
         PremiumType::NitroBasic => "Nitro Basic",
         _ => "Unrecognized Premium Type",
     };
-    let guild = context.guild().unwrap().clone();
-    let member = guild.member(&context.http(), user_id).await.unwrap();
 
-    let embed = CreateEmbed::new()
+    let mut embed = CreateEmbed::new()
         .author(CreateEmbedAuthor::new(user_name.clone()).icon_url(user_avatar))
-        .thumbnail(guild.icon_url().unwrap().to_string())
         .description(format!("Showing information about user {user_name}"))
         .field("ID", user_id.to_string(), true)
         .field("Created at", user_created.to_string(), true)
         .field("Is bot", user_bot.to_string(), true)
-        .field(
-            "Joined server at",
-            member.joined_at.unwrap().to_string(),
-            true,
-        )
         .field("Nitro Subscription", user_nitro, true)
         .footer(CreateEmbedFooter::new(format!("User ID: {user_id}")));
 
+    // Server details are left out in DMs
+    if let Some(guild_id) = context.guild_id() {
+        let icon_url = context
+            .guild()
+            .ok_or_else(|| BismarckError::NotFound(format!("Guild {guild_id} in the cache")))?
+            .icon_url();
+
+        if let Some(icon_url) = icon_url {
+            embed = embed.thumbnail(icon_url);
+        }
+
+        // The user may not be in the server
+        if let Ok(member) = guild_id.member(context, user_id).await {
+            let joined_at = member
+                .joined_at
+                .map_or_else(|| "Unknown".to_string(), |joined_at| joined_at.to_string());
+            embed = embed.field("Joined server at", joined_at, true);
+        }
+    }
+
     let msg = CreateReply::default().embed(embed);
 
     let _ = context.send(msg).await;
@@ -125,7 +153,7 @@ pub async fn user_info(
     slash_command,
     category = "Info",
     required_bot_permissions = "SEND_MESSAGES",
-    aliases("av"),
+    aliases("av")
 )]
 pub async fn user_avatars(
     context: Context<'_>,
@@ -137,14 +165,16 @@ pub async fn user_avatars(
     };
     let user_id = user.id;
     let user_name = user.tag();
-    let guild = context.guild().unwrap().clone();
-    let guild_avatar = guild
-        .member(&context.http(), user_id)
-        .await
-        .unwrap()
-        .clone()
-        .avatar_url()
-        .unwrap_or("".to_string());
+    // Only members of a server can have a server avatar
+    let guild_avatar = match context.guild_id() {
+        Some(guild_id) => guild_id
+            .member(context, user_id)
+            .await
+            .ok()
+            .and_then(|member| member.avatar_url())
+            .unwrap_or_default(),
+        None => String::new(),
+    };
 
     if !guild_avatar.is_empty() {
         let embed = CreateEmbed::new()
@@ -179,27 +209,68 @@ pub async fn user_avatars(
     prefix_command,
     category = "Info",
     required_bot_permissions = "SEND_MESSAGES",
-    aliases("bs", "stats"),
+    aliases("bs")
 )]
 pub async fn bot_stat(context: Context<'_>) -> Result<(), Error> {
-    let guild = context.guild().unwrap().id;
-    let guildid = guild.get();
-
     let data = context.data();
+    let guild_id = context.guild_id();
+
+    if guild_id.is_none() && !is_owner(context) {
+        let reply = messages::error_reply("Bot stats are only available in guilds.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
 
-    let commands_ran = data.commands_ran.get(&guildid).unwrap();
-    let songs_played = data.songs_played.get(&guildid).unwrap();
+    let commands_ran = data
+        .commands_ran
+        .get(&guild_id.map_or(GLOBAL_ID, |guild_id| guild_id.get()))
+        .map_or(0, |count| count.load(Ordering::Relaxed));
+    let songs_played = guild_id
+        .and_then(|guild_id| data.songs_played.get(&guild_id.get()))
+        .map_or(0, |count| count.load(Ordering::Relaxed));
+
+    let since = Utc::now().timestamp() - STATS_DAYS * 24 * 60 * 60;
+    let stats = select_command_stats(
+        guild_id.as_ref(),
+        since,
+        &data.command_counters,
+        &data.sqlite,
+    )
+    .await?;
+
+    let uses = stats.iter().map(|stats| stats.uses).sum::<u64>();
+    let failures = stats.iter().map(|stats| stats.failures).sum::<u64>();
+    let error_rate = if uses == 0 {
+        0.0
+    } else {
+        failures as f64 / uses as f64 * 100.0
+    };
+    let most_used = stats
+        .first()
+        .map_or("None".to_string(), |stats| format!("`{}`", stats.command));
+
+    let scope = if guild_id.is_some() {
+        "Guild"
+    } else {
+        "Global"
+    };
 
     let embed = CreateEmbed::new()
         .title("**Stats**")
         .field(
-            "Commands Ran (Guild)",
-            commands_ran.load(Ordering::Relaxed).to_string(),
+            format!("Commands Ran ({scope})"),
+            commands_ran.to_string(),
             true,
         )
         .field(
-            "Songs Played (Guild)",
-            songs_played.load(Ordering::Relaxed).to_string(),
+            format!("Songs Played ({scope})"),
+            songs_played.to_string(),
+            true,
+        )
+        .field(format!("Most Used ({STATS_DAYS}d)"), most_used, true)
+        .field(
+            format!("Error Rate ({STATS_DAYS}d)"),
+            format!("{error_rate:.1}%"),
             true,
         )
         .footer(CreateEmbedFooter::new(format!(
@@ -213,3 +284,134 @@ pub async fn bot_stat(context: Context<'_>) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Shows how the bot's commands are used.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Info",
+    required_bot_permissions = "SEND_MESSAGES | ATTACH_FILES",
+    subcommands("stats_commands")
+)]
+pub async fn stats(context: Context<'_>) -> Result<(), Error> {
+    command_stats(context, false).await
+}
+
+/// Shows the most used commands, their error rates and latency.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "commands",
+    category = "Info",
+    required_bot_permissions = "SEND_MESSAGES | ATTACH_FILES"
+)]
+pub async fn stats_commands(
+    context: Context<'_>,
+    #[description = "Show stats across every guild (owners only)."] global: Option<bool>,
+) -> Result<(), Error> {
+    command_stats(context, global.unwrap_or_default()).await
+}
+
+/// Whether the author is one of the bot's owners, who may see stats across every guild.
+fn is_owner(context: Context<'_>) -> bool {
+    context
+        .framework()
+        .options()
+        .owners
+        .contains(&context.author().id)
+}
+
+async fn command_stats(context: Context<'_>, global: bool) -> Result<(), Error> {
+    let data = context.data();
+    let is_owner = is_owner(context);
+
+    if global && !is_owner {
+        let reply = messages::error_reply("Only the bot's owners can see global stats.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let guild_id = if global { None } else { context.guild_id() };
+
+    if guild_id.is_none() && !is_owner {
+        let reply = messages::error_reply("Command stats are only available in guilds.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    context.defer().await?;
+
+    let since = Utc::now().timestamp() - STATS_DAYS * 24 * 60 * 60;
+    let mut stats = select_command_stats(
+        guild_id.as_ref(),
+        since,
+        &data.command_counters,
+        &data.sqlite,
+    )
+    .await?;
+
+    if stats.is_empty() {
+        let reply = messages::info_reply(
+            format!("No commands have been ran in the last {STATS_DAYS} days."),
+            true,
+        );
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let uses = stats.iter().map(|stats| stats.uses).sum::<u64>();
+    let failures = stats.iter().map(|stats| stats.failures).sum::<u64>();
+    stats.truncate(TOP_COMMANDS);
+
+    let lines = stats
+        .iter()
+        .map(|stats| {
+            format!(
+                "`{}`: {} use(s), {:.1}% errors, p50 {}ms, p95 {}ms",
+                stats.command,
+                stats.uses,
+                stats.error_rate(),
+                stats.p50,
+                stats.p95
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let bars = stats
+        .iter()
+        .map(|stats| Bar {
+            label: stats.command.clone(),
+            value: stats.uses,
+            highlighted: stats.failures,
+        })
+        .collect::<Vec<_>>();
+    let chart = bar_chart(&bars)
+        .map_err(|why| BismarckError::Internal(format!("Couldn't draw the chart: {why}")))?;
+    let chart = CreateAttachment::bytes(chart, "commands.png");
+
+    let scope = if guild_id.is_some() {
+        "Guild"
+    } else {
+        "Global"
+    };
+
+    let embed = CreateEmbed::new()
+        .title(format!("**Command Stats ({scope}, {STATS_DAYS}d)**"))
+        .color(0x008b_0000)
+        .description(lines)
+        .field("Commands Ran", uses.to_string(), true)
+        .field(
+            "Error Rate",
+            format!("{:.1}%", failures as f64 / uses as f64 * 100.0),
+            true,
+        )
+        .image("attachment://commands.png")
+        .footer(CreateEmbedFooter::new("Failed runs are highlighted."));
+
+    context
+        .send(CreateReply::default().embed(embed).attachment(chart))
+        .await?;
+
+    Ok(())
+}
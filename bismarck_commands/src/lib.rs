@@ -1,8 +1,16 @@
+pub mod automod;
+pub mod greeting;
 pub mod info;
 pub mod moderation;
 pub mod neko;
 pub mod owner;
+pub mod raid;
+pub mod reminder;
+pub mod role_menu;
+pub mod roles;
 pub mod setup;
+pub mod starboard;
+pub mod ticket;
 pub mod utilities;
 pub mod wiki;
 pub mod wish;
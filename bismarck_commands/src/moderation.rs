@@ -1,14 +1,37 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
-use bismarck_core::{context::Context, error::Error};
-use bismarck_utilities::{embeds::warnings_command_embed, messages, models, modlog::*, paginate};
+use bismarck_core::{
+    context::Context,
+    error::{BismarckError, Error},
+};
+use bismarck_utilities::{
+    embeds::warnings_command_embed, hierarchy, lockdown::*, messages, models, modlog::*, paginate,
+};
 
 use chrono::{Days, NaiveDateTime, Utc};
 use duration_str::parse;
-use poise::serenity_prelude::UserId;
+use poise::serenity_prelude::{
+    self as serenity, ButtonStyle, ChannelType, ComponentInteractionCollector, CreateActionRow,
+    CreateButton, EditChannel, GetMessages, GuildChannel, PermissionOverwrite,
+    PermissionOverwriteType, Permissions, RoleId, StatusCode, UserId,
+};
+use regex::Regex;
 use serenity::model::Timestamp;
 use tracing::{error, info};
 
+/// Maximum number of users a single mass ban may target.
+const MASSBAN_LIMIT: usize = 200;
+/// How often, in bans, the mass ban progress message is updated.
+const MASSBAN_PROGRESS_INTERVAL: usize = 10;
+/// Maximum number of messages scanned by a single purge.
+const PURGE_SCAN_LIMIT: usize = 1000;
+/// Permissions denied to @everyone while a channel is locked.
+const LOCKED_PERMISSIONS: Permissions =
+    Permissions::SEND_MESSAGES.union(Permissions::SEND_MESSAGES_IN_THREADS);
+/// Longest slowmode delay Discord allows.
+const MAX_SLOWMODE: Duration = Duration::from_secs(6 * 60 * 60);
+
 /// Bans a user.
 #[poise::command(
     prefix_command,
@@ -28,106 +51,11 @@ pub async fn ban(
     #[max_length = 80]
     reason: Option<String>,
 ) -> Result<(), Error> {
-    let database = &context.data().sqlite;
-
-    let user = models::user(context, user_id).await?;
-
-    let member = models::member(context, context.guild_id().unwrap(), user_id).await?;
-
-    let ensure = ensure_user(&member, &user_id, &context.guild_id().unwrap(), database).await;
-
-    if let Err(why) = ensure {
-        let _ = messages::error_response(why.to_string(), true).await;
-        return Ok(());
-    }
-
-    let moderator = context.author();
-    let moderator_id = moderator.id;
-
-    if user.system {
-        let reply = messages::error_reply("Cannot ban a system user.", false);
-        context.send(reply).await?;
-        return Ok(());
-    }
-
-    if user_id == moderator_id {
-        let reply = messages::error_reply("Sorry, but you cannot ban yourself.", true);
-        context.send(reply).await?;
-
-        return Ok(());
-    }
-
-    let reason = reason.unwrap_or_else(|| "No reason provided.".to_string());
-
-    let reason_char_count = reason.chars().count();
-    if reason_char_count > 80 {
-        let reply = messages::info_reply("Reason must be no more than 80 characters long.", true);
-        context.send(reply).await?;
-
-        return Ok(());
-    }
-
-    let result = {
-        let (user_name, user_mention) = (&user.name, models::user_mention(context, user_id).await?);
-
-        let (moderator_name, moderator_mention) =
-            (&moderator.name, models::author_mention(context)?);
-
-        let (guild_id, guild_name) = {
-            let guild_id = context.guild_id().unwrap();
-            let guild = context.guild().unwrap();
-            (guild_id, guild.name.clone())
-        };
-
-        let created_at = Utc::now().naive_utc();
-
-        let mut user_mod_history = select_modlog_from_users(&user_id, database).await?;
-
-        let message = messages::info_message(format!(
-            "You've been banned from {guild_name} by {moderator_mention} for {reason}.",
-        ));
-        let dm = user.direct_message(context, message).await;
-
-        if let Err(why) = dm {
-            error!("Couldn't send DM to @{user_name}: {why:?}");
-        }
-
-        match guild_id.ban_with_reason(context, user_id, 0, &reason).await {
-            Ok(_) => {
-                insert_modlog(
-                    ModType::Ban,
-                    &guild_id,
-                    &user_id,
-                    &moderator_id,
-                    &reason,
-                    created_at,
-                    database,
-                )
-                .await?;
-
-                user_mod_history += 1;
-
-                update_users_set_modlog(&user_id, user_mod_history, database).await?;
-
-                info!("@{moderator_name} banned @{user_name} from {guild_name}: {reason}");
-                Ok(format!("{user_mention} has been banned."))
-            }
-            Err(why) => {
-                error!("Couldn't ban @{user_name}: {why:?}");
-                Err(format!("Sorry, but I couldn't ban {user_mention}."))
-            }
-        }
+    let action = Ban {
+        delete_message_days: 0,
     };
 
-    if let Err(why) = result {
-        let reply = messages::error_reply(&why, true);
-        context.send(reply).await?;
-    } else {
-        let reply = messages::info_reply(result.unwrap(), true);
-        context.send(reply).await?;
-    }
-
-    Ok(())
+    moderate(context, &action, user_id, reason).await
 }
 
 /// Kicks a user.
@@ -149,107 +77,7 @@ pub async fn kick(
     #[max_length = 80]
     reason: Option<String>,
 ) -> Result<(), Error> {
-    let database = &context.data().sqlite;
-
-    let user = models::user(context, user_id).await?;
-
-    let member = models::member(context, context.guild_id().unwrap(), user_id).await?;
-
-    let ensure = ensure_user(&member, &user_id, &context.guild_id().unwrap(), database).await;
-
-    if let Err(why) = ensure {
-        let _ = messages::error_response(why.to_string(), true).await;
-        return Ok(());
-    }
-
-    let moderator = context.author();
-    let moderator_id = moderator.id;
-
-    if user.system {
-        let reply = messages::error_reply("Cannot kick a system user.", false);
-        context.send(reply).await?;
-        return Ok(());
-    }
-
-    if user_id == moderator_id {
-        let reply = messages::error_reply("Sorry, but you cannot kick yourself.", true);
-        context.send(reply).await?;
-
-        return Ok(());
-    }
-
-    let reason = reason.unwrap_or_else(|| "No reason provided.".to_string());
-
-    let reason_char_count = reason.chars().count();
-    if reason_char_count > 80 {
-        let reply = messages::info_reply("Reason must be no more than 80 characters long.", true);
-        context.send(reply).await?;
-
-        return Ok(());
-    }
-
-    let result = {
-        let (user_name, user_mention) = (&user.name, models::user_mention(context, user_id).await?);
-
-        let (moderator_name, moderator_mention) =
-            (&moderator.name, models::author_mention(context)?);
-
-        let (guild_id, guild_name) = {
-            let guild_id = context.guild_id().unwrap();
-            let guild = context.guild().unwrap();
-            (guild_id, guild.name.clone())
-        };
-
-        let created_at = Utc::now().naive_utc();
-
-        let mut user_mod_history = select_modlog_from_users(&user_id, database).await?;
-
-        let message = messages::info_message(format!(
-            "You've been kicked from {guild_name} by {moderator_mention} for {reason}.",
-        ));
-        let dm = user.direct_message(context, message).await;
-
-        if let Err(why) = dm {
-            error!("Couldn't send DM to @{user_name}: {why:?}");
-        }
-
-        match guild_id.kick_with_reason(context, user_id, &reason).await {
-            Ok(_) => {
-                insert_modlog(
-                    ModType::Kick,
-                    &guild_id,
-                    &user_id,
-                    &moderator_id,
-                    &reason,
-                    created_at,
-                    database,
-                )
-                .await?;
-
-                user_mod_history += 1;
-
-                update_users_set_modlog(&user_id, user_mod_history, database).await?;
-
-                info!("@{moderator_name} kicked @{user_name} from {guild_name}: {reason}");
-                Ok(format!("{user_mention} has been kicked."))
-            }
-            Err(why) => {
-                error!("Couldn't kick @{user_name}: {why:?}");
-                Err(format!("Sorry, but I couldn't kick {user_mention}."))
-            }
-        }
-    };
-
-    if let Err(why) = result {
-        let reply = messages::error_reply(&why, true);
-        context.send(reply).await?;
-    } else {
-        let reply = messages::info_reply(result.unwrap(), true);
-
-        context.send(reply).await?;
-    }
-
-    Ok(())
+    moderate(context, &Kick, user_id, reason).await
 }
 
 /// Unbans a user.
@@ -271,97 +99,7 @@ pub async fn unban(
     #[max_length = 80]
     reason: Option<String>,
 ) -> Result<(), Error> {
-    let database = &context.data().sqlite;
-
-    let user = models::user(context, user_id).await?;
-
-    let member = models::member(context, context.guild_id().unwrap(), user_id).await?;
-
-    let ensure = ensure_user(&member, &user_id, &context.guild_id().unwrap(), database).await;
-
-    if let Err(why) = ensure {
-        let _ = messages::error_response(why.to_string(), true).await;
-        return Ok(());
-    }
-
-    let moderator = context.author();
-    let moderator_id = moderator.id;
-
-    if user.system {
-        let reply = messages::error_reply("Cannot unban a system user.", false);
-        context.send(reply).await?;
-        return Ok(());
-    }
-
-    if user_id == moderator_id {
-        let reply = messages::error_reply("Sorry, but you cannot unban yourself.", true);
-        context.send(reply).await?;
-
-        return Ok(());
-    }
-
-    let reason = reason.unwrap_or_else(|| "No reason provided.".to_string());
-
-    let reason_char_count = reason.chars().count();
-    if reason_char_count > 80 {
-        let reply = messages::info_reply("Reason must be no more than 80 characters long.", true);
-        context.send(reply).await?;
-
-        return Ok(());
-    }
-
-    let result = {
-        let (user_name, user_mention) = (&user.name, models::user_mention(context, user_id).await?);
-
-        let moderator_name = &moderator.name;
-
-        let (guild_id, guild_name) = {
-            let guild_id = context.guild_id().unwrap();
-            let guild = context.guild().unwrap();
-            (guild_id, guild.name.clone())
-        };
-
-        let created_at = Utc::now().naive_utc();
-
-        let mut user_mod_history = select_modlog_from_users(&user_id, database).await?;
-
-        match guild_id.unban(context, user_id).await {
-            Ok(_) => {
-                insert_modlog(
-                    ModType::Unban,
-                    &guild_id,
-                    &user_id,
-                    &moderator_id,
-                    &reason,
-                    created_at,
-                    database,
-                )
-                .await?;
-
-                user_mod_history += 1;
-
-                update_users_set_modlog(&user_id, user_mod_history, database).await?;
-
-                info!("@{moderator_name} unbanned @{user_name} from {guild_name}: {reason}");
-                Ok(format!("{user_mention} has been unbanned."))
-            }
-            Err(why) => {
-                error!("Couldn't unban @{user_name}: {why:?}");
-                Err(format!("Sorry, but I couldn't unban {user_mention}."))
-            }
-        }
-    };
-
-    if let Err(why) = result {
-        let reply = messages::error_reply(&why, true);
-        context.send(reply).await?;
-    } else {
-        let reply = messages::info_reply(result.unwrap(), true);
-
-        context.send(reply).await?;
-    }
-
-    Ok(())
+    moderate(context, &Unban, user_id, reason).await
 }
 
 /// Times out a user.
@@ -384,45 +122,6 @@ pub async fn timeout(
     #[max_length = 80]
     reason: Option<String>,
 ) -> Result<(), Error> {
-    let database = &context.data().sqlite;
-
-    let user = models::user(context, user_id).await?;
-
-    let moderator = context.author();
-    let moderator_id = moderator.id;
-
-    if user.system {
-        let reply = messages::error_reply("Cannot timeout a system user.", false);
-        context.send(reply).await?;
-        return Ok(());
-    }
-
-    if user_id == moderator_id {
-        let reply = messages::error_reply("Sorry, but you cannot timeout yourself.", true);
-        context.send(reply).await?;
-
-        return Ok(());
-    }
-
-    let member = models::member(context, context.guild_id().unwrap(), user_id).await?;
-
-    let ensure = ensure_user(&member, &user_id, &context.guild_id().unwrap(), database).await;
-
-    if let Err(why) = ensure {
-        let _ = messages::error_response(why.to_string(), true).await;
-        return Ok(());
-    }
-
-    let reason = reason.unwrap_or_else(|| "No reason provided.".to_string());
-
-    let reason_char_count = reason.chars().count();
-    if reason_char_count > 80 {
-        let reply = messages::info_reply("Reason must be no more than 80 characters long.", true);
-        context.send(reply).await?;
-
-        return Ok(());
-    }
-
     let duration = match parse(&duration) {
         Ok(duration) => duration,
         Err(why) => {
@@ -446,49 +145,153 @@ pub async fn timeout(
         return Ok(());
     }
 
-    let result = {
-        let (user_name, user_mention) = (&user.name, models::user_mention(context, user_id).await?);
-
-        let mut member = models::member(context, context.guild_id().unwrap(), user_id).await?;
-
-        let moderator_name = &moderator.name;
-
-        let (guild_id, guild_name) = {
-            let guild_id = context.guild_id().unwrap();
-            let guild = context.guild().unwrap();
-            (guild_id, guild.name.clone())
-        };
-
-        let created_at = Utc::now().naive_utc();
+    moderate(context, &Timeout { until: time }, user_id, reason).await
+}
 
-        let mut user_mod_history = select_modlog_from_users(&user_id, database).await?;
+/// Un-times out a user.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MODERATE_MEMBERS",
+    required_bot_permissions = "MODERATE_MEMBERS | SEND_MESSAGES",
+    guild_only,
+    ephemeral
+)]
+pub async fn untimeout(
+    context: Context<'_>,
+    #[description = "The user to untimeout."]
+    #[rename = "user"]
+    user_id: UserId,
+    #[description = "Reason for the untimeout."]
+    #[max_length = 80]
+    reason: Option<String>,
+) -> Result<(), Error> {
+    moderate(context, &Untimeout, user_id, reason).await
+}
 
-        match member
-            .disable_communication_until_datetime(context, time)
-            .await
-        {
-            Ok(_) => {
-                insert_modlog(
-                    ModType::Timeout,
-                    &guild_id,
-                    &user_id,
-                    &moderator_id,
-                    &reason,
-                    created_at,
-                    database,
-                )
-                .await?;
+/// Warns a user.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MODERATE_MEMBERS",
+    required_bot_permissions = "MODERATE_MEMBERS | SEND_MESSAGES",
+    guild_only,
+    ephemeral
+)]
+pub async fn warn(
+    context: Context<'_>,
+    #[description = "The user to warn."]
+    #[rename = "user"]
+    user_id: UserId,
+    #[description = "The reason for the warning."]
+    #[max_length = 80]
+    reason: String,
+) -> Result<(), Error> {
+    moderate(context, &Warn, user_id, Some(reason)).await
+}
+
+/// Gets a user's warnings.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MODERATE_MEMBERS",
+    required_bot_permissions = "MODERATE_MEMBERS | SEND_MESSAGES",
+    guild_only
+)]
+pub async fn warnings(
+    context: Context<'_>,
+    #[description = "The user to get warnings for."]
+    #[rename = "user"]
+    user_id: UserId,
+) -> Result<(), Error> {
+    let database = &context.data().sqlite;
+
+    let user = models::user(context, user_id).await?;
+
+    if user.system {
+        let reply = messages::error_reply("Cannot get warnings for a system user.", false);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let result = {
+        let (user_name, user_mention) = (&user.name, models::user_mention(context, user_id).await?);
+
+        let guild_id = context.guild_id().unwrap();
+
+        let user_mod_history =
+            match select_modlog(ModType::Warn, &user_id, &guild_id, database).await {
+                Ok(user_mod_history) => user_mod_history,
+                Err(why) => {
+                    error!("Couldn't select warnings from infractions: {why:?}");
+                    return Err(why.into());
+                }
+            };
 
-                user_mod_history += 1;
+        let warning_count = user_mod_history.len();
+        if warning_count < 1 {
+            let reply =
+                messages::info_reply(format!("{user_mention} doesn't have any warnings."), false);
+            context.send(reply).await?;
 
-                update_users_set_modlog(&user_id, user_mod_history, database).await?;
+            return Ok(());
+        }
 
-                info!("@{moderator_name} timed out @{user_name} from {guild_name}: {reason}");
-                Ok(format!("{user_mention} has been timed out."))
+        let (uuids, moderator_ids, reasons, created_ats) = (
+            user_mod_history
+                .iter()
+                .map(|(uuid, _, _, _, _, _, _)| uuid)
+                .collect::<Vec<&String>>(),
+            user_mod_history
+                .iter()
+                .map(|(_, _, _, moderator_id, _, _, _)| moderator_id)
+                .collect::<Vec<&i64>>(),
+            user_mod_history
+                .iter()
+                .map(|(_, _, _, _, reason, _, _)| reason)
+                .collect::<Vec<&String>>(),
+            user_mod_history
+                .iter()
+                .map(|(_, _, _, _, _, created_at, _)| created_at)
+                .collect::<Vec<&NaiveDateTime>>(),
+        );
+
+        // TODO: Add pagination
+        let uuids_iter = uuids.chunks(25);
+        let mod_ids_iter = moderator_ids.chunks(25);
+        let reasons_iter = reasons.chunks(25);
+        let created_ats_iter = created_ats.chunks(25);
+
+        // Cycle through the chunks of 25, creating pagination embeds
+        let mut embeds = Vec::new();
+        uuids_iter
+            .zip(mod_ids_iter.zip(reasons_iter.zip(created_ats_iter)))
+            .for_each(|(uuids, (moderator_ids, (reasons, created_ats)))| {
+                embeds.push(warnings_command_embed(
+                    &user,
+                    uuids,
+                    moderator_ids,
+                    reasons,
+                    created_ats,
+                ));
+            });
+
+        let infractions = select_infractions(&user_id, &guild_id, database).await?;
+
+        match paginate::paginate(context, embeds).await {
+            Ok(_) => {
+                let author = context.author().id;
+                info!("@{author} requested @{user_name}'s warnings");
+                Ok(format!(
+                    "{user_mention} has {warning_count} warning(s) and {infractions} infraction(s) in total."
+                ))
             }
             Err(why) => {
-                error!("Couldn't timeout @{user_name}: {why:?}");
-                Err(format!("Sorry, but I couldn't timeout {user_mention}."))
+                error!("Failed to paginate: {why:?}");
+                Err(why.to_string())
             }
         }
     };
@@ -505,304 +308,851 @@ pub async fn timeout(
     Ok(())
 }
 
-/// Un-times out a user.
+/// Bans multiple users at once.
 #[poise::command(
     prefix_command,
     slash_command,
     category = "Moderator",
-    required_permissions = "MODERATE_MEMBERS",
-    required_bot_permissions = "MODERATE_MEMBERS | SEND_MESSAGES",
+    required_permissions = "BAN_MEMBERS",
+    required_bot_permissions = "BAN_MEMBERS | SEND_MESSAGES",
     guild_only,
     ephemeral
 )]
-pub async fn untimeout(
+pub async fn massban(
     context: Context<'_>,
-    #[description = "The user to untimeout."]
-    #[rename = "user"]
-    user_id: UserId,
+    #[description = "User IDs or mentions to ban, separated by spaces or commas."] users: Option<
+        String,
+    >,
+    #[description = "Ban every member who joined in the last N minutes."]
+    #[rename = "joined_within"]
+    #[min = 1]
+    #[max = 10080]
+    joined_minutes: Option<u64>,
+    #[description = "Reason for the ban."]
+    #[max_length = 80]
+    reason: Option<String>,
 ) -> Result<(), Error> {
     let database = &context.data().sqlite;
 
-    let user = models::user(context, user_id).await?;
+    let guild_id = context.guild_id().unwrap();
+    let moderator_id = context.author().id;
 
-    if user.system {
-        let reply = messages::error_reply("Cannot untimeout a system user.", false);
+    if users.is_none() && joined_minutes.is_none() {
+        let reply = messages::error_reply(
+            "Please provide a list of users, a join window, or both.",
+            true,
+        );
         context.send(reply).await?;
         return Ok(());
     }
 
-    let member = models::member(context, context.guild_id().unwrap(), user_id).await?;
+    let reason = reason.unwrap_or_else(|| "No reason provided.".to_string());
 
-    let ensure = ensure_user(&member, &user_id, &context.guild_id().unwrap(), database).await;
+    if reason.chars().count() > 80 {
+        let reply = messages::info_reply("Reason must be no more than 80 characters long.", true);
+        context.send(reply).await?;
 
-    if let Err(why) = ensure {
-        let _ = messages::error_response(why.to_string(), true).await;
         return Ok(());
     }
 
-    let result = {
-        let (user_name, user_mention) = (&user.name, models::user_mention(context, user_id).await?);
+    let mut targets = Vec::new();
+
+    if let Some(users) = users {
+        for token in users
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+        {
+            let id = token.trim_start_matches("<@").trim_start_matches('!');
+            let id = id.trim_end_matches('>');
+
+            match id.parse::<u64>() {
+                Ok(id) if id != 0 => targets.push(UserId::new(id)),
+                _ => {
+                    let reply =
+                        messages::error_reply(format!("`{token}` is not a valid user ID."), true);
+                    context.send(reply).await?;
+                    return Ok(());
+                }
+            }
+        }
+    }
 
-        let mut member = models::member(context, context.guild_id().unwrap(), user_id).await?;
+    if let Some(minutes) = joined_minutes {
+        let cutoff = Utc::now() - Duration::from_secs(minutes * 60);
 
-        let moderator_id = context.author().id;
+        let mut after = None;
+        loop {
+            let members = guild_id.members(context, Some(1000), after).await?;
+            let fetched = members.len();
 
-        let (guild_id, guild_name) = {
-            let guild_id = context.guild_id().unwrap();
-            let guild = context.guild().unwrap();
-            (guild_id, guild.name.clone())
-        };
+            for member in &members {
+                if member
+                    .joined_at
+                    .is_some_and(|joined_at| *joined_at >= cutoff)
+                {
+                    targets.push(member.user.id);
+                }
+            }
+
+            after = members.last().map(|member| member.user.id);
+
+            if fetched < 1000 {
+                break;
+            }
+        }
+    }
+
+    let (owner_id, bot_id) = {
+        let guild = context
+            .guild()
+            .ok_or_else(|| BismarckError::NotFound(format!("Guild {guild_id} in the cache")))?;
+        (guild.owner_id, context.cache().current_user().id)
+    };
+
+    targets.sort_unstable();
+    targets.dedup();
+    targets
+        .retain(|user_id| *user_id != moderator_id && *user_id != owner_id && *user_id != bot_id);
+
+    let outranked = match hierarchy::retain_outranked(context, &mut targets).await {
+        Ok(outranked) => outranked,
+        Err(why) => {
+            let reply = messages::error_reply(why.message("ban"), true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    if targets.is_empty() {
+        let reply = messages::info_reply("No users matched the given criteria.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    if targets.len() > MASSBAN_LIMIT {
+        let reply = messages::error_reply(
+            format!(
+                "{} users matched, but I can only ban up to {MASSBAN_LIMIT} at once.",
+                targets.len()
+            ),
+            true,
+        );
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let total = targets.len();
+
+    let ctx_id = context.id();
+    let confirm_button_id = format!("{ctx_id}confirm");
+    let cancel_button_id = format!("{ctx_id}cancel");
+
+    let components = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(&confirm_button_id)
+            .label("Ban")
+            .style(ButtonStyle::Danger),
+        CreateButton::new(&cancel_button_id)
+            .label("Cancel")
+            .style(ButtonStyle::Secondary),
+    ])];
+
+    let handle = context
+        .send(
+            messages::info_reply(
+                format!("You are about to ban {total} user(s) for: {reason}\nAre you sure?"),
+                true,
+            )
+            .components(components),
+        )
+        .await?;
+
+    let press = ComponentInteractionCollector::new(context)
+        .author_id(moderator_id)
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+        .timeout(Duration::from_secs(60))
+        .await;
+
+    let press = match press {
+        Some(press) if press.data.custom_id == confirm_button_id => press,
+        Some(press) => {
+            press.defer(context.http()).await?;
+
+            let reply = messages::info_reply("Mass ban cancelled.", true).components(vec![]);
+            handle.edit(context, reply).await?;
+            return Ok(());
+        }
+        None => {
+            let reply = messages::info_reply("Mass ban timed out.", true).components(vec![]);
+            handle.edit(context, reply).await?;
+            return Ok(());
+        }
+    };
 
-        let created_at = Utc::now().naive_utc();
+    press.defer(context.http()).await?;
 
-        let mut user_mod_history = select_modlog_from_users(&user_id, database).await?;
+    let created_at = Utc::now().naive_utc();
 
-        match member.enable_communication(context).await {
+    let mut banned = 0;
+    let mut failed = Vec::new();
+    let mut unlogged = 0;
+
+    for (index, user_id) in targets.iter().enumerate() {
+        match guild_id
+            .ban_with_reason(context, *user_id, 0, &reason)
+            .await
+        {
             Ok(_) => {
-                insert_modlog(
-                    ModType::Untimeout,
+                banned += 1;
+
+                if let Err(why) = insert_infraction(
+                    ModType::Ban,
                     &guild_id,
-                    &user_id,
+                    user_id,
                     &moderator_id,
-                    "",
+                    &reason,
                     created_at,
                     database,
                 )
-                .await?;
-
-                user_mod_history += 1;
-
-                update_users_set_modlog(&user_id, user_mod_history, database).await?;
-
-                info!("@{moderator_id} untimed out @{user_name} from {guild_name}");
-                Ok(format!("{user_mention} has been untimed out."))
+                .await
+                {
+                    error!("Couldn't log ban of {user_id} during mass ban: {why:?}");
+                    unlogged += 1;
+                }
             }
             Err(why) => {
-                error!("Couldn't untimeout @{user_name}: {why:?}");
-                Err(format!("Sorry, but I couldn't untimeout {user_mention}."))
+                error!("Couldn't ban {user_id} during mass ban: {why:?}");
+                failed.push(*user_id);
             }
         }
-    };
 
-    if let Err(why) = result {
-        let reply = messages::error_reply(&why, true);
-        context.send(reply).await?;
-    } else {
-        let reply = messages::info_reply(result.unwrap(), true);
+        let done = index + 1;
+        if done % MASSBAN_PROGRESS_INTERVAL == 0 && done < total {
+            let reply = messages::info_reply(format!("Banning users... ({done}/{total})"), true)
+                .components(vec![]);
+            handle.edit(context, reply).await?;
+        }
+    }
 
-        context.send(reply).await?;
+    info!(
+        "@{} mass banned {banned}/{total} user(s) from {guild_id}: {reason}",
+        context.author().name
+    );
+
+    let mut summary = format!("Banned {banned} of {total} user(s).");
+    if outranked > 0 {
+        summary.push_str(&format!(
+            "\nSkipped {outranked} user(s) with a role equal to or above yours or mine, or whose membership I couldn't verify."
+        ));
+    }
+    if !failed.is_empty() {
+        let failed = failed
+            .iter()
+            .take(10)
+            .map(|user_id| format!("<@{user_id}>"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        summary.push_str(&format!("\nCouldn't ban: {failed}"));
+    }
+    if unlogged > 0 {
+        summary.push_str(&format!(
+            "\nCouldn't log {unlogged} ban(s) to the moderation logs."
+        ));
     }
 
+    let reply = messages::info_reply(summary, true).components(vec![]);
+    handle.edit(context, reply).await?;
+
     Ok(())
 }
 
-/// Warns a user.
+/// Bulk deletes recent messages in the current channel.
 #[poise::command(
     prefix_command,
     slash_command,
     category = "Moderator",
-    required_permissions = "MODERATE_MEMBERS",
-    required_bot_permissions = "MODERATE_MEMBERS | SEND_MESSAGES",
+    required_permissions = "MANAGE_MESSAGES",
+    required_bot_permissions = "MANAGE_MESSAGES | READ_MESSAGE_HISTORY | SEND_MESSAGES",
     guild_only,
     ephemeral
 )]
-pub async fn warn(
+pub async fn purge(
     context: Context<'_>,
-    #[description = "The user to warn."]
+    #[description = "Number of messages to delete."]
+    #[min = 1]
+    #[max = 500]
+    count: u16,
+    #[description = "Only delete messages sent by this user."]
     #[rename = "user"]
-    user_id: UserId,
-    #[description = "The reason for the warning."] reason: String,
+    user_id: Option<UserId>,
+    #[description = "Only delete messages matching this regular expression."] pattern: Option<
+        String,
+    >,
+    #[description = "Only delete messages sent by bots."] bots: Option<bool>,
+    #[description = "Only delete messages with attachments."] attachments: Option<bool>,
 ) -> Result<(), Error> {
     let database = &context.data().sqlite;
 
-    let user = models::user(context, user_id).await?;
+    let guild_id = context.guild_id().unwrap();
+    let channel_id = context.channel_id();
+    let moderator_id = context.author().id;
+
+    let bots = bots.unwrap_or(false);
+    let attachments = attachments.unwrap_or(false);
+
+    let pattern = match pattern.as_deref().map(Regex::new).transpose() {
+        Ok(pattern) => pattern,
+        Err(why) => {
+            let reply = messages::error_reply(format!("Invalid pattern: {why}"), true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
 
-    let member = models::member(context, context.guild_id().unwrap(), user_id).await?;
+    // Skip the invoking message of prefix commands so it doesn't count towards the total.
+    let invocation_id = match context {
+        poise::Context::Prefix(prefix) => Some(prefix.msg.id),
+        poise::Context::Application(_) => None,
+    };
 
-    let ensure = ensure_user(&member, &user_id, &context.guild_id().unwrap(), database).await;
+    // Discord refuses to bulk delete messages older than 14 days.
+    let cutoff = Utc::now().timestamp() - 14 * 24 * 60 * 60;
 
-    if let Err(why) = ensure {
-        let _ = messages::error_response(why.to_string(), true).await;
-        return Ok(());
+    let count = count as usize;
+    // Along with their authors, to log only what was actually deleted.
+    let mut to_delete = Vec::new();
+    let mut scanned = 0;
+    let mut before = None;
+
+    'scan: while to_delete.len() < count && scanned < PURGE_SCAN_LIMIT {
+        let mut request = GetMessages::new().limit(100);
+        if let Some(before) = before {
+            request = request.before(before);
+        }
+
+        let batch = channel_id.messages(context, request).await?;
+        if batch.is_empty() {
+            break;
+        }
+
+        scanned += batch.len();
+        before = batch.last().map(|message| message.id);
+
+        for message in batch {
+            if message.timestamp.unix_timestamp() < cutoff {
+                break 'scan;
+            }
+
+            if Some(message.id) == invocation_id
+                || user_id.is_some_and(|user_id| message.author.id != user_id)
+                || (bots && !message.author.bot)
+                || (attachments && message.attachments.is_empty())
+                || pattern
+                    .as_ref()
+                    .is_some_and(|pattern| !pattern.is_match(&message.content))
+            {
+                continue;
+            }
+
+            to_delete.push((message.id, message.author.id));
+
+            if to_delete.len() >= count {
+                break 'scan;
+            }
+        }
     }
 
-    if user.system {
-        let reply = messages::error_reply("Cannot warn a system user.", false);
+    if to_delete.is_empty() {
+        let reply = messages::info_reply("No messages matched the given filters.", true);
         context.send(reply).await?;
         return Ok(());
     }
 
-    let result = {
-        let (user_name, user_mention) = (&user.name, models::user_mention(context, user_id).await?);
+    let mut affected: HashMap<UserId, usize> = HashMap::new();
+    let mut deleted = 0;
 
-        let moderator_id = context.author().id;
+    // Messages deleted before a chunk fails are still logged.
+    for chunk in to_delete.chunks(100) {
+        let message_ids = chunk
+            .iter()
+            .map(|(message_id, _)| *message_id)
+            .collect::<Vec<_>>();
 
-        let (guild_id, guild_name) = {
-            let guild_id = context.guild_id().unwrap();
-            let guild = context.guild().unwrap();
-            (guild_id, guild.name.clone())
-        };
+        if let Err(why) = channel_id.delete_messages(context, &message_ids).await {
+            error!("Couldn't purge messages in {channel_id}: {why:?}");
+            break;
+        }
 
-        let created_at = Utc::now().naive_utc();
+        for (_, author_id) in chunk {
+            *affected.entry(*author_id).or_default() += 1;
+        }
+        deleted += chunk.len();
+    }
 
-        let mut user_mod_history = select_modlog_from_users(&user_id, database).await?;
+    if deleted == 0 {
+        let reply = messages::error_reply("Sorry, but I couldn't delete those messages.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let created_at = Utc::now().naive_utc();
 
-        match insert_modlog(
-            ModType::Warn,
+    for (author_id, amount) in &affected {
+        insert_modlog(
+            ModType::Purge,
             &guild_id,
-            &user_id,
+            author_id,
             &moderator_id,
-            &reason,
+            &format!("{amount} message(s) purged from <#{channel_id}>."),
             created_at,
             database,
         )
-        .await
-        {
-            Ok(_) => {
-                user_mod_history += 1;
-
-                update_users_set_modlog(&user_id, user_mod_history, database).await?;
+        .await?;
+    }
 
-                info!("@{moderator_id} warned @{user_name} from {guild_name}");
-                Ok(format!("{user_mention} has been warned."))
-            }
+    let mut filters = Vec::new();
+    if let Some(user_id) = user_id {
+        filters.push(format!("user: <@{user_id}>"));
+    }
+    if let Some(pattern) = &pattern {
+        filters.push(format!("pattern: `{pattern}`"));
+    }
+    if bots {
+        filters.push("bots only".to_string());
+    }
+    if attachments {
+        filters.push("attachments only".to_string());
+    }
 
-            Err(why) => {
-                error!("Couldn't warn @{user_name}: {why:?}");
-                Err(format!("Sorry, but I couldn't warn {user_mention}."))
-            }
-        }
+    let summary = if filters.is_empty() {
+        format!("Purged {deleted} message(s) in <#{channel_id}>.")
+    } else {
+        format!(
+            "Purged {deleted} message(s) in <#{channel_id}> ({}).",
+            filters.join(", ")
+        )
     };
 
-    if let Err(why) = result {
-        let reply = messages::error_reply(&why, true);
-        context.send(reply).await?;
+    insert_modlog_summary(
+        ModType::Purge,
+        &guild_id,
+        &moderator_id,
+        &summary,
+        created_at,
+        database,
+    )
+    .await?;
+
+    info!(
+        "@{} purged {deleted} message(s) from {} user(s) in {channel_id}",
+        context.author().name,
+        affected.len()
+    );
+
+    let reply = if deleted < to_delete.len() {
+        messages::error_reply(
+            format!(
+                "{summary}\nSorry, but I couldn't delete the other {} message(s).",
+                to_delete.len() - deleted
+            ),
+            true,
+        )
     } else {
-        let reply = messages::info_reply(result.unwrap(), true);
+        messages::info_reply(summary, true)
+    };
+    context.send(reply).await?;
 
-        context.send(reply).await?;
+    Ok(())
+}
+
+/// Which channels a lockdown or unlock applies to.
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum LockdownScope {
+    #[name = "channel"]
+    Channel,
+    #[name = "category"]
+    Category,
+    #[name = "server"]
+    Server,
+}
+
+impl LockdownScope {
+    fn as_str(&self) -> &str {
+        match self {
+            LockdownScope::Channel => "channel",
+            LockdownScope::Category => "category",
+            LockdownScope::Server => "server",
+        }
     }
+}
 
-    Ok(())
+/// Resolves the channels a lockdown scope covers, starting from the given channel.
+fn scoped_channels(
+    context: Context<'_>,
+    scope: LockdownScope,
+    channel: Option<GuildChannel>,
+) -> Result<Vec<GuildChannel>, String> {
+    let guild = context
+        .guild()
+        .ok_or("This command can only be used in a server.")?;
+
+    let anchor = match channel {
+        Some(channel) if channel.guild_id == guild.id => channel,
+        Some(_) => return Err("That channel is not in this server.".to_string()),
+        None => guild
+            .channels
+            .get(&context.channel_id())
+            .cloned()
+            .ok_or("Please specify a channel.")?,
+    };
+
+    let lockable = |channel: &GuildChannel| {
+        matches!(
+            channel.kind,
+            ChannelType::Text | ChannelType::News | ChannelType::Forum | ChannelType::Voice
+        )
+    };
+
+    let channels = match scope {
+        LockdownScope::Channel => {
+            if !lockable(&anchor) {
+                return Err(format!("<#{}> cannot be locked.", anchor.id));
+            }
+
+            vec![anchor]
+        }
+        LockdownScope::Category => {
+            let category_id = if anchor.kind == ChannelType::Category {
+                anchor.id
+            } else {
+                anchor
+                    .parent_id
+                    .ok_or(format!("<#{}> is not in a category.", anchor.id))?
+            };
+
+            guild
+                .channels
+                .values()
+                .filter(|channel| channel.parent_id == Some(category_id) && lockable(channel))
+                .cloned()
+                .collect()
+        }
+        LockdownScope::Server => guild
+            .channels
+            .values()
+            .filter(|channel| lockable(channel))
+            .cloned()
+            .collect(),
+    };
+
+    Ok(channels)
 }
 
-/// Gets a user's warnings.
+/// Stops @everyone from sending messages in a channel, category or the whole server.
 #[poise::command(
     prefix_command,
     slash_command,
     category = "Moderator",
-    required_permissions = "MODERATE_MEMBERS",
-    required_bot_permissions = "MODERATE_MEMBERS | SEND_MESSAGES",
-    guild_only
+    required_permissions = "MANAGE_CHANNELS",
+    required_bot_permissions = "MANAGE_CHANNELS | MANAGE_ROLES | SEND_MESSAGES",
+    guild_only,
+    ephemeral
 )]
-pub async fn warnings(
+pub async fn lockdown(
     context: Context<'_>,
-    #[description = "The user to get warnings for."]
-    #[rename = "user"]
-    user_id: UserId,
+    #[description = "What to lock. Defaults to the channel."] scope: Option<LockdownScope>,
+    #[description = "The channel (or a channel in the category) to lock."] channel: Option<
+        GuildChannel,
+    >,
+    #[description = "Reason for the lockdown."]
+    #[max_length = 80]
+    reason: Option<String>,
 ) -> Result<(), Error> {
     let database = &context.data().sqlite;
 
-    let user = models::user(context, user_id).await?;
+    let guild_id = context.guild_id().unwrap();
+    let moderator_id = context.author().id;
 
-    let member = models::member(context, context.guild_id().unwrap(), user_id).await?;
+    let scope = scope.unwrap_or(LockdownScope::Channel);
+    let reason = reason.unwrap_or_else(|| "No reason provided.".to_string());
 
-    let ensure = ensure_user(&member, &user_id, &context.guild_id().unwrap(), database).await;
+    let channels = match scoped_channels(context, scope, channel) {
+        Ok(channels) => channels,
+        Err(why) => {
+            let reply = messages::error_reply(why, true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
 
-    if let Err(why) = ensure {
-        let _ = messages::error_response(why.to_string(), true).await;
-        return Ok(());
+    let everyone = PermissionOverwriteType::Role(RoleId::new(guild_id.get()));
+
+    let (mut locked, mut already_locked, mut failed) = (0, 0, 0);
+
+    for channel in &channels {
+        let previous = channel
+            .permission_overwrites
+            .iter()
+            .find(|overwrite| overwrite.kind == everyone);
+
+        if !insert_lockdown(&guild_id, &channel.id, previous, database).await? {
+            already_locked += 1;
+            continue;
+        }
+
+        let (allow, deny) = previous
+            .map_or((Permissions::empty(), Permissions::empty()), |overwrite| {
+                (overwrite.allow, overwrite.deny)
+            });
+
+        let overwrite = PermissionOverwrite {
+            allow: allow - LOCKED_PERMISSIONS,
+            deny: deny | LOCKED_PERMISSIONS,
+            kind: everyone,
+        };
+
+        if let Err(why) = channel.id.create_permission(context, overwrite).await {
+            error!("Couldn't lock {}: {why:?}", channel.id);
+            delete_lockdown(&guild_id, &channel.id, database).await?;
+            failed += 1;
+        } else {
+            locked += 1;
+        }
     }
 
-    if user.system {
-        let reply = messages::error_reply("Cannot get warnings for a system user.", false);
-        context.send(reply).await?;
-        return Ok(());
+    let mut summary = format!("Locked {locked} channel(s).");
+    if already_locked > 0 {
+        summary.push_str(&format!(
+            " {already_locked} channel(s) were already locked."
+        ));
+    }
+    if failed > 0 {
+        summary.push_str(&format!(" Couldn't lock {failed} channel(s)."));
     }
 
-    let result = {
-        let (user_name, user_mention) = (&user.name, models::user_mention(context, user_id).await?);
+    if locked > 0 {
+        insert_modlog_summary(
+            ModType::Lockdown,
+            &guild_id,
+            &moderator_id,
+            &format!("Locked {locked} channel(s) ({}): {reason}", scope.as_str()),
+            Utc::now().naive_utc(),
+            database,
+        )
+        .await?;
 
-        let guild_id = context.guild_id().unwrap();
+        info!(
+            "@{} locked down {locked} channel(s) in {guild_id}: {reason}",
+            context.author().name
+        );
+    }
 
-        let user_mod_history =
-            match select_modlog(ModType::Warn, &user_id, &guild_id, database).await {
-                Ok(user_mod_history) => user_mod_history,
+    let reply = messages::info_reply(summary, true);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Restores the permissions of channels locked with lockdown.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_CHANNELS",
+    required_bot_permissions = "MANAGE_CHANNELS | MANAGE_ROLES | SEND_MESSAGES",
+    guild_only,
+    ephemeral
+)]
+pub async fn unlock(
+    context: Context<'_>,
+    #[description = "What to unlock. Defaults to the channel."] scope: Option<LockdownScope>,
+    #[description = "The channel (or a channel in the category) to unlock."] channel: Option<
+        GuildChannel,
+    >,
+) -> Result<(), Error> {
+    let database = &context.data().sqlite;
+
+    let guild_id = context.guild_id().unwrap();
+    let moderator_id = context.author().id;
+
+    let scope = scope.unwrap_or(LockdownScope::Channel);
+
+    let saved = select_lockdowns(&guild_id, database).await?;
+
+    let saved = match scope {
+        // Channels deleted since the lockdown are no longer in the cache, so restore everything.
+        LockdownScope::Server => saved,
+        _ => {
+            let channels = match scoped_channels(context, scope, channel) {
+                Ok(channels) => channels,
                 Err(why) => {
-                    error!("Couldn't select warnings from infractions: {why:?}");
-                    return Err(why.into());
+                    let reply = messages::error_reply(why, true);
+                    context.send(reply).await?;
+                    return Ok(());
                 }
             };
 
-        let warning_count = user_mod_history.len();
-        if warning_count < 1 {
-            let reply =
-                messages::info_reply(format!("{user_mention} doesn't have any warnings."), false);
-            context.send(reply).await?;
-
-            return Ok(());
+            saved
+                .into_iter()
+                .filter(|saved| {
+                    channels
+                        .iter()
+                        .any(|channel| channel.id == saved.channel_id)
+                })
+                .collect()
         }
+    };
 
-        let (uuids, moderator_ids, reasons, created_ats) = (
-            user_mod_history
-                .iter()
-                .map(|(uuid, _, _, _, _, _, _)| uuid)
-                .collect::<Vec<&String>>(),
-            user_mod_history
-                .iter()
-                .map(|(_, _, _, moderator_id, _, _, _)| moderator_id)
-                .collect::<Vec<&i64>>(),
-            user_mod_history
-                .iter()
-                .map(|(_, _, _, _, reason, _, _)| reason)
-                .collect::<Vec<&String>>(),
-            user_mod_history
-                .iter()
-                .map(|(_, _, _, _, _, created_at, _)| created_at)
-                .collect::<Vec<&NaiveDateTime>>(),
-        );
+    if saved.is_empty() {
+        let reply = messages::info_reply("There are no locked channels to unlock.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
 
-        // TODO: Add pagination
-        let uuids_iter = uuids.chunks(25);
-        let mod_ids_iter = moderator_ids.chunks(25);
-        let reasons_iter = reasons.chunks(25);
-        let created_ats_iter = created_ats.chunks(25);
+    let everyone = PermissionOverwriteType::Role(RoleId::new(guild_id.get()));
 
-        // Cycle through the chunks of 25, creating pagination embeds
-        let mut embeds = Vec::new();
-        uuids_iter
-            .zip(mod_ids_iter.zip(reasons_iter.zip(created_ats_iter)))
-            .for_each(|(uuids, (moderator_ids, (reasons, created_ats)))| {
-                embeds.push(warnings_command_embed(
-                    &user,
-                    uuids,
-                    moderator_ids,
-                    reasons,
-                    created_ats,
-                ));
-            });
+    let (mut unlocked, mut failed) = (0, 0);
 
-        match paginate::paginate(context, embeds).await {
+    for saved in &saved {
+        let result = match saved.overwrite {
+            Some((allow, deny)) => {
+                let overwrite = PermissionOverwrite {
+                    allow,
+                    deny,
+                    kind: everyone,
+                };
+                saved.channel_id.create_permission(context, overwrite).await
+            }
+            None => saved.channel_id.delete_permission(context, everyone).await,
+        };
+
+        match result {
             Ok(_) => {
-                let author = context.author().id;
-                info!("@{author} requested @{user_name}'s warnings");
-                Ok(format!("{user_mention} has {warning_count} warning(s)."))
+                delete_lockdown(&guild_id, &saved.channel_id, database).await?;
+                unlocked += 1;
+            }
+            Err(serenity::Error::Http(why)) if why.status_code() == Some(StatusCode::NOT_FOUND) => {
+                // The channel no longer exists, so there is nothing left to restore.
+                delete_lockdown(&guild_id, &saved.channel_id, database).await?;
             }
             Err(why) => {
-                error!("Failed to paginate: {why:?}");
-                Err(why.to_string())
+                error!("Couldn't unlock {}: {why:?}", saved.channel_id);
+                failed += 1;
             }
         }
+    }
+
+    let mut summary = format!("Unlocked {unlocked} channel(s).");
+    if failed > 0 {
+        summary.push_str(&format!(" Couldn't unlock {failed} channel(s)."));
+    }
+
+    if unlocked > 0 {
+        insert_modlog_summary(
+            ModType::Unlock,
+            &guild_id,
+            &moderator_id,
+            &format!("Unlocked {unlocked} channel(s) ({}).", scope.as_str()),
+            Utc::now().naive_utc(),
+            database,
+        )
+        .await?;
+
+        info!(
+            "@{} unlocked {unlocked} channel(s) in {guild_id}",
+            context.author().name
+        );
+    }
+
+    let reply = messages::info_reply(summary, true);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets the slowmode delay of a channel.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_CHANNELS",
+    required_bot_permissions = "MANAGE_CHANNELS | SEND_MESSAGES",
+    guild_only,
+    ephemeral
+)]
+pub async fn slowmode(
+    context: Context<'_>,
+    #[description = "Delay between messages, such as `30s` or `2m`. Use `0` to disable."]
+    duration: String,
+    #[description = "The channel to change. Defaults to this channel."] channel: Option<
+        GuildChannel,
+    >,
+) -> Result<(), Error> {
+    let database = &context.data().sqlite;
+
+    let guild_id = context.guild_id().unwrap();
+    let moderator_id = context.author().id;
+
+    let channel_id = match channel {
+        Some(channel) if channel.guild_id == guild_id => channel.id,
+        Some(_) => {
+            let reply = messages::error_reply("That channel is not in this server.", true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+        None => context.channel_id(),
     };
 
-    if let Err(why) = result {
-        let reply = messages::error_reply(&why, true);
+    let duration = match duration.trim() {
+        "0" | "off" => Duration::ZERO,
+        duration => match parse(duration) {
+            Ok(duration) => duration,
+            Err(why) => {
+                let reply = messages::error_reply(why.to_string(), true);
+                context.send(reply).await?;
+                return Ok(());
+            }
+        },
+    };
+
+    if duration > MAX_SLOWMODE {
+        let reply = messages::error_reply("Slowmode cannot be longer than 6 hours.", true);
         context.send(reply).await?;
-    } else {
-        let reply = messages::info_reply(result.unwrap(), true);
+        return Ok(());
+    }
+
+    let seconds = duration.as_secs() as u16;
 
+    let builder = EditChannel::new().rate_limit_per_user(seconds);
+    if let Err(why) = channel_id.edit(context, builder).await {
+        error!("Couldn't set slowmode in {channel_id}: {why:?}");
+        let reply = messages::error_reply(
+            format!("Sorry, but I couldn't change the slowmode of <#{channel_id}>."),
+            true,
+        );
         context.send(reply).await?;
+        return Ok(());
     }
 
+    let summary = if seconds == 0 {
+        format!("Disabled slowmode in <#{channel_id}>.")
+    } else {
+        format!("Set slowmode in <#{channel_id}> to {seconds} second(s).")
+    };
+
+    insert_modlog_summary(
+        ModType::Slowmode,
+        &guild_id,
+        &moderator_id,
+        &summary,
+        Utc::now().naive_utc(),
+        database,
+    )
+    .await?;
+
+    info!("@{} changed slowmode: {summary}", context.author().name);
+
+    let reply = messages::info_reply(summary, true);
+    context.send(reply).await?;
+
     Ok(())
 }
@@ -1,7 +1,12 @@
 use poise::CreateReply;
 use serenity::all::{CreateEmbed, CreateEmbedFooter};
 
-use bismarck_core::{context::Context, error::Error, types::Items};
+use bismarck_core::{
+    context::Context,
+    error::{BismarckError, Error},
+    metrics,
+    types::Items,
+};
 
 /// Sends a random Neko image.
 #[poise::command(
@@ -16,14 +21,15 @@ pub async fn neko(context: Context<'_>) -> Result<(), Error> {
 
     let params = [("rating", "safe"), ("limit", "1")];
 
-    let url = reqwest::Url::parse_with_params("https://api.nekosapi.com/v3/images/random", params)
-        .unwrap();
+    let url = reqwest::Url::parse_with_params(&context.data().config.api.nekos, params)
+        .map_err(|why| BismarckError::Internal(format!("Invalid nekosapi URL: {why}")))?;
 
     let res = request.get(url).send().await;
 
     let res = match res {
         Ok(res) => res,
         Err(_) => {
+            metrics::http_failure("nekosapi");
             context.reply("Failed to get image.").await?;
             return Ok(());
         }
@@ -36,6 +42,7 @@ pub async fn neko(context: Context<'_>) -> Result<(), Error> {
     let data = match data {
         Ok(data) => data,
         Err(_) => {
+            metrics::http_failure("nekosapi");
             context.reply("Failed to get image.").await?;
             return Ok(());
         }
@@ -4,7 +4,7 @@ use poise;
 /// Shuts down the bot gracefully
 #[poise::command(prefix_command, owners_only, hide_in_help)]
 pub async fn shutdown(ctx: Context<'_>) -> Result<(), Error> {
-    ctx.framework().shard_manager().shutdown_all().await;
+    ctx.data().shutdown.trigger();
     Ok(())
 }
 
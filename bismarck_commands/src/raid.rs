@@ -0,0 +1,385 @@
+use std::time::Duration;
+
+use bismarck_core::{
+    context::Context,
+    error::Error,
+    types::{RaidAction, RaidSettings},
+};
+use bismarck_utilities::{
+    messages,
+    raid::{end_raid, mod_log_channel, start_raid, upsert_raid_settings},
+};
+use duration_str::parse;
+use poise::{ChoiceParameter, CreateReply};
+use serenity::all::CreateEmbed;
+use tracing::info;
+
+/// Longest timeout Discord allows, which raid mode may last at most.
+const MAX_COOLDOWN: Duration = Duration::from_secs(28 * 24 * 60 * 60);
+
+/// Shows the anti-raid settings of the server.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    subcommands(
+        "raid_enable",
+        "raid_disable",
+        "raid_joins",
+        "raid_new_accounts",
+        "raid_action",
+        "raid_cooldown",
+        "raid_start",
+        "raid_end"
+    )
+)]
+pub async fn raid(context: Context<'_>) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let settings = context
+        .data()
+        .raid
+        .get(&guild_id.get())
+        .map(|settings| settings.clone())
+        .unwrap_or_default();
+
+    let status = match settings.raid_until {
+        Some(until) => format!("On until <t:{}:t>", until.unix_timestamp()),
+        None => "Off".to_string(),
+    };
+
+    let limit = |limit: u32, description: String| {
+        if limit == 0 {
+            "Off".to_string()
+        } else {
+            description
+        }
+    };
+
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title("Anti-raid")
+        .field("Enabled", if settings.enabled { "Yes" } else { "No" }, true)
+        .field("Raid mode", status, true)
+        .field("Action", settings.action.name(), true)
+        .field(
+            "Joins",
+            limit(
+                settings.join_limit,
+                format!("{} per {}s", settings.join_limit, settings.join_window),
+            ),
+            true,
+        )
+        .field(
+            "New accounts",
+            limit(
+                settings.new_account_limit,
+                format!(
+                    "{} per {}s, younger than {}h",
+                    settings.new_account_limit,
+                    settings.join_window,
+                    settings.new_account_age / 3600
+                ),
+            ),
+            true,
+        )
+        .field("Cooldown", format!("{}s", settings.cooldown), true);
+
+    context.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Updates the anti-raid settings of the guild in memory and in the database.
+async fn update_settings(
+    context: Context<'_>,
+    update: impl FnOnce(&mut RaidSettings),
+) -> Result<RaidSettings, Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let settings = {
+        let mut settings = context.data().raid.entry(guild_id.get()).or_default();
+        update(&mut settings);
+        settings.clone()
+    };
+
+    upsert_raid_settings(&guild_id, &settings, &context.data().sqlite).await?;
+
+    Ok(settings)
+}
+
+/// Turns join monitoring on.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "enable",
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "KICK_MEMBERS | MODERATE_MEMBERS | MANAGE_GUILD | SEND_MESSAGES",
+    guild_only
+)]
+pub async fn raid_enable(context: Context<'_>) -> Result<(), Error> {
+    update_settings(context, |settings| settings.enabled = true).await?;
+
+    info!("Anti-raid enabled in guild {}", context.guild_id().unwrap());
+
+    let reply = messages::info_reply("Anti-raid is now enabled.", false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Turns join monitoring off. Raid mode stays on until it ends if it already is.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "disable",
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn raid_disable(context: Context<'_>) -> Result<(), Error> {
+    update_settings(context, |settings| settings.enabled = false).await?;
+
+    info!(
+        "Anti-raid disabled in guild {}",
+        context.guild_id().unwrap()
+    );
+
+    let reply = messages::info_reply("Anti-raid is now disabled.", false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets how many members may join within a number of seconds.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "joins",
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn raid_joins(
+    context: Context<'_>,
+    #[description = "Most joins allowed, 0 to turn off."]
+    #[max = 1000]
+    joins: u32,
+    #[description = "Within this many seconds."]
+    #[min = 1]
+    #[max = 3600]
+    seconds: u64,
+) -> Result<(), Error> {
+    if seconds == 0 {
+        let reply = messages::error_reply("Seconds must be at least 1.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    update_settings(context, |settings| {
+        settings.join_limit = joins;
+        settings.join_window = seconds;
+    })
+    .await?;
+
+    let reply = if joins == 0 {
+        messages::info_reply("Join limit turned off.", false)
+    } else {
+        messages::info_reply(
+            format!(
+                "Raid mode will turn on when more than {joins} members join within {seconds}s."
+            ),
+            false,
+        )
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets how many new accounts may join within the join window.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "new_accounts",
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn raid_new_accounts(
+    context: Context<'_>,
+    #[description = "Most new accounts allowed, 0 to turn off."]
+    #[max = 1000]
+    joins: u32,
+    #[description = "Accounts younger than this are new, such as 7d."] age: Option<String>,
+) -> Result<(), Error> {
+    let age = match age.as_deref().map(parse).transpose() {
+        Ok(age) => age,
+        Err(why) => {
+            let reply = messages::error_reply(why.to_string(), true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    let settings = update_settings(context, |settings| {
+        settings.new_account_limit = joins;
+        if let Some(age) = age {
+            settings.new_account_age = age.as_secs();
+        }
+    })
+    .await?;
+
+    let reply = if joins == 0 {
+        messages::info_reply("New account limit turned off.", false)
+    } else {
+        messages::info_reply(
+            format!(
+                "Raid mode will turn on when more than {joins} accounts younger than {}h join within {}s.",
+                settings.new_account_age / 3600,
+                settings.join_window
+            ),
+            false,
+        )
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets what raid mode does with members joining while it is on.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "action",
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn raid_action(
+    context: Context<'_>,
+    #[description = "Members are kicked, or timed out until raid mode ends."] action: RaidAction,
+) -> Result<(), Error> {
+    update_settings(context, |settings| settings.action = action).await?;
+
+    let reply = messages::info_reply(format!("Raid mode action set to {}.", action.name()), false);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets how long raid mode stays on for.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "cooldown",
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn raid_cooldown(
+    context: Context<'_>,
+    #[description = "How long raid mode lasts, such as 15m."] duration: String,
+) -> Result<(), Error> {
+    let duration = match parse(&duration) {
+        Ok(duration) if duration > MAX_COOLDOWN => {
+            let reply = messages::error_reply("Raid mode cannot last longer than 28 days.", true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+        Ok(duration) if duration < Duration::from_secs(60) => {
+            let reply = messages::error_reply("Raid mode must last at least a minute.", true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+        Ok(duration) => duration,
+        Err(why) => {
+            let reply = messages::error_reply(why.to_string(), true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    update_settings(context, |settings| settings.cooldown = duration.as_secs()).await?;
+
+    let reply = messages::info_reply(
+        format!("Raid mode will last {}s.", duration.as_secs()),
+        false,
+    );
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Turns raid mode on until the cooldown passes.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "start",
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "KICK_MEMBERS | MODERATE_MEMBERS | MANAGE_GUILD | SEND_MESSAGES",
+    guild_only
+)]
+pub async fn raid_start(context: Context<'_>) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let reason = format!("Started by {}", context.author().name);
+
+    let reply = if start_raid(
+        context.serenity_context(),
+        context.data(),
+        guild_id,
+        &reason,
+    )
+    .await?
+    {
+        messages::info_reply("Raid mode is now on.", false)
+    } else {
+        messages::error_reply("Raid mode is already on.", true)
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Turns raid mode off before the cooldown passes.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "end",
+    category = "Moderator",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "MANAGE_GUILD | SEND_MESSAGES",
+    guild_only
+)]
+pub async fn raid_end(context: Context<'_>) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let data = context.data();
+
+    let ended = end_raid(
+        &context.serenity_context().http,
+        &data.raid,
+        &data.sqlite,
+        mod_log_channel(data, guild_id),
+        guild_id,
+    )
+    .await?;
+
+    let reply = if ended {
+        messages::info_reply("Raid mode is now off.", false)
+    } else {
+        messages::error_reply("Raid mode is already off.", true)
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
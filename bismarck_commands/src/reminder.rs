@@ -0,0 +1,259 @@
+use bismarck_core::{context::Context, error::Error};
+use bismarck_utilities::{
+    messages,
+    reminder::{
+        delete_user_reminder, insert_reminder, job_key, parse_time, schedule,
+        select_user_reminders, Reminder, MAX_REMINDERS, MIN_REPEAT_INTERVAL,
+    },
+};
+use chrono::{DateTime, Utc};
+use duration_str::parse;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use tracing::info;
+
+/// Longest part of a reminder's message shown in the list of reminders.
+const PREVIEW_LENGTH: usize = 100;
+
+/// Longest description Discord accepts in an embed.
+const DESCRIPTION_LENGTH: usize = 4096;
+
+/// Reminds you of something later, once or repeating at a fixed interval.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Utility",
+    subcommands("remind_in", "remind_at", "remind_list", "remind_cancel")
+)]
+pub async fn remind(context: Context<'_>) -> Result<(), Error> {
+    list_reminders(context).await
+}
+
+/// Checks a repeat interval, such as `1d`, returning it in seconds.
+fn parse_repeat(repeat: &str) -> Result<i64, String> {
+    let interval = parse(repeat).map_err(|why| why.to_string())?;
+
+    if interval < MIN_REPEAT_INTERVAL {
+        return Err("Reminders can repeat at most once a minute.".to_string());
+    }
+
+    Ok(interval.as_secs() as i64)
+}
+
+/// Stores a reminder and confirms it.
+async fn create_reminder(
+    context: Context<'_>,
+    remind_at: DateTime<Utc>,
+    message: String,
+    repeat: Option<String>,
+    dm: bool,
+) -> Result<(), Error> {
+    if remind_at <= Utc::now() {
+        let reply = messages::error_reply("That time has already passed.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let repeat_interval = match repeat.as_deref().map(parse_repeat).transpose() {
+        Ok(repeat_interval) => repeat_interval,
+        Err(why) => {
+            let reply = messages::error_reply(why, true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    let user_id = context.author().id;
+    let pool = &context.data().sqlite;
+
+    if select_user_reminders(&user_id, pool).await?.len() as i64 >= MAX_REMINDERS {
+        let reply = messages::error_reply(
+            format!("You can't have more than {MAX_REMINDERS} reminders."),
+            true,
+        );
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let channel_id = context.channel_id();
+    let channel_id = (!dm && context.guild_id().is_some()).then_some(&channel_id);
+
+    let reminder_id = insert_reminder(
+        &user_id,
+        channel_id,
+        &message,
+        remind_at.timestamp(),
+        repeat_interval,
+        pool,
+    )
+    .await?;
+    schedule(
+        &context.data().scheduler,
+        reminder_id,
+        remind_at.timestamp(),
+    )
+    .await?;
+
+    info!("Reminder {reminder_id} set by {user_id}");
+
+    let mut confirmation = format!(
+        "I'll remind you <t:{0}:R>, on <t:{0}:f>.",
+        remind_at.timestamp()
+    );
+    if let Some(repeat) = repeat {
+        confirmation.push_str(&format!(" It repeats every {repeat}."));
+    }
+
+    let reply = messages::info_reply(confirmation, true);
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Reminds you after some time, such as `2h30m`.
+#[poise::command(prefix_command, slash_command, rename = "in", category = "Utility")]
+pub async fn remind_in(
+    context: Context<'_>,
+    #[description = "How long until the reminder, such as 2h30m."] duration: String,
+    #[description = "What to remind you of."]
+    #[max_length = 1000]
+    message: String,
+    #[description = "Fixed interval to repeat the reminder at, such as 1d. Calendar schedules aren't supported."]
+    repeat: Option<String>,
+    #[description = "Send the reminder in DMs instead of here."] dm: Option<bool>,
+) -> Result<(), Error> {
+    let duration = match parse(&duration) {
+        Ok(duration) => duration,
+        Err(why) => {
+            let reply = messages::error_reply(why.to_string(), true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    let Some(remind_at) = chrono::Duration::from_std(duration)
+        .ok()
+        .and_then(|duration| Utc::now().checked_add_signed(duration))
+    else {
+        let reply = messages::error_reply("That's too far in the future.", true);
+        context.send(reply).await?;
+        return Ok(());
+    };
+
+    create_reminder(context, remind_at, message, repeat, dm.unwrap_or_default()).await
+}
+
+/// Reminds you at a time in UTC, such as `2026-11-01 18:00`.
+#[poise::command(prefix_command, slash_command, rename = "at", category = "Utility")]
+pub async fn remind_at(
+    context: Context<'_>,
+    #[description = "When to remind you, in UTC, such as 2026-11-01 18:00."] time: String,
+    #[description = "What to remind you of."]
+    #[max_length = 1000]
+    message: String,
+    #[description = "Fixed interval to repeat the reminder at, such as 1w. Calendar schedules aren't supported."]
+    repeat: Option<String>,
+    #[description = "Send the reminder in DMs instead of here."] dm: Option<bool>,
+) -> Result<(), Error> {
+    let Some(remind_at) = parse_time(&time) else {
+        let reply = messages::error_reply("Times look like `2026-11-01 18:00`.", true);
+        context.send(reply).await?;
+        return Ok(());
+    };
+
+    create_reminder(context, remind_at, message, repeat, dm.unwrap_or_default()).await
+}
+
+fn reminder_line(reminder: &Reminder) -> String {
+    let mut message = reminder
+        .message
+        .chars()
+        .take(PREVIEW_LENGTH)
+        .collect::<String>();
+    if reminder.message.chars().count() > PREVIEW_LENGTH {
+        message.push('…');
+    }
+
+    let mut line = format!("`{}` <t:{}:R>: {message}", reminder.id, reminder.remind_at);
+
+    if let Some(repeat_interval) = reminder.repeat_interval {
+        line.push_str(&format!(" (every {repeat_interval}s)"));
+    }
+
+    line
+}
+
+/// Lists reminders, one per line, leaving out the ones that don't fit in an embed.
+fn reminder_list(reminders: &[Reminder]) -> String {
+    let mut list = String::new();
+
+    for (index, reminder) in reminders.iter().enumerate() {
+        let line = reminder_line(reminder);
+        let more = format!("\n…and {} more.", reminders.len() - index);
+
+        // Unless it's the last, the line leaves room for noting how many were left out.
+        let reserved = if index + 1 < reminders.len() {
+            more.chars().count()
+        } else {
+            0
+        };
+
+        if list.chars().count() + line.chars().count() + reserved + 1 > DESCRIPTION_LENGTH {
+            list.push_str(&more);
+            break;
+        }
+
+        if !list.is_empty() {
+            list.push('\n');
+        }
+        list.push_str(&line);
+    }
+
+    list
+}
+
+async fn list_reminders(context: Context<'_>) -> Result<(), Error> {
+    let reminders = select_user_reminders(&context.author().id, &context.data().sqlite).await?;
+
+    if reminders.is_empty() {
+        let reply = messages::info_reply("You have no reminders.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title("Reminders")
+        .description(reminder_list(&reminders));
+
+    context
+        .send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Lists your reminders.
+#[poise::command(prefix_command, slash_command, rename = "list", category = "Utility")]
+pub async fn remind_list(context: Context<'_>) -> Result<(), Error> {
+    list_reminders(context).await
+}
+
+/// Cancels one of your reminders.
+#[poise::command(prefix_command, slash_command, rename = "cancel", category = "Utility")]
+pub async fn remind_cancel(
+    context: Context<'_>,
+    #[description = "The reminder's ID, as shown by remind list."] id: i64,
+) -> Result<(), Error> {
+    let data = context.data();
+
+    let reply = if delete_user_reminder(&context.author().id, id, &data.sqlite).await? {
+        data.scheduler.cancel(&job_key(id)).await?;
+        messages::info_reply(format!("Cancelled reminder {id}."), true)
+    } else {
+        messages::error_reply("You have no reminder with that ID.", true)
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
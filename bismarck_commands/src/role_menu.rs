@@ -0,0 +1,361 @@
+use bismarck_core::{context::Context, error::Error};
+use bismarck_utilities::{
+    hierarchy::{self, HierarchyError},
+    messages,
+    role_menu::{
+        delete_menu_option, delete_role_menu, insert_role_menu, publish, select_role_menu,
+        select_role_menus, update_role_menu_message, upsert_menu_option, MenuKind, MenuOption,
+        RoleMenu,
+    },
+    roles::is_assignable,
+};
+use poise::CreateReply;
+use serenity::all::{CreateEmbed, GuildChannel, Mentionable, ReactionType, Role};
+use tracing::{error, info};
+
+/// Shows the role menus of the server.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    subcommands(
+        "rolemenu_create",
+        "rolemenu_add",
+        "rolemenu_remove",
+        "rolemenu_publish",
+        "rolemenu_delete"
+    )
+)]
+pub async fn rolemenu(context: Context<'_>) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let menus = select_role_menus(&guild_id, &context.data().sqlite).await?;
+
+    let description = if menus.is_empty() {
+        "No role menus yet. Create one with `rolemenu create`.".to_string()
+    } else {
+        menus
+            .iter()
+            .map(|menu| {
+                let published = match menu.channel_id {
+                    Some(channel_id) => format!("in {}", channel_id.mention()),
+                    None => "not published".to_string(),
+                };
+
+                format!(
+                    "`{}` **{}**, {}, {published}",
+                    menu.id,
+                    menu.title,
+                    menu.kind.as_str()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title("Role menus")
+        .description(description);
+
+    context.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Fetches a menu of the guild, replying with an error if it doesn't exist.
+async fn find_menu(context: Context<'_>, menu_id: i64) -> Result<Option<RoleMenu>, Error> {
+    let guild_id = context.guild_id().unwrap();
+    let menu = select_role_menu(&guild_id, menu_id, &context.data().sqlite).await?;
+
+    if menu.is_none() {
+        let reply = messages::error_reply(format!("There's no role menu `{menu_id}`."), true);
+        context.send(reply).await?;
+    }
+
+    Ok(menu)
+}
+
+/// Updates the message of a menu that is already published, so it shows its current roles.
+async fn refresh(context: Context<'_>, menu_id: i64) -> Result<(), Error> {
+    let Some(menu) = find_menu(context, menu_id).await? else {
+        return Ok(());
+    };
+
+    let Some(channel_id) = menu.channel_id else {
+        return Ok(());
+    };
+
+    if let Err(why) = publish(context.http(), &menu, channel_id).await {
+        error!("Couldn't refresh role menu {menu_id}: {why:?}");
+    }
+
+    Ok(())
+}
+
+/// Creates a role menu to add roles to and publish.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "create",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn rolemenu_create(
+    context: Context<'_>,
+    #[description = "Whether roles are picked with reactions, buttons or a select menu."]
+    kind: MenuKind,
+    #[description = "The title of the menu."]
+    #[max_length = 256]
+    title: String,
+    #[description = "Most roles a member may pick, 1 for a single choice or 0 for any."]
+    #[max = 25]
+    max_roles: Option<u32>,
+    #[description = "Shown above the roles."]
+    #[max_length = 2000]
+    description: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let menu_id = insert_role_menu(
+        &guild_id,
+        kind,
+        max_roles.unwrap_or(0),
+        &title,
+        description.as_deref(),
+        &context.data().sqlite,
+    )
+    .await?;
+
+    info!("Role menu {menu_id} created in guild {guild_id}");
+
+    let reply = messages::info_reply(
+        format!("Role menu `{menu_id}` created. Add roles with `rolemenu add`, then post it with `rolemenu publish`."),
+        false,
+    );
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Adds a role to a menu, or changes its emoji and label.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "add",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES | SEND_MESSAGES",
+    guild_only
+)]
+pub async fn rolemenu_add(
+    context: Context<'_>,
+    #[description = "The ID of the menu."] menu_id: i64,
+    #[description = "The role to add."] role: Role,
+    #[description = "The emoji to pick it with, required for reaction menus."] emoji: Option<
+        String,
+    >,
+    #[description = "The label of its button or option, the role's name by default."]
+    #[max_length = 80]
+    label: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let Some(menu) = find_menu(context, menu_id).await? else {
+        return Ok(());
+    };
+
+    let emoji = match emoji.map(ReactionType::try_from).transpose() {
+        Ok(emoji) => emoji,
+        Err(_) => {
+            let reply = messages::error_reply("That isn't an emoji.", true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    if menu.kind == MenuKind::Reactions && emoji.is_none() {
+        let reply = messages::error_reply("Reaction menus need an emoji for every role.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let is_new = !menu.options.iter().any(|option| option.role_id == role.id);
+
+    if is_new && menu.options.len() >= menu.kind.max_options() {
+        let reply = messages::error_reply(
+            format!(
+                "Menus using {} can hold at most {} roles.",
+                menu.kind.as_str(),
+                menu.kind.max_options()
+            ),
+            true,
+        );
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    if !is_assignable(context.serenity_context(), guild_id, role.id).await {
+        let reply = messages::error_reply(
+            "Sorry, but I cannot give that role. It must be below my highest role and not managed by an integration.",
+            true,
+        );
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    if let Err(why) = hierarchy::check_role(context, &role).await {
+        let message = match why {
+            HierarchyError::Unavailable => "Sorry, but I couldn't verify your highest role.",
+            _ => "Sorry, but you can only add roles below your highest role.",
+        };
+        let reply = messages::error_reply(message, true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let option = MenuOption {
+        role_id: role.id,
+        emoji,
+        label: label.unwrap_or_else(|| role.name.clone()),
+    };
+
+    upsert_menu_option(menu_id, &option, &context.data().sqlite).await?;
+    refresh(context, menu_id).await?;
+
+    let reply = messages::info_reply(
+        format!("{} is now in role menu `{menu_id}`.", role.mention()),
+        false,
+    );
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Removes a role from a menu.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "remove",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn rolemenu_remove(
+    context: Context<'_>,
+    #[description = "The ID of the menu."] menu_id: i64,
+    #[description = "The role to remove."] role: Role,
+) -> Result<(), Error> {
+    if find_menu(context, menu_id).await?.is_none() {
+        return Ok(());
+    }
+
+    let reply = if delete_menu_option(menu_id, &role.id, &context.data().sqlite).await? {
+        refresh(context, menu_id).await?;
+        messages::info_reply(
+            format!("{} is no longer in role menu `{menu_id}`.", role.mention()),
+            false,
+        )
+    } else {
+        messages::error_reply(
+            format!("{} isn't in role menu `{menu_id}`.", role.mention()),
+            true,
+        )
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Posts a menu to a channel, or updates it if it is already posted there.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "publish",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS | ADD_REACTIONS",
+    guild_only
+)]
+pub async fn rolemenu_publish(
+    context: Context<'_>,
+    #[description = "The ID of the menu."] menu_id: i64,
+    #[description = "The channel to post it to."] channel: GuildChannel,
+) -> Result<(), Error> {
+    let Some(menu) = find_menu(context, menu_id).await? else {
+        return Ok(());
+    };
+
+    if menu.options.is_empty() {
+        let reply = messages::error_reply("Add a role to the menu before publishing it.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let message_id = match publish(context.http(), &menu, channel.id).await {
+        Ok(message_id) => message_id,
+        Err(why) => {
+            error!("Couldn't publish role menu {menu_id}: {why:?}");
+            let reply = messages::error_reply(
+                format!(
+                    "Sorry, but I couldn't post the menu in {}.",
+                    channel.mention()
+                ),
+                true,
+            );
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    update_role_menu_message(menu_id, &channel.id, &message_id, &context.data().sqlite).await?;
+
+    let reply = messages::info_reply(
+        format!("Role menu `{menu_id}` is posted in {}.", channel.mention()),
+        false,
+    );
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Deletes a menu along with its message.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "delete",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn rolemenu_delete(
+    context: Context<'_>,
+    #[description = "The ID of the menu."] menu_id: i64,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let Some(menu) = find_menu(context, menu_id).await? else {
+        return Ok(());
+    };
+
+    if let (Some(channel_id), Some(message_id)) = (menu.channel_id, menu.message_id) {
+        if let Err(why) = channel_id.delete_message(context.http(), message_id).await {
+            error!("Couldn't delete role menu {menu_id} message: {why:?}");
+        }
+    }
+
+    delete_role_menu(&guild_id, menu_id, &context.data().sqlite).await?;
+
+    info!("Role menu {menu_id} deleted in guild {guild_id}");
+
+    let reply = messages::info_reply(format!("Role menu `{menu_id}` deleted."), false);
+    context.send(reply).await?;
+
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use std::time::Duration;
+
+use bismarck_core::{context::Context, error::Error};
+use bismarck_utilities::{
+    hierarchy::{self, HierarchyError},
+    messages,
+    roles::{
+        clear_persisted_roles, delete_autorole, insert_autorole, is_assignable,
+        select_role_settings, upsert_role_config, RoleSettings,
+    },
+};
+use duration_str::parse;
+use poise::CreateReply;
+use serenity::all::{CreateEmbed, Mentionable, Role, RoleId};
+use tracing::info;
+
+/// Longest delay autoroles may be given after.
+const MAX_DELAY: Duration = Duration::from_secs(60 * 60);
+
+fn role_list(role_ids: &[RoleId]) -> String {
+    if role_ids.is_empty() {
+        return "None".to_string();
+    }
+
+    role_ids
+        .iter()
+        .map(|role_id| role_id.mention().to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Shows the roles given to members and bots joining the server.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    subcommands("autorole_add", "autorole_remove", "autorole_delay")
+)]
+pub async fn autorole(context: Context<'_>) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let settings = select_role_settings(&guild_id, &context.data().sqlite).await?;
+
+    let delay = if settings.autorole_delay == 0 {
+        "None".to_string()
+    } else {
+        format!("{}s", settings.autorole_delay)
+    };
+
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title("Autoroles")
+        .field("Members", role_list(&settings.member_roles), false)
+        .field("Bots", role_list(&settings.bot_roles), false)
+        .field("Delay", delay, true)
+        .field(
+            "Persistent roles",
+            if settings.persist_roles { "Yes" } else { "No" },
+            true,
+        );
+
+    context.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Updates the role settings of the guild in the database.
+async fn update_settings(
+    context: Context<'_>,
+    update: impl FnOnce(&mut RoleSettings),
+) -> Result<RoleSettings, Error> {
+    let guild_id = context.guild_id().unwrap();
+    let database = &context.data().sqlite;
+
+    let mut settings = select_role_settings(&guild_id, database).await?;
+    update(&mut settings);
+
+    upsert_role_config(&guild_id, &settings, database).await?;
+
+    Ok(settings)
+}
+
+/// Gives a role to members or bots joining.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "add",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES | SEND_MESSAGES",
+    guild_only
+)]
+pub async fn autorole_add(
+    context: Context<'_>,
+    #[description = "The role to give."] role: Role,
+    #[description = "Whether to give it to bots instead of members."] bots: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let bots = bots.unwrap_or(false);
+
+    if !is_assignable(context.serenity_context(), guild_id, role.id).await {
+        let reply = messages::error_reply(
+            "Sorry, but I cannot give that role. It must be below my highest role and not managed by an integration.",
+            true,
+        );
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    if let Err(why) = hierarchy::check_role(context, &role).await {
+        let message = match why {
+            HierarchyError::Unavailable => "Sorry, but I couldn't verify your highest role.",
+            _ => "Sorry, but you can only add autoroles below your highest role.",
+        };
+        let reply = messages::error_reply(message, true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let reply = if insert_autorole(&guild_id, &role.id, bots, &context.data().sqlite).await? {
+        info!("Autorole {} added in guild {guild_id}", role.id);
+        let joining = if bots { "bots" } else { "members" };
+        messages::info_reply(
+            format!("{} is now given to {joining} joining.", role.mention()),
+            false,
+        )
+    } else {
+        messages::error_reply(format!("{} is already an autorole.", role.mention()), true)
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Stops giving a role to members or bots joining.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "remove",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn autorole_remove(
+    context: Context<'_>,
+    #[description = "The role to stop giving."] role: Role,
+    #[description = "Whether it is given to bots instead of members."] bots: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let bots = bots.unwrap_or(false);
+
+    let reply = if delete_autorole(&guild_id, &role.id, bots, &context.data().sqlite).await? {
+        messages::info_reply(
+            format!("{} is no longer an autorole.", role.mention()),
+            false,
+        )
+    } else {
+        messages::error_reply(format!("{} isn't an autorole.", role.mention()), true)
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets how long after joining autoroles are given.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "delay",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn autorole_delay(
+    context: Context<'_>,
+    #[description = "How long to wait, such as 10m. Leave empty to give them right away."]
+    duration: Option<String>,
+) -> Result<(), Error> {
+    let delay = match duration.as_deref().map(parse).transpose() {
+        Ok(Some(delay)) if delay > MAX_DELAY => {
+            let reply = messages::error_reply("The delay cannot be longer than an hour.", true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+        Ok(delay) => delay.unwrap_or_default(),
+        Err(why) => {
+            let reply = messages::error_reply(why.to_string(), true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    update_settings(context, |settings| {
+        settings.autorole_delay = delay.as_secs()
+    })
+    .await?;
+
+    let reply = if delay.is_zero() {
+        messages::info_reply("Autoroles are now given right away.", false)
+    } else {
+        messages::info_reply(
+            format!(
+                "Autoroles are now given {}s after joining.",
+                delay.as_secs()
+            ),
+            false,
+        )
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets whether members get their roles back when they leave and rejoin.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD | MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES | SEND_MESSAGES",
+    guild_only
+)]
+pub async fn persistroles(
+    context: Context<'_>,
+    #[description = "Whether roles are given back on rejoin."] enabled: bool,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    update_settings(context, |settings| settings.persist_roles = enabled).await?;
+
+    // Roles saved before turning it off would otherwise come back if it's turned on again.
+    if !enabled {
+        clear_persisted_roles(&guild_id, &context.data().sqlite).await?;
+    }
+
+    info!("Persistent roles set to {enabled} in guild {guild_id}");
+
+    let reply = if enabled {
+        messages::info_reply("Members now get their roles back when they rejoin.", false)
+    } else {
+        messages::info_reply(
+            "Members no longer get their roles back when they rejoin.",
+            false,
+        )
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
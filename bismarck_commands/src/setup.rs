@@ -1,4 +1,8 @@
-use bismarck_core::{context::Context, error::Error, types::GuildSettings};
+use bismarck_core::{
+    context::Context,
+    error::{BismarckError, Error},
+    types::GuildSettings,
+};
 use poise::{serenity_prelude as serenity, CreateReply};
 use serenity::{CreateEmbed, CreateEmbedFooter};
 use tracing::info;
@@ -32,7 +36,9 @@ pub async fn prefix(context: Context<'_>) -> Result<(), Error> {
 
                 Ok(())
             }
-            None => Err(Error::from("No guild settings found")),
+            None => Err(BismarckError::NotFound(
+                "the settings of this server".to_string(),
+            )),
         }
     } else {
         let embed = CreateEmbed::default()
@@ -64,10 +70,12 @@ pub async fn set(
     prefix: Option<String>,
 ) -> Result<(), Error> {
     if let Some(guild_id) = context.guild_id() {
-        let prefix = prefix.unwrap_or_else(|| "+".to_string());
+        let prefix = prefix.unwrap_or_else(|| context.data().config.bot.default_prefix.clone());
 
         if prefix.contains(' ') {
-            return Err(Error::from("Prefix cannot contain spaces."));
+            return Err(BismarckError::UserInput(
+                "Prefix cannot contain spaces.".to_string(),
+            ));
         }
 
         let id = guild_id.get();
@@ -85,6 +93,7 @@ pub async fn set(
                 mute_type: "timeout".to_string(),
                 mute_role: 0,
                 default_mute_duration: 60000,
+                mod_log_channel: None,
             };
 
             let mut guild_setting = pf.entry(id).or_insert(setting);
@@ -121,7 +130,9 @@ pub async fn set(
         return Ok(());
     }
 
-    Err(Error::from("Not in Guild."))
+    Err(BismarckError::UserInput(
+        "This command can only be used in a server.".to_string(),
+    ))
 }
 
 /// Views current guild's prefix commands' prefix.
@@ -147,7 +158,9 @@ pub async fn view(context: Context<'_>) -> Result<(), Error> {
                 return Ok(());
             }
             None => {
-                return Err(Error::from("No guild settings found"));
+                return Err(BismarckError::NotFound(
+                    "the settings of this server".to_string(),
+                ));
             }
         }
     }
@@ -163,3 +176,53 @@ pub async fn view(context: Context<'_>) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Sets the channel moderation actions are posted to.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "modlog",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn mod_log_channel(
+    context: Context<'_>,
+    #[description = "The channel to post to. Leave empty to stop posting."] channel: Option<
+        serenity::GuildChannel,
+    >,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let channel_id = channel.map(|channel| channel.id);
+
+    {
+        let database = &context.data().sqlite;
+
+        sqlx::query("UPDATE guild SET mod_log_channel = ? WHERE id = ?")
+            .bind(channel_id.map(i64::from))
+            .bind(i64::from(guild_id))
+            .execute(database)
+            .await?;
+    }
+
+    if let Some(mut guild_settings) = context.data().guild_data.get_mut(&guild_id.get()) {
+        guild_settings.mod_log_channel = channel_id.map(|id| id.get());
+    }
+
+    let description = match channel_id {
+        Some(channel_id) => format!("Moderation actions will be posted to <#{channel_id}>."),
+        None => "Moderation actions will no longer be posted.".to_string(),
+    };
+
+    info!("Mod log channel set to {channel_id:?} for guild {guild_id}");
+
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title("Mod Log")
+        .description(description);
+
+    context.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use bismarck_core::{context::Context, error::Error, types::StarboardSettings};
+use bismarck_utilities::{
+    messages,
+    starboard::{clear_starboard_messages, upsert_starboard_settings},
+};
+use poise::CreateReply;
+use serenity::all::{CreateEmbed, GuildChannel, Mentionable, ReactionType};
+use tracing::info;
+
+/// Shows the starboard settings of the server.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    subcommands("starboard_channel", "starboard_threshold", "starboard_emoji")
+)]
+pub async fn starboard(context: Context<'_>) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let settings = context
+        .data()
+        .starboard
+        .get(&guild_id.get())
+        .map(|settings| settings.clone())
+        .unwrap_or_default();
+
+    let channel = match settings.channel_id {
+        Some(channel_id) => format!("<#{channel_id}>"),
+        None => "Off".to_string(),
+    };
+
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title("Starboard")
+        .field("Channel", channel, true)
+        .field("Emoji", &settings.emoji, true)
+        .field("Threshold", settings.threshold.to_string(), true);
+
+    context.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Updates the starboard settings of the guild in memory and in the database.
+async fn update_settings(
+    context: Context<'_>,
+    update: impl FnOnce(&mut StarboardSettings),
+) -> Result<StarboardSettings, Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let settings = {
+        let mut settings = context.data().starboard.entry(guild_id.get()).or_default();
+        update(&mut settings);
+        settings.clone()
+    };
+
+    upsert_starboard_settings(&guild_id, &settings, &context.data().sqlite).await?;
+
+    Ok(settings)
+}
+
+/// Sets the channel starred messages are reposted to, or turns the starboard off.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "channel",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS",
+    guild_only
+)]
+pub async fn starboard_channel(
+    context: Context<'_>,
+    #[description = "The starboard channel. Leave empty to turn the starboard off."]
+    channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let channel_id = channel.as_ref().map(|channel| channel.id.get());
+
+    update_settings(context, |settings| settings.channel_id = channel_id).await?;
+
+    // Posts in the previous channel can no longer be updated from the new one.
+    clear_starboard_messages(&guild_id, &context.data().sqlite).await?;
+
+    info!("Starboard channel set to {channel_id:?} in guild {guild_id}");
+
+    let reply = match channel {
+        Some(channel) => messages::info_reply(
+            format!("Starred messages are now posted in {}.", channel.mention()),
+            false,
+        ),
+        None => messages::info_reply("The starboard is now off.", false),
+    };
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets how many stars a message needs to be reposted.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "threshold",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn starboard_threshold(
+    context: Context<'_>,
+    #[description = "Stars needed, not counting the author's own."]
+    #[min = 1]
+    #[max = 100]
+    stars: u32,
+) -> Result<(), Error> {
+    if !(1..=100).contains(&stars) {
+        let reply = messages::error_reply("Stars must be between 1 and 100.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    update_settings(context, |settings| settings.threshold = stars).await?;
+
+    let reply = messages::info_reply(
+        format!("Messages now need {stars} star(s) to be reposted."),
+        false,
+    );
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets the emoji that stars a message.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "emoji",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn starboard_emoji(
+    context: Context<'_>,
+    #[description = "The emoji, such as ⭐."] emoji: String,
+) -> Result<(), Error> {
+    let emoji = match ReactionType::try_from(emoji.trim()) {
+        Ok(emoji) => emoji.to_string(),
+        Err(_) => {
+            let reply = messages::error_reply("That isn't an emoji.", true);
+            context.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    update_settings(context, |settings| settings.emoji = emoji.clone()).await?;
+
+    let reply = messages::info_reply(format!("Messages are now starred with {emoji}."), false);
+    context.send(reply).await?;
+
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use bismarck_core::{context::Context, error::Error};
+use bismarck_utilities::{
+    messages,
+    ticket::{
+        is_staff, panel, select_ticket_by_thread, select_ticket_config, upsert_ticket_config,
+        TicketConfig,
+    },
+};
+use poise::CreateReply;
+use serenity::all::{ChannelType, CreateEmbed, GuildChannel, Mentionable, Role, User};
+use tracing::info;
+
+/// Shows the ticket settings of the server.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only,
+    subcommands("ticket_setup", "ticket_panel", "ticket_add")
+)]
+pub async fn ticket(context: Context<'_>) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let config = select_ticket_config(&guild_id, &context.data().sqlite)
+        .await?
+        .unwrap_or_default();
+
+    let staff_role = match config.staff_role_id {
+        Some(role_id) => role_id.mention().to_string(),
+        None => "Not set".to_string(),
+    };
+    let log_channel = match config.log_channel_id {
+        Some(channel_id) => channel_id.mention().to_string(),
+        None => "Off".to_string(),
+    };
+
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title("Tickets")
+        .field("Staff role", staff_role, true)
+        .field("Transcripts", log_channel, true);
+
+    context.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Sets the staff role handling tickets and where transcripts are saved.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "setup",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn ticket_setup(
+    context: Context<'_>,
+    #[description = "The role that handles tickets."] staff_role: Role,
+    #[description = "The channel transcripts are saved to. Leave empty to not save them."]
+    log_channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    let config = TicketConfig {
+        staff_role_id: Some(staff_role.id),
+        log_channel_id: log_channel.as_ref().map(|channel| channel.id),
+    };
+    upsert_ticket_config(&guild_id, &config, &context.data().sqlite).await?;
+
+    info!("Ticket settings updated in guild {guild_id}");
+
+    let reply = messages::info_reply(
+        format!("Tickets are now handled by {}.", staff_role.mention()),
+        false,
+    );
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Posts the panel members open tickets from.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "panel",
+    category = "Settings",
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS | CREATE_PRIVATE_THREADS",
+    guild_only
+)]
+pub async fn ticket_panel(
+    context: Context<'_>,
+    #[description = "The channel to post the panel in. Tickets open as threads of it."]
+    #[channel_types("Text")]
+    channel: GuildChannel,
+    #[description = "The panel's title."] title: Option<String>,
+    #[description = "The panel's description."] description: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+
+    if channel.kind != ChannelType::Text {
+        let reply = messages::error_reply("Tickets can only be opened from text channels.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let configured = select_ticket_config(&guild_id, &context.data().sqlite)
+        .await?
+        .is_some_and(|config| config.staff_role_id.is_some());
+    if !configured {
+        let reply = messages::error_reply("Set up tickets with `ticket setup` first.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    let title = title.unwrap_or_else(|| "Support".to_string());
+    let description = description.unwrap_or_else(|| {
+        "Press the button below to open a private ticket with staff.".to_string()
+    });
+
+    channel
+        .id
+        .send_message(context, panel(&title, &description))
+        .await?;
+
+    let reply = messages::info_reply(
+        format!("The ticket panel is posted in {}.", channel.mention()),
+        true,
+    );
+    context.send(reply).await?;
+
+    Ok(())
+}
+
+/// Adds a user to the ticket this is used in.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "add",
+    category = "Moderator",
+    required_bot_permissions = "SEND_MESSAGES",
+    guild_only
+)]
+pub async fn ticket_add(
+    context: Context<'_>,
+    #[description = "The user to add to the ticket."] user: User,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().unwrap();
+    let pool = &context.data().sqlite;
+
+    let ticket = select_ticket_by_thread(&context.channel_id(), pool)
+        .await?
+        .filter(|ticket| ticket.guild_id == guild_id);
+    let Some(ticket) = ticket else {
+        let reply = messages::error_reply("This isn't a ticket.", true);
+        context.send(reply).await?;
+        return Ok(());
+    };
+
+    let config = select_ticket_config(&guild_id, pool)
+        .await?
+        .unwrap_or_default();
+    let staff = match context.author_member().await {
+        Some(member) => is_staff(&member, &config),
+        None => false,
+    };
+    if !staff {
+        let reply = messages::error_reply("Only staff can add users to tickets.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    if !ticket.open {
+        let reply = messages::error_reply("This ticket is closed.", true);
+        context.send(reply).await?;
+        return Ok(());
+    }
+
+    ticket.thread_id.add_thread_member(context, user.id).await?;
+
+    let reply = messages::info_reply(format!("Added {} to the ticket.", user.mention()), false);
+    context.send(reply).await?;
+
+    Ok(())
+}
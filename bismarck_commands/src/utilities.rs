@@ -31,7 +31,10 @@ pub async fn help(
     let prefix = context
         .guild_id()
         .and_then(|guild_id| pf.get(&guild_id.get()))
-        .map_or_else(|| "+".to_string(), |gs| gs.prefix.clone());
+        .map_or_else(
+            || context.data().config.bot.default_prefix.clone(),
+            |gs| gs.prefix.clone(),
+        );
 
     let format = format!(
         "\
@@ -1,11 +1,13 @@
 use poise::CreateReply;
-use serenity::all::{CreateEmbed, CreateMessage, CreateSelectMenu, CreateSelectMenuOption};
+use serenity::all::{CreateEmbed, CreateSelectMenu, CreateSelectMenuOption};
 
 use bismarck_core::{
     context::Context,
-    error::Error,
-    types::{QueryContainer, WikiQuery},
+    error::{BismarckError, Error},
+    metrics,
+    types::WikiQuery,
 };
+use bismarck_utilities::wiki;
 use tracing::debug;
 
 /// Shows Wikipedia search results.
@@ -30,8 +32,8 @@ pub async fn wiki(
         ("limit", "3"),
     ];
 
-    let url =
-        reqwest::Url::parse_with_params("https://en.wikipedia.org/w/api.php", params).unwrap();
+    let url = reqwest::Url::parse_with_params(&ctx.data().config.api.wikipedia, params)
+        .map_err(|why| BismarckError::Internal(format!("Invalid Wikipedia URL: {why}")))?;
 
     debug!("URL: {}", url);
 
@@ -52,18 +54,16 @@ pub async fn wiki(
                 return Ok(());
             }
 
-            // TODO: Make buttons/select menu
+            let articles = &ctx.data().config.api.wikipedia_articles;
             let mut options = Vec::new();
 
             for (label, value) in data.1.iter().zip(data.3.iter()) {
-                let value = value.replace("https://en.wikipedia.org/wiki/", "");
+                let value = value.strip_prefix(articles.as_str()).unwrap_or(value);
                 options.push(CreateSelectMenuOption::new(label, value));
             }
 
-            let ctx_id = ctx.id();
-
             let menu = CreateSelectMenu::new(
-                ctx_id.to_string(),
+                wiki::CUSTOM_ID_PREFIX,
                 poise::serenity_prelude::CreateSelectMenuKind::String { options },
             )
             .max_values(1)
@@ -79,63 +79,19 @@ pub async fn wiki(
                     menu,
                 )]);
 
-            let handle = ctx.send(reply).await?;
-
-            let refer = handle.into_message().await?;
-
-            while let Some(interaction) =
-                serenity::collector::ComponentInteractionCollector::new(ctx)
-                    .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
-                    .timeout(std::time::Duration::from_secs(3600 * 24))
-                    .await
-            {
-                // TODO: Implement select interaction to show the summary of the selected article
-                interaction.defer(ctx.http()).await?;
-
-                if let poise::serenity_prelude::ComponentInteractionDataKind::StringSelect {
-                    values,
-                } = interaction.data.kind
-                {
-                    if let Some(value) = values.first() {
-                        // interaction.channel_id.say(ctx.http(), "https://en.wikipedia.org/wiki/".to_owned() + value).await?; // debug
-                        //TODO: Add summary fetching
-                        let request = &ctx.data().reqwest;
-                        let url = format!("https://en.wikipedia.org/w/api.php?format=json&action=query&prop=extracts&exintro&explaintext&redirects=1&titles={value}");
-
-                        debug!("URL: {}", url);
-
-                        let get = request.get(url).send().await;
-
-                        let res = match get {
-                            Ok(res) => res.text().await,
-                            Err(_) => {
-                                return Err("Failed to get data.".into());
-                            }
-                        };
-
-                        let res = res.unwrap();
-                        debug!("Response: {:?}", res);
-
-                        let data: QueryContainer = serde_json::from_str(&res).unwrap();
-                        debug!("{:?}", data);
-
-                        let data = data.query;
-                        let embed = CreateEmbed::new()
-                            .title(data.pages.title)
-                            .description(data.pages.extract);
-
-                        let message = CreateMessage::new().embed(embed).reference_message(&refer);
-
-                        ctx.channel_id().send_message(ctx.http(), message).await?;
-                    }
-                }
-            }
+            ctx.send(reply).await?;
 
             Ok(())
         } else {
-            Err("Failed to deserialize the data from the Wikipedia API.".into())
+            metrics::http_failure("wikipedia");
+            Err(BismarckError::External(
+                "Failed to deserialize the data from the Wikipedia API.".to_string(),
+            ))
         }
     } else {
-        Err("Wikipedia API data request failed.".into())
+        metrics::http_failure("wikipedia");
+        Err(BismarckError::External(
+            "Wikipedia API data request failed.".to_string(),
+        ))
     }
 }
@@ -0,0 +1,309 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::{BismarckError, Error};
+
+/// Where the config file is looked for, unless `BISMARCK_CONFIG` says otherwise.
+pub const DEFAULT_PATH: &str = "config.toml";
+
+/// Longest prefix allowed, as for prefixes set by guilds.
+pub const MAX_PREFIX_LENGTH: usize = 5;
+
+/// Settings read at startup from a TOML file, then overridden by environment variables. Every
+/// setting has a default, so neither the file nor any setting in it is required.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub bot: BotConfig,
+    pub presence: PresenceConfig,
+    pub http: HttpConfig,
+    pub api: ApiConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// Path of the SQLite database, created if missing.
+    pub path: String,
+    pub max_connections: u32,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            path: "database.sqlite".to_string(),
+            max_connections: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotConfig {
+    /// Prefix of guilds that haven't set their own, and of DMs.
+    pub default_prefix: String,
+    /// Sent when the bot is mentioned on its own. `{prefix}` is replaced by the prefix in use.
+    pub mention_reply: String,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            default_prefix: "+".to_string(),
+            mention_reply: "```To see the list of commands type {prefix}help```".to_string(),
+        }
+    }
+}
+
+/// Statuses the bot alternates between. In `playing`, `{guilds}` and `{prefix}` are replaced by
+/// the guild count and the default prefix, and in `listening`, `{shard}` by the shard's ID.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PresenceConfig {
+    pub playing: String,
+    pub listening: String,
+    /// Seconds between switching statuses.
+    pub interval: u64,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            playing: "Monitoring a total of {guilds} guilds | {prefix}help".to_string(),
+            listening: "On Shard {shard} | Flottenstützpunkt Hamburg".to_string(),
+            interval: 3,
+        }
+    }
+}
+
+/// Where health checks, readiness checks and metrics are served.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 9000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Wikipedia's API endpoint, `api.php`.
+    pub wikipedia: String,
+    /// What Wikipedia's article links start with, before the article's title.
+    pub wikipedia_articles: String,
+    /// nekosapi's endpoint for random images.
+    pub nekos: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            wikipedia: "https://en.wikipedia.org/w/api.php".to_string(),
+            wikipedia_articles: "https://en.wikipedia.org/wiki/".to_string(),
+            nekos: "https://api.nekosapi.com/v3/images/random".to_string(),
+        }
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, Error> {
+    value
+        .parse()
+        .map_err(|_| BismarckError::Internal(format!("{name} is not valid: `{value}`")))
+}
+
+impl Config {
+    /// Loads the config file at `BISMARCK_CONFIG` or `config.toml`, if there is one, applies
+    /// environment overrides and validates the result.
+    pub fn load() -> Result<Self, Error> {
+        let path = std::env::var("BISMARCK_CONFIG").unwrap_or_else(|_| DEFAULT_PATH.to_string());
+
+        let mut config = if Path::new(&path).exists() {
+            let toml = std::fs::read_to_string(&path)
+                .map_err(|why| BismarckError::Internal(format!("Couldn't read {path}: {why}")))?;
+
+            Self::from_toml(&toml)
+                .map_err(|why| BismarckError::Internal(format!("Couldn't read {path}: {why}")))?
+        } else {
+            Self::default()
+        };
+
+        config.apply_env(|name| std::env::var(name).ok())?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        toml::from_str(toml).map_err(|why| BismarckError::Internal(why.to_string()))
+    }
+
+    /// Overrides settings with the environment variables `get` finds.
+    pub fn apply_env(&mut self, get: impl Fn(&str) -> Option<String>) -> Result<(), Error> {
+        if let Some(path) = get("BISMARCK_DATABASE_PATH") {
+            self.database.path = path;
+        }
+        if let Some(value) = get("BISMARCK_DATABASE_MAX_CONNECTIONS") {
+            self.database.max_connections = parse_env("BISMARCK_DATABASE_MAX_CONNECTIONS", &value)?;
+        }
+        if let Some(prefix) = get("BISMARCK_DEFAULT_PREFIX") {
+            self.bot.default_prefix = prefix;
+        }
+        if let Some(host) = get("HTTP_HOST") {
+            self.http.host = host;
+        }
+        if let Some(value) = get("HTTP_PORT") {
+            self.http.port = parse_env("HTTP_PORT", &value)?;
+        }
+        if let Some(url) = get("BISMARCK_WIKIPEDIA_URL") {
+            self.api.wikipedia = url;
+        }
+        if let Some(url) = get("BISMARCK_WIKIPEDIA_ARTICLES_URL") {
+            self.api.wikipedia_articles = url;
+        }
+        if let Some(url) = get("BISMARCK_NEKOS_URL") {
+            self.api.nekos = url;
+        }
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        let prefix = &self.bot.default_prefix;
+
+        if prefix.is_empty() || prefix.contains(char::is_whitespace) {
+            return Err(BismarckError::Internal(
+                "The default prefix can't be empty or contain spaces.".to_string(),
+            ));
+        }
+
+        if prefix.chars().count() > MAX_PREFIX_LENGTH {
+            return Err(BismarckError::Internal(format!(
+                "The default prefix can be at most {MAX_PREFIX_LENGTH} characters long."
+            )));
+        }
+
+        if self.database.max_connections == 0 {
+            return Err(BismarckError::Internal(
+                "The database needs at least one connection.".to_string(),
+            ));
+        }
+
+        if self.presence.interval == 0 {
+            return Err(BismarckError::Internal(
+                "The presence interval must be at least a second.".to_string(),
+            ));
+        }
+
+        for (name, url) in [
+            ("api.wikipedia", &self.api.wikipedia),
+            ("api.wikipedia_articles", &self.api.wikipedia_articles),
+            ("api.nekos", &self.api.nekos),
+        ] {
+            if let Err(why) = reqwest::Url::parse(url) {
+                return Err(BismarckError::Internal(format!(
+                    "{name} is not a valid URL: {why}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn from_toml_test() {
+        let config = Config::from_toml(
+            r#"
+            [database]
+            path = "/data/bismarck.sqlite"
+
+            [bot]
+            default_prefix = "-"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.database.path, "/data/bismarck.sqlite");
+        // Missing settings keep their defaults.
+        assert_eq!(config.database.max_connections, 5);
+        assert_eq!(config.bot.default_prefix, "-");
+        assert_eq!(config.http, HttpConfig::default());
+
+        assert!(Config::from_toml("[bot]\nprefix = \"-\"").is_err());
+        assert!(Config::from_toml("").unwrap().validate().is_ok());
+    }
+
+    #[test]
+    fn apply_env_test() {
+        let mut config = Config::default();
+
+        config
+            .apply_env(|name| match name {
+                "HTTP_PORT" => Some("8080".to_string()),
+                "BISMARCK_DEFAULT_PREFIX" => Some("!".to_string()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(config.http.port, 8080);
+        assert_eq!(config.bot.default_prefix, "!");
+        assert_eq!(config.database, DatabaseConfig::default());
+
+        assert!(config
+            .apply_env(|name| (name == "HTTP_PORT").then(|| "http".to_string()))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn default_prefix_test() {
+        let pool = crate::testing::pool().await;
+
+        sqlx::query("INSERT INTO user (id) VALUES (10)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO guild (id, owner, commands_ran, songs_played) VALUES (1, 10, 0, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Guilds stored without a prefix get the same one as the bot's default.
+        let prefix: String = sqlx::query_scalar("SELECT prefix FROM guild WHERE id = 1")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(prefix, Config::default().bot.default_prefix);
+    }
+
+    #[test]
+    fn validate_test() {
+        let mut config = Config::default();
+        config.bot.default_prefix = "a b".to_string();
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.api.nekos = "not a url".to_string();
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.database.max_connections = 0;
+        assert!(config.validate().is_err());
+    }
+}
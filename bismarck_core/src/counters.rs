@@ -0,0 +1,316 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use chrono::Utc;
+use dashmap::DashMap;
+use sqlx::SqlitePool;
+use tracing::{error, info};
+
+/// The guild ID the global command count is stored under.
+pub const GLOBAL_ID: u64 = 0;
+
+/// How long command usage is kept for.
+const USAGE_RETENTION: i64 = 90 * 24 * 60 * 60;
+
+/// One run of a command, for usage analytics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandUsage {
+    /// The command's qualified name, such as `remind in`.
+    pub command: String,
+    pub guild_id: Option<u64>,
+    pub user_id: u64,
+    pub success: bool,
+    pub latency_ms: u64,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    /// Commands ran per guild since the last flush, including the global count.
+    guilds: DashMap<u64, AtomicU64>,
+    /// Commands ran per user since the last flush.
+    users: DashMap<u64, AtomicU64>,
+    usage: Mutex<Vec<CommandUsage>>,
+}
+
+/// Command counts and usage not yet written to the database, so running a command doesn't have
+/// to wait on it. They are flushed in batches on an interval and when shutting down.
+#[derive(Debug, Clone, Default)]
+pub struct CommandCounters {
+    pending: Arc<Pending>,
+}
+
+/// Takes the non-zero counts out of a map, leaving zeros in their place.
+fn drain(counts: &DashMap<u64, AtomicU64>) -> Vec<(u64, u64)> {
+    counts
+        .iter()
+        .map(|entry| (*entry.key(), entry.value().swap(0, Ordering::AcqRel)))
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+fn add(counts: &DashMap<u64, AtomicU64>, id: u64, count: u64) {
+    counts
+        .entry(id)
+        .or_default()
+        .fetch_add(count, Ordering::AcqRel);
+}
+
+impl CommandCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a command ran by a user, in a guild or in DMs.
+    pub fn record(&self, guild_id: Option<u64>, user_id: u64) {
+        if let Some(guild_id) = guild_id {
+            add(&self.pending.guilds, guild_id, 1);
+        }
+
+        add(&self.pending.guilds, GLOBAL_ID, 1);
+        add(&self.pending.users, user_id, 1);
+    }
+
+    /// Commands ran in a guild, or globally under [`GLOBAL_ID`], not yet written to the database.
+    pub fn pending_commands(&self, guild_id: u64) -> u64 {
+        self.pending
+            .guilds
+            .get(&guild_id)
+            .map_or(0, |count| count.load(Ordering::Acquire))
+    }
+
+    /// Logs how a command run went, once it has finished.
+    pub fn record_usage(&self, usage: CommandUsage) {
+        if let Ok(mut pending) = self.pending.usage.lock() {
+            pending.push(usage);
+        }
+    }
+
+    /// Runs of commands since `since` not yet written to the database, as
+    /// `(command, success, latency)`, in a guild or across all of them.
+    pub fn pending_usage(&self, guild_id: Option<u64>, since: i64) -> Vec<(String, bool, u64)> {
+        let Ok(pending) = self.pending.usage.lock() else {
+            return Vec::new();
+        };
+
+        pending
+            .iter()
+            .filter(|usage| guild_id.is_none() || usage.guild_id == guild_id)
+            .filter(|usage| usage.created_at >= since)
+            .map(|usage| (usage.command.clone(), usage.success, usage.latency_ms))
+            .collect()
+    }
+
+    fn take_usage(&self) -> Vec<CommandUsage> {
+        self.pending
+            .usage
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default()
+    }
+
+    /// Writes the pending counts and usage to the database in one transaction. Whatever fails
+    /// to be written is kept for the next flush.
+    pub async fn flush(&self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        let guilds = drain(&self.pending.guilds);
+        let users = drain(&self.pending.users);
+        let usage = self.take_usage();
+
+        if guilds.is_empty() && users.is_empty() && usage.is_empty() {
+            return Ok(());
+        }
+
+        if let Err(why) = write_counts(&guilds, &users, &usage, pool).await {
+            error!("Failed to flush command counts: {:?}", why);
+
+            for (guild_id, count) in guilds {
+                add(&self.pending.guilds, guild_id, count);
+            }
+            for (user_id, count) in users {
+                add(&self.pending.users, user_id, count);
+            }
+            if let Ok(mut pending) = self.pending.usage.lock() {
+                pending.splice(0..0, usage);
+            }
+
+            return Err(why);
+        }
+
+        Ok(())
+    }
+}
+
+async fn write_counts(
+    guilds: &[(u64, u64)],
+    users: &[(u64, u64)],
+    usage: &[CommandUsage],
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let mut transaction = pool.begin().await?;
+
+    for (guild_id, count) in guilds {
+        sqlx::query("UPDATE guild SET commands_ran = commands_ran + ? WHERE id = ?")
+            .bind(*count as i64)
+            .bind(*guild_id as i64)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    for (user_id, count) in users {
+        sqlx::query(
+            "INSERT INTO user (id, commands_run) VALUES (?, ?) ON CONFLICT(id) DO UPDATE SET commands_run = commands_run + excluded.commands_run",
+        )
+        .bind(*user_id as i64)
+        .bind(*count as i64)
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    for usage in usage {
+        sqlx::query(
+            "INSERT INTO command_log (command, guild_id, user_id, success, latency_ms, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&usage.command)
+        .bind(usage.guild_id.map(|guild_id| guild_id as i64))
+        .bind(usage.user_id as i64)
+        .bind(usage.success)
+        .bind(usage.latency_ms as i64)
+        .bind(usage.created_at)
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    if !usage.is_empty() {
+        sqlx::query("DELETE FROM command_log WHERE created_at < ?")
+            .bind(Utc::now().timestamp() - USAGE_RETENTION)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    transaction.commit().await?;
+
+    let elapsed_time = start_time.elapsed();
+    info!(
+        "Flushed command counts of {} guild(s) and {} user(s) and {} command run(s) in {elapsed_time:.2?}",
+        guilds.len(),
+        users.len(),
+        usage.len()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod counters_tests {
+    use super::*;
+    use sqlx::Row;
+
+    async fn pool() -> SqlitePool {
+        let pool = crate::testing::pool().await;
+
+        sqlx::query("INSERT INTO user (id) VALUES (10)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO guild (id, owner, commands_ran, songs_played) VALUES (1, 10, 5, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        pool
+    }
+
+    async fn commands_ran(pool: &SqlitePool) -> (i64, i64, i64) {
+        let guild = sqlx::query("SELECT commands_ran FROM guild WHERE id = 1")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+            .get(0);
+        let owner = sqlx::query("SELECT commands_run FROM user WHERE id = 10")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+            .get(0);
+        let new_user = sqlx::query("SELECT commands_run FROM user WHERE id = 20")
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+            .map_or(0, |row| row.get(0));
+
+        (guild, owner, new_user)
+    }
+
+    #[tokio::test]
+    async fn flush_test() {
+        let pool = pool().await;
+        let counters = CommandCounters::new();
+
+        counters.record(Some(1), 10);
+        counters.record(Some(1), 20);
+        counters.record(None, 20);
+        counters.record_usage(CommandUsage {
+            command: "ping".to_string(),
+            guild_id: Some(1),
+            user_id: 10,
+            success: true,
+            latency_ms: 12,
+            created_at: Utc::now().timestamp(),
+        });
+        counters.flush(&pool).await.unwrap();
+
+        assert_eq!(commands_ran(&pool).await, (7, 1, 2));
+
+        let logged: i64 = sqlx::query("SELECT COUNT(*) FROM command_log")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(logged, 1);
+
+        // Nothing is written twice.
+        counters.flush(&pool).await.unwrap();
+        assert_eq!(commands_ran(&pool).await, (7, 1, 2));
+    }
+
+    #[test]
+    fn pending_usage_test() {
+        let counters = CommandCounters::new();
+        let now = Utc::now().timestamp();
+
+        for (guild_id, created_at) in [(Some(1), now), (Some(2), now), (None, now - 10)] {
+            counters.record_usage(CommandUsage {
+                command: "ping".to_string(),
+                guild_id,
+                user_id: 10,
+                success: true,
+                latency_ms: 12,
+                created_at,
+            });
+        }
+
+        assert_eq!(
+            counters.pending_usage(Some(1), now),
+            vec![("ping".to_string(), true, 12)]
+        );
+        assert_eq!(counters.pending_usage(None, now).len(), 2);
+        assert_eq!(counters.pending_usage(None, now - 10).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn failed_flush_keeps_counts_test() {
+        let pool = pool().await;
+        let counters = CommandCounters::new();
+
+        counters.record(Some(1), 10);
+        pool.close().await;
+        assert!(counters.flush(&pool).await.is_err());
+
+        assert_eq!(drain(&counters.pending.guilds).len(), 2);
+        assert_eq!(drain(&counters.pending.users), vec![(10, 1)]);
+    }
+}
@@ -1,19 +1,44 @@
-use crate::types::{GuildSettings, User};
+use crate::config::Config;
+use crate::counters::CommandCounters;
+use crate::health::Health;
+use crate::scheduler::Scheduler;
+use crate::shutdown::Shutdown;
+use crate::types::{AutomodSettings, GuildSettings, RaidSettings, StarboardSettings, User};
 use dashmap::DashMap;
 use poise::serenity_prelude as serenity;
 use sqlx::SqlitePool;
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicU64};
 use std::sync::Arc;
+use std::time::Instant;
+
+/// Messages recently sent per guild and user, as when they were sent and a hash of their
+/// content.
+pub type RecentMessages = DashMap<(u64, u64), VecDeque<(Instant, u64)>>;
 
 #[derive(Debug)]
 pub struct Data {
+    pub config: Config,
     pub reqwest: reqwest::Client,
     pub sqlite: SqlitePool,
     pub guild_data: DashMap<u64, GuildSettings>,
+    pub automod: DashMap<u64, AutomodSettings>,
+    /// Used by automod's rate based checks, and shared with the task pruning it.
+    pub recent_messages: Arc<RecentMessages>,
+    /// Shared with the tasks turning raid mode off.
+    pub raid: Arc<DashMap<u64, RaidSettings>>,
+    /// Members recently joined per guild, as when they joined and whether their account is new.
+    pub recent_joins: DashMap<u64, VecDeque<(Instant, bool)>>,
+    pub starboard: DashMap<u64, StarboardSettings>,
+    pub scheduler: Scheduler,
     pub users: DashMap<u64, User>,
     pub commands_ran: DashMap<u64, AtomicU64>,
     pub commands_ran_users: DashMap<u64, AtomicU64>,
+    /// Counts of `commands_ran` and `commands_ran_users` not yet written to the database.
+    pub command_counters: CommandCounters,
     pub songs_played: DashMap<u64, AtomicU64>,
     pub shard_manager: Arc<serenity::ShardManager>,
+    pub shutdown: Shutdown,
+    pub health: Health,
     pub is_loop_running: AtomicBool,
 } // User data, which is stored and accessible in all command invocations
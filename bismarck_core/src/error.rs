@@ -1,7 +1,138 @@
+use std::fmt;
+
 use data::Data;
 use poise;
+use poise::serenity_prelude as serenity;
 
 use crate::data;
 
-pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub type Error = BismarckError;
 pub type FrameworkError<'a> = poise::FrameworkError<'a, Data, Error>;
+
+/// Why a command, event handler or job failed.
+///
+/// Mistakes of users, and failures they can do something about, carry a message for them.
+/// Anything else is on the bot's side, so users are only given an ID to report it with.
+#[derive(Debug)]
+pub enum BismarckError {
+    /// The user gave something invalid, such as a malformed duration.
+    UserInput(String),
+    /// The user or the bot isn't allowed to do something, such as acting on a higher role.
+    Permission(String),
+    /// Something looked up wasn't there, such as a guild missing from the cache.
+    NotFound(String),
+    Database(sqlx::Error),
+    /// Boxed, as serenity's errors are large.
+    Http(Box<serenity::Error>),
+    /// A service other than Discord failed, such as Wikipedia's API.
+    External(String),
+    /// Discord sent less than expected, or the bot is misconfigured.
+    Internal(String),
+}
+
+impl BismarckError {
+    /// Whether the error was caused by the user rather than the bot, so it isn't worth an
+    /// error ID.
+    pub fn is_user_error(&self) -> bool {
+        matches!(
+            self,
+            Self::UserInput(_) | Self::Permission(_) | Self::NotFound(_)
+        )
+    }
+
+    /// What to tell the user about the error.
+    pub fn user_message(&self) -> String {
+        match self {
+            Self::UserInput(message) | Self::Permission(message) => message.clone(),
+            Self::NotFound(what) => format!("I couldn't find {what}."),
+            Self::Database(_) => {
+                "I couldn't reach my database. Please try again later.".to_string()
+            }
+            Self::Http(_) => {
+                "Discord didn't accept my request. Please try again later.".to_string()
+            }
+            Self::External(_) => {
+                "An external service isn't responding. Please try again later.".to_string()
+            }
+            Self::Internal(_) => "Oh no! There's a problem in executing this command.".to_string(),
+        }
+    }
+}
+
+/// A short random ID to link an error reported by a user to its log.
+pub fn error_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..8].to_string()
+}
+
+impl fmt::Display for BismarckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UserInput(message) => write!(f, "Invalid input: {message}"),
+            Self::Permission(message) => write!(f, "Missing permission: {message}"),
+            Self::NotFound(what) => write!(f, "Not found: {what}"),
+            Self::Database(why) => write!(f, "Database error: {why}"),
+            Self::Http(why) => write!(f, "Discord error: {why}"),
+            Self::External(why) => write!(f, "External service error: {why}"),
+            Self::Internal(why) => write!(f, "Internal error: {why}"),
+        }
+    }
+}
+
+impl std::error::Error for BismarckError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Database(why) => Some(why),
+            Self::Http(why) => Some(why.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for BismarckError {
+    fn from(why: sqlx::Error) -> Self {
+        Self::Database(why)
+    }
+}
+
+impl From<serenity::Error> for BismarckError {
+    fn from(why: serenity::Error) -> Self {
+        Self::Http(Box::new(why))
+    }
+}
+
+impl From<serenity::ModelError> for BismarckError {
+    fn from(why: serenity::ModelError) -> Self {
+        Self::Http(Box::new(serenity::Error::Model(why)))
+    }
+}
+
+impl From<reqwest::Error> for BismarckError {
+    fn from(why: reqwest::Error) -> Self {
+        Self::External(why.to_string())
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use super::*;
+
+    #[test]
+    fn user_message_test() {
+        let error = BismarckError::UserInput("Prefix cannot contain spaces.".to_string());
+        assert!(error.is_user_error());
+        assert_eq!(error.user_message(), "Prefix cannot contain spaces.");
+
+        let error = BismarckError::from(sqlx::Error::RowNotFound);
+        assert!(!error.is_user_error());
+        // Details of the bot's own failures are only logged.
+        assert!(!error.user_message().contains("no rows"));
+    }
+
+    #[test]
+    fn error_id_test() {
+        let id = error_id();
+
+        assert_eq!(id.len(), 8);
+        assert_ne!(id, error_id());
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use poise::serenity_prelude::{ConnectionStage, ShardManager};
+use sqlx::SqlitePool;
+
+/// What health and readiness probes check, besides the database.
+#[derive(Debug, Clone, Default)]
+pub struct Health {
+    cache_ready: Arc<AtomicBool>,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the cache as having every guild the bot is in.
+    pub fn set_cache_ready(&self) {
+        self.cache_ready.store(true, Ordering::Release);
+    }
+
+    pub fn is_cache_ready(&self) -> bool {
+        self.cache_ready.load(Ordering::Acquire)
+    }
+}
+
+/// Checks that the database can be queried.
+pub async fn check_database(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+
+    Ok(())
+}
+
+/// Whether there are shards and all of them are connected.
+pub fn all_connected(stages: impl IntoIterator<Item = ConnectionStage>) -> bool {
+    let mut stages = stages.into_iter().peekable();
+
+    stages.peek().is_some() && stages.all(|stage| stage == ConnectionStage::Connected)
+}
+
+/// Checks that every shard is connected, returning the ones that aren't otherwise.
+pub async fn check_shards(shard_manager: &ShardManager) -> Result<(), String> {
+    let runners = shard_manager.runners.lock().await;
+
+    if all_connected(runners.values().map(|runner| runner.stage)) {
+        return Ok(());
+    }
+
+    if runners.is_empty() {
+        return Err("No shards are running".to_string());
+    }
+
+    let stages = runners
+        .iter()
+        .filter(|(_, runner)| runner.stage != ConnectionStage::Connected)
+        .map(|(id, runner)| format!("shard {id} is {}", runner.stage))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Err(stages)
+}
+
+#[cfg(test)]
+mod health_tests {
+    use super::*;
+    use crate::testing::pool;
+
+    #[test]
+    fn all_connected_test() {
+        assert!(all_connected([ConnectionStage::Connected]));
+        assert!(!all_connected([
+            ConnectionStage::Connected,
+            ConnectionStage::Resuming
+        ]));
+        // Not ready before any shard has started.
+        assert!(!all_connected([]));
+    }
+
+    #[tokio::test]
+    async fn check_database_test() {
+        let pool = pool().await;
+
+        assert!(check_database(&pool).await.is_ok());
+
+        pool.close().await;
+        assert!(check_database(&pool).await.is_err());
+    }
+
+    #[test]
+    fn cache_ready_test() {
+        let health = Health::new();
+        let shared = health.clone();
+
+        assert!(!health.is_cache_ready());
+        shared.set_cache_ready();
+        assert!(health.is_cache_ready());
+    }
+}
@@ -1,10 +1,31 @@
 use poise::serenity_prelude as serenity;
+use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection};
 
+pub mod config;
 pub mod context;
+pub mod counters;
 pub mod data;
 pub mod error;
+pub mod health;
+pub mod metrics;
+pub mod scheduler;
+pub mod shutdown;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod types;
 
+/// Runs the database's migrations through a connection of their own with foreign keys off.
+/// SQLite only lets them be turned off outside of a transaction, which every migration runs in,
+/// and rebuilding a table with them on would delete the rows referencing it.
+pub async fn migrate(options: &SqliteConnectOptions) -> Result<(), sqlx::migrate::MigrateError> {
+    let mut connection = options.clone().foreign_keys(false).connect().await?;
+
+    sqlx::migrate!("../migrations").run(&mut connection).await?;
+    connection.close().await?;
+
+    Ok(())
+}
+
 pub async fn gateway_intents() -> serenity::GatewayIntents {
     serenity::GatewayIntents::non_privileged()
         | serenity::GatewayIntents::GUILD_MEMBERS
@@ -0,0 +1,110 @@
+//! Prometheus metrics, recorded only with the `metrics` feature. Without it, recording is a
+//! no-op so callers don't need to care whether it is enabled.
+
+use std::time::Duration;
+
+use poise::serenity_prelude::ConnectionStage;
+
+#[cfg(feature = "metrics")]
+pub use metrics_exporter_prometheus::PrometheusHandle;
+
+/// Every stage a shard can be in, so the stages it isn't in can be reset.
+#[cfg(feature = "metrics")]
+const STAGES: [ConnectionStage; 6] = [
+    ConnectionStage::Connected,
+    ConnectionStage::Connecting,
+    ConnectionStage::Disconnected,
+    ConnectionStage::Handshake,
+    ConnectionStage::Identifying,
+    ConnectionStage::Resuming,
+];
+
+/// Installs the Prometheus recorder, returning a handle to render the metrics with.
+#[cfg(feature = "metrics")]
+pub fn install() -> Result<PrometheusHandle, crate::error::Error> {
+    use crate::error::BismarckError;
+    use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Suffix("duration_seconds".to_string()),
+            &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0],
+        )
+        .and_then(PrometheusBuilder::install_recorder)
+        .map_err(|why| BismarckError::Internal(format!("Couldn't install metrics: {why}")))
+}
+
+/// Counts a command being ran.
+pub fn command_ran(command: &str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("bismarck_commands_total", "command" => command.to_string()).increment(1);
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = command;
+}
+
+/// Counts an error handled by the framework, by its kind, such as `Command` or `CooldownHit`.
+pub fn framework_error(kind: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("bismarck_framework_errors_total", "kind" => kind).increment(1);
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = kind;
+}
+
+/// Records a shard's stage and latency.
+pub fn shard_status(shard_id: u32, stage: ConnectionStage, latency: Option<Duration>) {
+    #[cfg(feature = "metrics")]
+    {
+        let shard = shard_id.to_string();
+
+        for known in STAGES {
+            metrics::gauge!(
+                "bismarck_shard_stage",
+                "shard" => shard.clone(),
+                "stage" => known.to_string()
+            )
+            .set(if known == stage { 1.0 } else { 0.0 });
+        }
+
+        // Unknown until the first heartbeat is acknowledged.
+        if let Some(latency) = latency {
+            metrics::gauge!("bismarck_shard_latency_seconds", "shard" => shard)
+                .set(latency.as_secs_f64());
+        }
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = (shard_id, stage, latency);
+}
+
+pub fn guild_count(count: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::gauge!("bismarck_guilds").set(count as f64);
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = count;
+}
+
+/// Records how long a query took, by its operation, such as `select`, and table.
+pub fn query(operation: &'static str, table: &'static str, elapsed: Duration) {
+    #[cfg(feature = "metrics")]
+    metrics::histogram!(
+        "bismarck_query_duration_seconds",
+        "operation" => operation,
+        "table" => table
+    )
+    .record(elapsed.as_secs_f64());
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = (operation, table, elapsed);
+}
+
+/// Counts a failed request to an external service, such as `wikipedia`.
+pub fn http_failure(service: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("bismarck_http_failures_total", "service" => service).increment(1);
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = service;
+}
@@ -0,0 +1,399 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use tokio::sync::{watch, Notify};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::error::{BismarckError, Error};
+use crate::metrics;
+
+/// How often due jobs are looked for when nothing wakes the scheduler sooner.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a claimed job is held before another worker may take it over, in case the worker
+/// running it died.
+const LEASE: i64 = 5 * 60;
+
+/// Attempts after which a failing job is dropped.
+const MAX_ATTEMPTS: i64 = 5;
+
+/// Delay before the first retry, doubled for every one after it.
+const BASE_BACKOFF: i64 = 30;
+
+const MAX_BACKOFF: i64 = 60 * 60;
+
+/// Delayed work, stored as JSON so it survives restarts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Job {
+    /// Sends a reminder, rescheduling it if it repeats.
+    Reminder { reminder_id: i64 },
+    /// Turns raid mode off, unless it was turned off or restarted since.
+    EndRaid {
+        guild_id: u64,
+        until: i64,
+        mod_log_channel: Option<u64>,
+    },
+    /// Gives a member the autoroles held back by the guild's delay.
+    GiveAutoroles {
+        guild_id: u64,
+        user_id: u64,
+        role_ids: Vec<u64>,
+    },
+}
+
+pub type JobFuture = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+
+#[derive(Debug)]
+struct Inner {
+    pool: SqlitePool,
+    /// Marks the jobs claimed by this process.
+    worker_id: String,
+    wake: Notify,
+    shutdown: watch::Sender<bool>,
+    runner: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Runs jobs at their due time, retrying them with backoff when they fail.
+///
+/// Jobs are claimed in the database before they run, so a job runs once even when several
+/// processes share the database.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    inner: Arc<Inner>,
+}
+
+impl Scheduler {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                pool,
+                worker_id: Uuid::new_v4().to_string(),
+                wake: Notify::new(),
+                shutdown: watch::channel(false).0,
+                runner: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Schedules a job to run at `run_at`. A job already scheduled under the same key is
+    /// replaced, so rescheduling is idempotent.
+    pub async fn schedule(&self, key: &str, job: &Job, run_at: DateTime<Utc>) -> Result<(), Error> {
+        upsert_job(key, job, run_at.timestamp(), &self.inner.pool).await?;
+        self.inner.wake.notify_one();
+
+        Ok(())
+    }
+
+    /// Cancels the job scheduled under a key, returning whether there was one.
+    pub async fn cancel(&self, key: &str) -> Result<bool, Error> {
+        Ok(delete_job_by_key(key, &self.inner.pool).await?)
+    }
+
+    /// Starts running due jobs with `handler`. Does nothing if the scheduler is already
+    /// running.
+    pub fn start<F>(&self, handler: F)
+    where
+        F: Fn(Job) -> JobFuture + Send + Sync + 'static,
+    {
+        let Ok(mut runner) = self.inner.runner.lock() else {
+            return;
+        };
+
+        if runner.is_some() {
+            return;
+        }
+
+        let scheduler = self.clone();
+        let handler = Arc::new(handler);
+
+        *runner = Some(tokio::spawn(async move {
+            scheduler.run(handler).await;
+        }));
+    }
+
+    /// Stops claiming jobs and waits for the ones running to finish.
+    pub async fn shutdown(&self) {
+        self.inner.shutdown.send_replace(true);
+
+        let runner = match self.inner.runner.lock() {
+            Ok(mut runner) => runner.take(),
+            Err(_) => None,
+        };
+
+        if let Some(runner) = runner {
+            if let Err(why) = runner.await {
+                error!("Scheduler stopped abnormally: {why:?}");
+            }
+        }
+    }
+
+    async fn run<F>(&self, handler: Arc<F>)
+    where
+        F: Fn(Job) -> JobFuture + Send + Sync + 'static,
+    {
+        let mut shutdown = self.inner.shutdown.subscribe();
+        let mut running = JoinSet::new();
+
+        while !*shutdown.borrow() {
+            while running.try_join_next().is_some() {}
+
+            match claim_job(&self.inner.worker_id, &self.inner.pool).await {
+                Ok(Some((job_id, attempts, payload))) => {
+                    let scheduler = self.clone();
+                    let handler = Arc::clone(&handler);
+
+                    running.spawn(async move {
+                        scheduler
+                            .finish(job_id, attempts, &payload, &*handler)
+                            .await;
+                    });
+
+                    continue;
+                }
+                Ok(None) => {}
+                Err(why) => error!("Couldn't claim a scheduled job: {why:?}"),
+            }
+
+            tokio::select! {
+                _ = time::sleep(POLL_INTERVAL) => {}
+                _ = self.inner.wake.notified() => {}
+                _ = shutdown.changed() => {}
+            }
+        }
+
+        info!("Waiting for {} scheduled job(s) to finish", running.len());
+        while running.join_next().await.is_some() {}
+    }
+
+    /// Runs a claimed job, then removes it or schedules its retry.
+    async fn finish<F>(&self, job_id: i64, attempts: i64, payload: &str, handler: &F)
+    where
+        F: Fn(Job) -> JobFuture,
+    {
+        let pool = &self.inner.pool;
+        let worker_id = &self.inner.worker_id;
+
+        let result = match serde_json::from_str::<Job>(payload) {
+            Ok(job) => handler(job).await,
+            Err(why) => {
+                error!("Dropping scheduled job {job_id} with unknown payload {payload}: {why}");
+                Ok(())
+            }
+        };
+
+        let outcome = match result {
+            Ok(()) => delete_job(job_id, worker_id, pool).await,
+            Err(why) if attempts >= MAX_ATTEMPTS => {
+                error!("Dropping scheduled job {job_id} after {attempts} attempts: {why:?}");
+                delete_job(job_id, worker_id, pool).await
+            }
+            Err(why) => {
+                let delay = backoff(attempts);
+                warn!("Scheduled job {job_id} failed, retrying in {delay}s: {why:?}");
+                release_job(job_id, worker_id, Utc::now().timestamp() + delay, pool).await
+            }
+        };
+
+        if let Err(why) = outcome {
+            error!("Couldn't update scheduled job {job_id}: {why:?}");
+        }
+    }
+}
+
+/// Seconds to wait before retrying a job that failed `attempts` times.
+fn backoff(attempts: i64) -> i64 {
+    let doublings = (attempts - 1).clamp(0, 16) as u32;
+
+    (BASE_BACKOFF << doublings).min(MAX_BACKOFF)
+}
+
+pub async fn upsert_job(key: &str, job: &Job, run_at: i64, pool: &SqlitePool) -> Result<(), Error> {
+    let start_time = Instant::now();
+
+    let payload = serde_json::to_string(job)
+        .map_err(|why| BismarckError::Internal(format!("Couldn't serialize job: {why}")))?;
+
+    // Rescheduling releases the job, so a job rescheduling itself isn't removed once it
+    // finishes.
+    let query = sqlx::query(
+        "INSERT INTO scheduled_job (key, payload, run_at) VALUES (?, ?, ?) ON CONFLICT(key) DO UPDATE SET payload = excluded.payload, run_at = excluded.run_at, attempts = 0, locked_by = NULL, locked_until = NULL",
+    )
+    .bind(key)
+    .bind(payload)
+    .bind(run_at);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why.into());
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("upsert", "scheduled_job", elapsed_time);
+    info!("Upserted into Scheduled Jobs in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Claims the most overdue job nobody holds, returning its ID, attempts so far and payload.
+pub async fn claim_job(
+    worker_id: &str,
+    pool: &SqlitePool,
+) -> Result<Option<(i64, i64, String)>, sqlx::Error> {
+    let now = Utc::now().timestamp();
+
+    let row = sqlx::query(
+        "UPDATE scheduled_job SET locked_by = ?, locked_until = ?, attempts = attempts + 1 WHERE id = (SELECT id FROM scheduled_job WHERE run_at <= ? AND (locked_until IS NULL OR locked_until < ?) ORDER BY run_at LIMIT 1) RETURNING id, attempts, payload",
+    )
+    .bind(worker_id)
+    .bind(now + LEASE)
+    .bind(now)
+    .bind(now)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| (row.get(0), row.get(1), row.get(2))))
+}
+
+/// Releases a claimed job to run again at `run_at`.
+pub async fn release_job(
+    job_id: i64,
+    worker_id: &str,
+    run_at: i64,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "UPDATE scheduled_job SET run_at = ?, locked_by = NULL, locked_until = NULL WHERE id = ? AND locked_by = ?",
+    )
+    .bind(run_at)
+    .bind(job_id)
+    .bind(worker_id);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("update", "scheduled_job", elapsed_time);
+    info!("Updated Scheduled Jobs in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Removes a finished job, unless it was rescheduled while it ran.
+pub async fn delete_job(
+    job_id: i64,
+    worker_id: &str,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM scheduled_job WHERE id = ? AND locked_by = ?")
+        .bind(job_id)
+        .bind(worker_id);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "scheduled_job", elapsed_time);
+    info!("Deleted from Scheduled Jobs in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+pub async fn delete_job_by_key(key: &str, pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM scheduled_job WHERE key = ?").bind(key);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "scheduled_job", elapsed_time);
+    info!("Deleted from Scheduled Jobs in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod scheduler_tests {
+    use super::*;
+    use crate::testing::pool;
+
+    #[test]
+    fn backoff_test() {
+        assert_eq!(backoff(1), 30);
+        assert_eq!(backoff(2), 60);
+        assert_eq!(backoff(4), 240);
+        assert_eq!(backoff(100), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn payload_test() {
+        let job = Job::Reminder { reminder_id: 7 };
+        let payload = serde_json::to_string(&job).unwrap();
+
+        // Matches the payloads written by the migration scheduling existing reminders.
+        assert_eq!(payload, r#"{"kind":"reminder","reminder_id":7}"#);
+        assert_eq!(serde_json::from_str::<Job>(&payload).unwrap(), job);
+    }
+
+    #[tokio::test]
+    async fn claim_once_test() {
+        let pool = pool().await;
+        let job = Job::Reminder { reminder_id: 1 };
+        let now = Utc::now().timestamp();
+
+        upsert_job("due", &job, now - 10, &pool).await.unwrap();
+        upsert_job("later", &job, now + 3600, &pool).await.unwrap();
+
+        let (job_id, attempts, _) = claim_job("a", &pool).await.unwrap().unwrap();
+        assert_eq!(attempts, 1);
+
+        // Held by the first worker, and the other job isn't due yet.
+        assert!(claim_job("b", &pool).await.unwrap().is_none());
+
+        // Only the worker holding a job can finish it.
+        delete_job(job_id, "b", &pool).await.unwrap();
+        assert!(!delete_job_by_key("missing", &pool).await.unwrap());
+        delete_job(job_id, "a", &pool).await.unwrap();
+        assert!(!delete_job_by_key("due", &pool).await.unwrap());
+        assert!(delete_job_by_key("later", &pool).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn reschedule_while_running_test() {
+        let pool = pool().await;
+        let job = Job::Reminder { reminder_id: 1 };
+        let now = Utc::now().timestamp();
+
+        upsert_job("repeat", &job, now - 10, &pool).await.unwrap();
+        let (job_id, _, _) = claim_job("a", &pool).await.unwrap().unwrap();
+
+        // A repeating job schedules its next run before it finishes.
+        upsert_job("repeat", &job, now + 60, &pool).await.unwrap();
+        delete_job(job_id, "a", &pool).await.unwrap();
+
+        assert!(delete_job_by_key("repeat", &pool).await.unwrap());
+    }
+}
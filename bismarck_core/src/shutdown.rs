@@ -0,0 +1,123 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+
+/// Coordinates shutting the bot down: background tasks stop on its token, and commands are
+/// tracked so they get a chance to finish first.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    running: Arc<AtomicUsize>,
+    idle: Arc<Notify>,
+}
+
+/// Held for as long as a command runs.
+#[derive(Debug)]
+pub struct CommandGuard {
+    running: Arc<AtomicUsize>,
+    idle: Arc<Notify>,
+}
+
+impl Drop for CommandGuard {
+    fn drop(&mut self) {
+        if self.running.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancelled once shutting down starts. Every spawned task should stop on it.
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Starts shutting down.
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Waits until shutting down starts.
+    pub async fn triggered(&self) {
+        self.token.cancelled().await;
+    }
+
+    /// Marks a command as running until the guard is dropped.
+    pub fn track_command(&self) -> CommandGuard {
+        self.running.fetch_add(1, Ordering::AcqRel);
+
+        CommandGuard {
+            running: Arc::clone(&self.running),
+            idle: Arc::clone(&self.idle),
+        }
+    }
+
+    pub fn running_commands(&self) -> usize {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Waits for running commands to finish, giving up after `deadline`. Returns whether they
+    /// all finished.
+    pub async fn wait_for_commands(&self, deadline: Duration) -> bool {
+        let wait = async {
+            loop {
+                let idle = self.idle.notified();
+
+                if self.running_commands() == 0 {
+                    return;
+                }
+
+                idle.await;
+            }
+        };
+
+        time::timeout(deadline, wait).await.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod shutdown_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn wait_for_commands_test() {
+        let shutdown = Shutdown::new();
+        assert!(shutdown.wait_for_commands(Duration::ZERO).await);
+
+        let guard = shutdown.track_command();
+        assert_eq!(shutdown.running_commands(), 1);
+        assert!(!shutdown.wait_for_commands(Duration::from_millis(10)).await);
+
+        let waiting = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.wait_for_commands(Duration::from_secs(5)).await }
+        });
+        drop(guard);
+
+        assert!(waiting.await.unwrap());
+        assert_eq!(shutdown.running_commands(), 0);
+    }
+
+    #[test]
+    fn trigger_test() {
+        let shutdown = Shutdown::new();
+        let token = shutdown.token();
+
+        shutdown.trigger();
+
+        assert!(shutdown.is_triggered());
+        assert!(token.is_cancelled());
+    }
+}
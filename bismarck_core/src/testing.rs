@@ -0,0 +1,25 @@
+//! Helpers for tests across the workspace, enabled by the `testing` feature.
+
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+/// Connects to a new in-memory database with every migration ran.
+pub async fn pool() -> SqlitePool {
+    // A single connection, so that queries run one after another as they are awaited.
+    shared_pool(1).await
+}
+
+/// Connects to a new in-memory database with every migration ran, through up to
+/// `max_connections` connections, for tests running queries concurrently.
+pub async fn shared_pool(max_connections: u32) -> SqlitePool {
+    // sqlx opens `:memory:` with a shared cache under a name unique to the pool, so its
+    // connections all see the same database.
+    let pool = SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    crate::migrate(&pool.connect_options()).await.unwrap();
+
+    pool
+}
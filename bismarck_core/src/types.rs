@@ -1,3 +1,4 @@
+use poise::serenity_prelude::Timestamp;
 use serde::Deserialize;
 
 // Guild settings type below
@@ -9,6 +10,7 @@ pub struct GuildSettings {
     pub mute_type: String,
     pub mute_role: u64,
     pub default_mute_duration: u64,
+    pub mod_log_channel: Option<u64>,
 }
 
 // Guild stat type below
@@ -19,6 +21,153 @@ pub struct GuildStats {
     pub songs_played: u64,
 }
 
+// Automod settings types below
+
+/// How much language the word filter lets through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum AutomodStrictness {
+    /// Only severe profanity, slurs and sexual language.
+    #[name = "low"]
+    Low,
+    /// Moderate or severe inappropriate language.
+    #[name = "medium"]
+    Medium,
+    /// Any inappropriate language, however mild.
+    #[name = "high"]
+    High,
+}
+
+/// What automod does with a message it catches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum AutomodAction {
+    #[name = "delete"]
+    Delete,
+    #[name = "warn"]
+    Warn,
+    #[name = "timeout"]
+    Timeout,
+}
+
+#[derive(Debug, Clone)]
+pub struct AutomodSettings {
+    pub enabled: bool,
+    pub strictness: AutomodStrictness,
+    pub action: AutomodAction,
+    /// In seconds, used when the action is a timeout.
+    pub timeout_duration: u64,
+    /// Lowercase words that are never filtered.
+    pub allowed_words: Vec<String>,
+    /// Lowercase words that are always filtered.
+    pub denied_words: Vec<String>,
+    pub exempt_roles: Vec<u64>,
+    pub exempt_channels: Vec<u64>,
+    /// Most messages a user may send within `message_window`, `0` to allow any.
+    pub message_limit: u32,
+    /// In seconds.
+    pub message_window: u64,
+    /// Most identical messages a user may send within a minute, `0` to allow any.
+    pub duplicate_limit: u32,
+    /// Most users and roles a message may mention, `0` to allow any.
+    pub mention_limit: u32,
+    pub filter_invites: bool,
+    /// Whether links to domains outside of `allowed_domains` are filtered.
+    pub filter_links: bool,
+    /// Lowercase domains, which also allow their subdomains.
+    pub allowed_domains: Vec<String>,
+}
+
+impl Default for AutomodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strictness: AutomodStrictness::Medium,
+            action: AutomodAction::Delete,
+            timeout_duration: 600,
+            allowed_words: Vec::new(),
+            denied_words: Vec::new(),
+            exempt_roles: Vec::new(),
+            exempt_channels: Vec::new(),
+            message_limit: 5,
+            message_window: 5,
+            duplicate_limit: 3,
+            mention_limit: 5,
+            filter_invites: false,
+            filter_links: false,
+            allowed_domains: Vec::new(),
+        }
+    }
+}
+
+// Anti-raid settings types below
+
+/// What raid mode does with members joining while it is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum RaidAction {
+    #[name = "kick"]
+    Kick,
+    #[name = "timeout"]
+    Timeout,
+}
+
+#[derive(Debug, Clone)]
+pub struct RaidSettings {
+    pub enabled: bool,
+    /// Most members that may join within `join_window`, `0` to allow any.
+    pub join_limit: u32,
+    /// In seconds.
+    pub join_window: u64,
+    /// Most accounts younger than `new_account_age` that may join within `join_window`, `0` to
+    /// allow any.
+    pub new_account_limit: u32,
+    /// In seconds.
+    pub new_account_age: u64,
+    pub action: RaidAction,
+    /// How long raid mode stays on for, in seconds.
+    pub cooldown: u64,
+    /// When raid mode turns off, if it is on.
+    pub raid_until: Option<Timestamp>,
+    /// The verification level from before raid mode raised it, if it did.
+    pub previous_verification: Option<u8>,
+}
+
+impl Default for RaidSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            join_limit: 10,
+            join_window: 10,
+            new_account_limit: 5,
+            new_account_age: 7 * 24 * 60 * 60,
+            action: RaidAction::Kick,
+            cooldown: 15 * 60,
+            raid_until: None,
+            previous_verification: None,
+        }
+    }
+}
+
+// Starboard settings type below
+
+#[derive(Debug, Clone)]
+pub struct StarboardSettings {
+    /// Where starred messages are reposted, none turning the starboard off.
+    pub channel_id: Option<u64>,
+    /// A unicode emoji, or a custom emoji as `<:name:id>`.
+    pub emoji: String,
+    /// Stars a message needs to be reposted.
+    pub threshold: u32,
+}
+
+impl Default for StarboardSettings {
+    fn default() -> Self {
+        Self {
+            channel_id: None,
+            emoji: "⭐".to_string(),
+            threshold: 3,
+        }
+    }
+}
+
 // Wish type below
 
 #[derive(Debug, Clone)]
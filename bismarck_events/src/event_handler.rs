@@ -1,12 +1,27 @@
+use dashmap::DashMap;
 use poise::serenity_prelude as serenity;
 use serenity::{ActivityData, CreateAllowedMentions};
+use sqlx::SqlitePool;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::time;
 
-use tracing::{debug, error, info};
-
-use bismarck_core::{data::Data, error::Error, types::GuildSettings};
+use tracing::{debug, error, info, warn};
+
+use crate::interactions;
+use bismarck_core::{
+    config::PresenceConfig,
+    counters::{CommandCounters, GLOBAL_ID},
+    data::Data,
+    error::{BismarckError, Error},
+    types::GuildSettings,
+};
+use bismarck_utilities::{
+    automod::{self, ScannedMessage},
+    greeting::{self, GreetingKind},
+    jobs, modlog, raid, role_menu, roles, starboard,
+};
 
 pub async fn event_handler(
     context: &serenity::Context,
@@ -17,24 +32,30 @@ pub async fn event_handler(
     match event {
         serenity::FullEvent::Ready { data_about_bot, .. } => {
             let ready = data_about_bot;
+            let shard_info = on_ready(
+                ready,
+                &data.sqlite,
+                &data.commands_ran,
+                &data.command_counters,
+            )
+            .await?;
+
             let http = &context.http;
 
             let api_version = ready.version;
-            let bot_gateway = http.get_bot_gateway().await.unwrap();
+            let bot_gateway = http.get_bot_gateway().await?;
             let bot_owner = http
                 .get_current_application_info()
-                .await
-                .unwrap()
+                .await?
                 .owner
-                .expect("Couldn't get bot owner");
+                .map_or_else(|| "Unknown".to_string(), |owner| owner.tag());
             let t_sessions = bot_gateway.session_start_limit.total;
             let r_sessions = bot_gateway.session_start_limit.remaining;
-            let shard_info = ready.shard.unwrap();
 
             info!("Successfully logged into Discord as the following user:");
             info!("Bot username: {}", ready.user.tag());
             info!("Bot user ID: {}", ready.user.id);
-            info!("Bot owner: {}", bot_owner.tag());
+            info!("Bot owner: {}", bot_owner);
 
             let guild_count = ready.guilds.len();
 
@@ -44,48 +65,61 @@ pub async fn event_handler(
             );
             info!("Connected to the Discord API (version {api_version}) with {r_sessions}/{t_sessions} sessions remaining.");
             info!("Connected to and serving a total of {guild_count} guild(s).");
-
-            // Cache is ready, now we get the total number of guilds with their commands ran and set the global commands to that
-            let sum_commands =
-                sqlx::query!("SELECT SUM(commands_ran) as commands_ran_sum FROM guild")
-                    .fetch_one(&data.sqlite)
-                    .await
-                    .unwrap()
-                    .commands_ran_sum
-                    .unwrap() as u64;
-
-            data.commands_ran.insert(0, AtomicU64::new(sum_commands));
-
-            // debug
-            info!("Global commands ran: {}", sum_commands);
         }
         serenity::FullEvent::Message { new_message } => {
             if new_message.author.bot {
                 return Ok(());
             }
 
-            // trim the end to make it easier for mobile users
-            let content = new_message.content.trim_end();
+            if let Some(guild_id) = new_message.guild_id {
+                let roles = new_message
+                    .member
+                    .as_ref()
+                    .map_or(&[][..], |member| &member.roles[..]);
+
+                let message = ScannedMessage {
+                    guild_id,
+                    channel_id: new_message.channel_id,
+                    message_id: new_message.id,
+                    author: &new_message.author,
+                    roles,
+                    content: &new_message.content,
+                    mentions: new_message.mentions.len() + new_message.mention_roles.len(),
+                    is_new: true,
+                };
+
+                // Automod failing mustn't keep the bot from answering its mention.
+                if let Err(why) = automod::scan_message(context, data, message).await {
+                    error!(
+                        "Couldn't scan message {} in {guild_id}: {why:?}",
+                        new_message.id
+                    );
+                }
+            }
 
             // calls bot by its mention only, let's respond with an embed telling them what the prefix for help command is
-            if content == "<@!1183487567094632638>" || content == "<@1183487567094632638>" {
+            let bot_id = context.cache.current_user().id;
+            let mentioned = new_message
+                .mentions
+                .iter()
+                .map(|user| user.id)
+                .collect::<Vec<_>>();
+
+            if is_only_mention(&new_message.content, &mentioned, bot_id) {
                 debug!("Received mention from {}", new_message.author.id);
 
-                let prefix = {
-                    let guild_data = &data.guild_data;
-                    let pf = guild_data;
-                    pf.get(&new_message.guild_id.unwrap().get())
-                        .unwrap()
-                        .prefix
-                        .clone()
-                };
+                // DMs and guilds without settings use the default prefix
+                let prefix = new_message
+                    .guild_id
+                    .and_then(|guild_id| data.guild_data.get(&guild_id.get()))
+                    .map_or_else(
+                        || data.config.bot.default_prefix.clone(),
+                        |settings| settings.prefix.clone(),
+                    );
 
                 let embed = serenity::builder::CreateEmbed::new()
                     .title("**Hello!**")
-                    .description(format!(
-                        "```To see the list of commands type {}help```",
-                        prefix
-                    ));
+                    .description(data.config.bot.mention_reply.replace("{prefix}", &prefix));
 
                 let builder = serenity::builder::CreateMessage::new()
                     .add_embed(embed)
@@ -97,23 +131,138 @@ pub async fn event_handler(
                 new_message
                     .channel_id
                     .send_message(&context, builder)
-                    .await
-                    .unwrap();
+                    .await?;
+            }
+        }
+        serenity::FullEvent::MessageUpdate { event, .. } => {
+            // Only edits that change the content carry it, along with the author.
+            if let (Some(guild_id), Some(author), Some(content)) =
+                (event.guild_id, &event.author, &event.content)
+            {
+                let roles = event
+                    .member
+                    .as_ref()
+                    .and_then(|member| member.as_ref())
+                    .map_or(&[][..], |member| &member.roles[..]);
+
+                let message = ScannedMessage {
+                    guild_id,
+                    channel_id: event.channel_id,
+                    message_id: event.id,
+                    author,
+                    roles,
+                    content,
+                    mentions: event.mentions.as_ref().map_or(0, Vec::len)
+                        + event.mention_roles.as_ref().map_or(0, Vec::len),
+                    is_new: false,
+                };
+
+                automod::scan_message(context, data, message).await?;
+            }
+        }
+        serenity::FullEvent::GuildMemberAddition { new_member } => {
+            let (user, guild_id) = (&new_member.user, new_member.guild_id);
+
+            // Bookkeeping failing mustn't keep anti-raid from acting on the join.
+            if let Some(joined_at) = new_member.joined_at {
+                if let Err(why) =
+                    modlog::upsert_join_date(&user.id, &guild_id, joined_at, &data.sqlite).await
+                {
+                    error!("Couldn't record join of {} in {guild_id}: {why:?}", user.id);
+                }
+            }
+
+            // Given back before anti-raid acts, so leaving doesn't shake off a mute role.
+            if let Err(why) = roles::restore_roles(context, &data.sqlite, new_member).await {
+                error!(
+                    "Couldn't restore roles of {} in {guild_id}: {why:?}",
+                    user.id
+                );
+            }
+
+            let moderated = raid::on_member_join(context, data, new_member).await?;
+
+            if !moderated {
+                roles::give_autoroles(context, &data.sqlite, &data.scheduler, new_member).await?;
+            }
+
+            if !moderated && !user.bot {
+                greeting::send_greeting(
+                    context,
+                    &data.sqlite,
+                    GreetingKind::Welcome,
+                    guild_id,
+                    user,
+                )
+                .await?;
+            }
+        }
+        serenity::FullEvent::GuildMemberRemoval {
+            guild_id,
+            user,
+            member_data_if_available,
+        } => {
+            // Saving roles failing mustn't keep the goodbye message from being sent.
+            if let Err(why) = roles::save_roles(
+                &data.sqlite,
+                *guild_id,
+                user.id,
+                member_data_if_available.as_ref(),
+            )
+            .await
+            {
+                error!("Couldn't save roles of {} in {guild_id}: {why:?}", user.id);
+            }
+
+            if !user.bot {
+                greeting::send_greeting(
+                    context,
+                    &data.sqlite,
+                    GreetingKind::Goodbye,
+                    *guild_id,
+                    user,
+                )
+                .await?;
             }
         }
+        serenity::FullEvent::InteractionCreate {
+            interaction: serenity::Interaction::Component(interaction),
+        } => {
+            interactions::route_component(context, data, interaction).await?;
+        }
+        serenity::FullEvent::ReactionAdd { add_reaction } => {
+            role_menu::handle_reaction(context, &data.sqlite, add_reaction, true).await?;
+            starboard::update_stars(context, data, add_reaction).await?;
+        }
+        serenity::FullEvent::ReactionRemove { removed_reaction } => {
+            role_menu::handle_reaction(context, &data.sqlite, removed_reaction, false).await?;
+            starboard::update_stars(context, data, removed_reaction).await?;
+        }
+        serenity::FullEvent::ReactionRemoveEmoji { removed_reactions } => {
+            starboard::update_stars(context, data, removed_reactions).await?;
+        }
+        serenity::FullEvent::ReactionRemoveAll {
+            removed_from_message_id,
+            ..
+        } => {
+            starboard::remove_post(context, data, *removed_from_message_id).await?;
+        }
         serenity::FullEvent::ThreadCreate { thread } => {
             if let Err(err) = thread.id.join_thread(&context.http).await {
                 let thread_id = thread.id;
                 error!("Failed to succesfully join thread (ID: {thread_id}): {err}")
             } else {
                 let name = &thread.name;
-                let guild = &thread.guild(&context.cache).unwrap().name;
+                let guild = thread
+                    .guild(&context.cache)
+                    .map_or_else(|| thread.guild_id.to_string(), |guild| guild.name.clone());
                 let id = thread.id.get();
                 error!("Joined new thread: {name} (Server: {guild}, ID: {id})")
             }
         }
         serenity::FullEvent::CacheReady { guilds } => {
             info!("Cache is ready with {} guilds", guilds.len());
+            data.health.set_cache_ready();
 
             // We need to check that the loop is not already running when this event triggers, as this
             // event triggers every time the bot enters or leaves a guild, along every time the ready
@@ -122,145 +271,418 @@ pub async fn event_handler(
                 // And of course, we can run more than one thread at different timings.'
                 let guild_len = guilds.len();
                 let cloned = context.clone();
-                let mut interval = time::interval(Duration::from_secs(3));
+                let presence = data.config.presence.clone();
+                let default_prefix = data.config.bot.default_prefix.clone();
+                let mut interval = time::interval(Duration::from_secs(presence.interval));
+
+                let token = data.shutdown.token();
 
                 tokio::spawn(async move {
-                    loop {
-                        set_activity(&cloned, guild_len);
-                        interval.tick().await;
-                        set_ad(&cloned);
-                        interval.tick().await;
-                    }
+                    token
+                        .run_until_cancelled(async move {
+                            loop {
+                                set_activity(&cloned, &presence, &default_prefix, guild_len);
+                                interval.tick().await;
+                                set_ad(&cloned, &presence);
+                                interval.tick().await;
+                            }
+                        })
+                        .await;
                 });
 
+                let job_context = jobs::JobContext {
+                    context: context.clone(),
+                    pool: data.sqlite.clone(),
+                    raids: Arc::clone(&data.raid),
+                    scheduler: data.scheduler.clone(),
+                };
+                data.scheduler
+                    .start(move |job| Box::pin(jobs::run(job_context.clone(), job)));
+
                 // Now that the loop is running, we set the bool to true
                 data.is_loop_running.swap(true, Ordering::Relaxed);
             }
         }
         serenity::FullEvent::GuildCreate { guild, is_new: _ } => {
-            // write into database and hashmap
-            info!("Connected to guild: {}", guild.name);
-            info!("Guild ID: {}", guild.id);
-            info!("Guild Owner ID: {}", guild.owner_id);
-            info!("Guild Members: {}", guild.member_count);
-
-            let database = data.sqlite.clone();
-            let (guild_id, owner_id) = {
-                let guild_id = i64::from(guild.id);
-                let owner_id = i64::from(guild.owner_id);
-
-                (guild_id, owner_id)
-            };
-
-            let owner_query = sqlx::query!(
-                "INSERT INTO user (
-                    id
-                ) VALUES (?)
-                ON CONFLICT DO NOTHING",
-                owner_id
-            )
-            .execute(&database)
-            .await
-            .unwrap();
-
-            debug!("Owner Query: {owner_query:?}");
-
-            let query = sqlx::query!(
-                "INSERT INTO guild (
-                    id,
-                    prefix,
-                    owner,
-                    commands_ran,
-                    songs_played
-                ) VALUES (?, ?, ?, ?, ?) ON CONFLICT DO NOTHING",
-                guild_id,
-                "+",
-                owner_id,
-                0,
-                0
-            )
-            .execute(&database)
-            .await
-            .unwrap();
-
-            debug!("Guild Settings Query: {query:?}");
-
-            let fetched_guild = sqlx::query!("SELECT * FROM guild WHERE id = ?", guild_id,)
-                .fetch_one(&database)
-                .await
-                .unwrap();
-
-            // TODO: cleanup
-            let fetched_bot_stats = sqlx::query!(
-                "SELECT commands_ran, songs_played FROM guild WHERE id = ?",
-                guild_id,
-            )
-            .fetch_one(&database)
-            .await
-            .unwrap();
+            let loaded = load_guild(guild, &data.config.bot.default_prefix, &data.sqlite).await?;
 
-            let owner_id_u64 = owner_id as u64;
-            let guild_id_u64 = guild_id as u64;
+            let guild_id_u64 = guild.id.get();
 
-            let commands_ran = fetched_bot_stats.commands_ran as u64;
-            let songs_played = fetched_bot_stats.songs_played as u64;
-
-            data.commands_ran
-                .insert(guild_id_u64, AtomicU64::new(commands_ran));
+            seed_commands_ran(
+                &data.commands_ran,
+                &data.command_counters,
+                guild_id_u64,
+                loaded.commands_ran,
+            );
             data.songs_played
-                .insert(guild_id_u64, AtomicU64::new(songs_played));
-
-            let data_to_set = GuildSettings {
-                prefix: fetched_guild.prefix,
-                owner_id: owner_id_u64,
-                mute_type: fetched_guild.mute_style.to_string(),
-                mute_role: fetched_guild.mute_role.unwrap_or_default() as u64,
-                default_mute_duration: fetched_guild.mute_duration as u64,
-            };
+                .insert(guild_id_u64, AtomicU64::new(loaded.songs_played));
 
             {
                 let guild_settings = &data.guild_data;
-                guild_settings.insert(guild_id_u64, data_to_set);
+                guild_settings.insert(guild_id_u64, loaded.settings);
+            }
+
+            if let Err(why) = raid::resume_raid(data, guild.id).await {
+                error!("Couldn't resume raid mode in guild {}: {why:?}", guild.id);
             }
 
             info!("Guild settings set complete for guild {}", guild.name);
         }
-        serenity::FullEvent::GuildDelete {
-            incomplete: _,
-            full,
-        } => {
-            let guild = full.clone().unwrap();
-            info!("Left guild: {}", guild.name);
-            // write into database and hashmap
-            {
-                let database = data.sqlite.clone();
-                let guild_id = i64::from(guild.id);
-                sqlx::query!("DELETE FROM guild WHERE id = ?", guild_id)
-                    .execute(&database)
-                    .await
-                    .unwrap();
-
-                info!("Guild settings removed for guild {}", guild.name);
+        serenity::FullEvent::GuildDelete { incomplete, full } => {
+            let Some(guild_id) = remove_guild(incomplete, full.as_ref(), &data.sqlite).await?
+            else {
+                return Ok(());
+            };
 
-                let guild_id = u64::from(guild.id);
+            let guild_id = guild_id.get();
 
-                data.guild_data.remove(&guild_id);
-                data.commands_ran.remove(&guild_id);
-            }
+            data.guild_data.remove(&guild_id);
+            data.commands_ran.remove(&guild_id);
+            data.automod.remove(&guild_id);
+            data.raid.remove(&guild_id);
+            data.recent_joins.remove(&guild_id);
+            data.starboard.remove(&guild_id);
         }
         _ => {}
     }
     Ok(())
 }
 
-fn set_activity(context: &serenity::Context, guild_count: usize) {
-    let presence = format!("Monitoring a total of {guild_count} guilds | -help");
+/// Sets the global command count from the database and finds the shard a `Ready` is for,
+/// everything handling it needs besides Discord's API.
+async fn on_ready(
+    ready: &serenity::Ready,
+    pool: &SqlitePool,
+    commands_ran: &DashMap<u64, AtomicU64>,
+    counters: &CommandCounters,
+) -> Result<serenity::ShardInfo, Error> {
+    // Cache is ready, now we get the total number of guilds with their commands ran and set the global commands to that
+    let sum_commands = sqlx::query!("SELECT SUM(commands_ran) as commands_ran_sum FROM guild")
+        .fetch_one(pool)
+        .await?
+        .commands_ran_sum
+        // No guilds yet
+        .unwrap_or_default() as u64;
+
+    let global = seed_commands_ran(commands_ran, counters, GLOBAL_ID, sum_commands);
+
+    // debug
+    info!("Global commands ran: {}", global);
+
+    shard_info(ready)
+}
+
+/// Sets a command count from the database, unless it's already counted in memory. The database
+/// lags behind by the counts not flushed yet, so those are added, and a count set before, such
+/// as on an earlier `Ready`, is never replaced. Returns the count in memory.
+fn seed_commands_ran(
+    commands_ran: &DashMap<u64, AtomicU64>,
+    counters: &CommandCounters,
+    guild_id: u64,
+    stored: u64,
+) -> u64 {
+    commands_ran
+        .entry(guild_id)
+        .or_insert_with(|| AtomicU64::new(stored + counters.pending_commands(guild_id)))
+        .load(Ordering::Relaxed)
+}
+
+/// A guild's settings and counts, as stored in the database.
+struct LoadedGuild {
+    settings: GuildSettings,
+    commands_ran: u64,
+    songs_played: u64,
+}
+
+/// Stores a guild the bot is in, along with its owner, if it's new, and loads it.
+async fn load_guild(
+    guild: &serenity::Guild,
+    default_prefix: &str,
+    database: &SqlitePool,
+) -> Result<LoadedGuild, Error> {
+    // write into database and hashmap
+    info!("Connected to guild: {}", guild.name);
+    info!("Guild ID: {}", guild.id);
+    info!("Guild Owner ID: {}", guild.owner_id);
+    info!("Guild Members: {}", guild.member_count);
+
+    let (guild_id, owner_id) = {
+        let guild_id = i64::from(guild.id);
+        let owner_id = i64::from(guild.owner_id);
+
+        (guild_id, owner_id)
+    };
+
+    let owner_query = sqlx::query!(
+        "INSERT INTO user (
+            id
+        ) VALUES (?)
+        ON CONFLICT DO NOTHING",
+        owner_id
+    )
+    .execute(database)
+    .await?;
+
+    debug!("Owner Query: {owner_query:?}");
+
+    let query = sqlx::query!(
+        "INSERT INTO guild (
+            id,
+            prefix,
+            owner,
+            commands_ran,
+            songs_played
+        ) VALUES (?, ?, ?, ?, ?) ON CONFLICT DO NOTHING",
+        guild_id,
+        default_prefix,
+        owner_id,
+        0,
+        0
+    )
+    .execute(database)
+    .await?;
+
+    debug!("Guild Settings Query: {query:?}");
+
+    let fetched_guild = sqlx::query!("SELECT * FROM guild WHERE id = ?", guild_id,)
+        .fetch_one(database)
+        .await?;
+
+    let settings = GuildSettings {
+        prefix: fetched_guild.prefix,
+        owner_id: owner_id as u64,
+        mute_type: fetched_guild.mute_style.to_string(),
+        mute_role: fetched_guild.mute_role.unwrap_or_default() as u64,
+        default_mute_duration: fetched_guild.mute_duration as u64,
+        mod_log_channel: fetched_guild.mod_log_channel.map(|id| id as u64),
+    };
+
+    Ok(LoadedGuild {
+        settings,
+        commands_ran: fetched_guild.commands_ran as u64,
+        songs_played: fetched_guild.songs_played as u64,
+    })
+}
+
+/// Deletes a guild the bot left from the database, returning it. Unavailable guilds are kept.
+async fn remove_guild(
+    incomplete: &serenity::UnavailableGuild,
+    full: Option<&serenity::Guild>,
+    database: &SqlitePool,
+) -> Result<Option<serenity::GuildId>, Error> {
+    let Some(guild_id) = left_guild(incomplete) else {
+        warn!("Guild {} is unavailable", incomplete.id);
+        return Ok(None);
+    };
+
+    // Not cached if the bot left before the guild was received
+    let name = full.map_or_else(|| guild_id.to_string(), |guild| guild.name.clone());
+    info!("Left guild: {}", name);
+
+    let id = i64::from(guild_id);
+    sqlx::query!("DELETE FROM guild WHERE id = ?", id)
+        .execute(database)
+        .await?;
+
+    info!("Guild settings removed for guild {}", name);
+
+    Ok(Some(guild_id))
+}
+
+/// The shard a `Ready` is for, which Discord always sends when sharding.
+fn shard_info(ready: &serenity::Ready) -> Result<serenity::ShardInfo, BismarckError> {
+    ready
+        .shard
+        .ok_or_else(|| BismarckError::Internal("Ready without shard info".to_string()))
+}
+
+/// The guild the bot left or was removed from, if any. Discord also deletes guilds going
+/// unavailable in an outage, but the bot is still in those, so they are skipped.
+fn left_guild(incomplete: &serenity::UnavailableGuild) -> Option<serenity::GuildId> {
+    (!incomplete.unavailable).then_some(incomplete.id)
+}
+
+/// Whether a message does nothing but mention the bot, as found in its mentions, ignoring
+/// whitespace.
+fn is_only_mention(
+    content: &str,
+    mentioned: &[serenity::UserId],
+    bot_id: serenity::UserId,
+) -> bool {
+    if mentioned != [bot_id] {
+        return false;
+    }
+
+    content
+        .replace(&format!("<@!{bot_id}>"), "")
+        .replace(&format!("<@{bot_id}>"), "")
+        .trim()
+        .is_empty()
+}
+
+fn set_activity(
+    context: &serenity::Context,
+    presence: &PresenceConfig,
+    default_prefix: &str,
+    guild_count: usize,
+) {
+    let presence = presence
+        .playing
+        .replace("{guilds}", &guild_count.to_string())
+        .replace("{prefix}", default_prefix);
 
     context.set_activity(Some(ActivityData::playing(presence)));
 }
 
-fn set_ad(context: &serenity::Context) {
-    let presence = format!("On Shard {} | Flottenstützpunkt Hamburg", context.shard_id);
+fn set_ad(context: &serenity::Context, presence: &PresenceConfig) {
+    let presence = presence
+        .listening
+        .replace("{shard}", &context.shard_id.to_string());
 
     context.set_activity(Some(ActivityData::listening(presence)));
 }
+
+#[cfg(test)]
+mod event_handler_tests {
+    use super::*;
+    use bismarck_core::testing::pool;
+    use poise::serenity_prelude::{GuildId, Ready, UnavailableGuild, UserId};
+    use serde_json::json;
+
+    fn ready(shard: Option<[u32; 2]>) -> Ready {
+        serde_json::from_value(json!({
+            "v": 10,
+            "user": {
+                "id": "10",
+                "username": "bismarck",
+                "discriminator": "0",
+                "avatar": null,
+                "bot": true
+            },
+            "guilds": [],
+            "session_id": "session",
+            "resume_gateway_url": "wss://gateway.discord.gg",
+            "shard": shard,
+            "application": { "id": "10", "flags": 0 }
+        }))
+        .unwrap()
+    }
+
+    fn unavailable_guild(unavailable: bool) -> UnavailableGuild {
+        serde_json::from_value(json!({
+            "id": "1",
+            "unavailable": unavailable
+        }))
+        .unwrap()
+    }
+
+    fn guild(id: u64, owner_id: u64) -> serenity::Guild {
+        let mut guild = serenity::Guild::default();
+        guild.id = GuildId::new(id);
+        guild.owner_id = UserId::new(owner_id);
+        guild
+    }
+
+    #[tokio::test]
+    async fn ready_test() {
+        let pool = pool().await;
+        let commands_ran = DashMap::new();
+        let counters = CommandCounters::new();
+
+        // Without shard info, and without any guild to sum the command counts of.
+        assert!(matches!(
+            on_ready(&ready(None), &pool, &commands_ran, &counters).await,
+            Err(BismarckError::Internal(_))
+        ));
+        assert_eq!(
+            commands_ran
+                .get(&GLOBAL_ID)
+                .unwrap()
+                .load(Ordering::Relaxed),
+            0
+        );
+
+        let shard_info = on_ready(&ready(Some([0, 1])), &pool, &commands_ran, &counters)
+            .await
+            .unwrap();
+        assert_eq!(shard_info.total, 1);
+    }
+
+    #[test]
+    fn seed_commands_ran_test() {
+        let commands_ran = DashMap::new();
+        let counters = CommandCounters::new();
+
+        // Ran before the guild was received, and not flushed yet.
+        counters.record(Some(1), 10);
+        assert_eq!(seed_commands_ran(&commands_ran, &counters, 1, 5), 6);
+
+        // Reconnecting doesn't drop counts the database doesn't have yet.
+        commands_ran
+            .get(&1)
+            .unwrap()
+            .fetch_add(1, Ordering::Relaxed);
+        assert_eq!(seed_commands_ran(&commands_ran, &counters, 1, 5), 7);
+    }
+
+    #[tokio::test]
+    async fn guild_create_test() {
+        let pool = pool().await;
+
+        // The owner isn't in the user table yet.
+        let loaded = load_guild(&guild(1, 10), "+", &pool).await.unwrap();
+        assert_eq!(loaded.settings.prefix, "+");
+        assert_eq!(loaded.settings.owner_id, 10);
+        assert_eq!(loaded.commands_ran, 0);
+
+        // Received again on reconnecting, keeping the settings.
+        let loaded = load_guild(&guild(1, 10), "-", &pool).await.unwrap();
+        assert_eq!(loaded.settings.prefix, "+");
+    }
+
+    #[tokio::test]
+    async fn guild_delete_test() {
+        let pool = pool().await;
+        load_guild(&guild(1, 10), "+", &pool).await.unwrap();
+
+        let guilds = || async {
+            sqlx::query!("SELECT id FROM guild")
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+                .len()
+        };
+
+        // Still in the guild, so its settings are kept.
+        assert_eq!(
+            remove_guild(&unavailable_guild(true), None, &pool)
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(guilds().await, 1);
+
+        // Never cached, as when the bot is removed before the guild is received.
+        assert_eq!(
+            remove_guild(&unavailable_guild(false), None, &pool)
+                .await
+                .unwrap(),
+            Some(GuildId::new(1))
+        );
+        assert_eq!(guilds().await, 0);
+    }
+
+    #[test]
+    fn is_only_mention_test() {
+        let bot_id = UserId::new(10);
+        let other_id = UserId::new(20);
+
+        assert!(is_only_mention("<@10>", &[bot_id], bot_id));
+        assert!(is_only_mention(" <@!10>\n", &[bot_id], bot_id));
+        assert!(!is_only_mention("<@10> help", &[bot_id], bot_id));
+        // Replying to the bot mentions it without it being in the content.
+        assert!(!is_only_mention("hello", &[bot_id], bot_id));
+        assert!(!is_only_mention("<@10> <@20>", &[bot_id, other_id], bot_id));
+        assert!(!is_only_mention("<@20>", &[other_id], bot_id));
+    }
+}
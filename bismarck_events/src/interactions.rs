@@ -0,0 +1,95 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use poise::serenity_prelude as serenity;
+use serenity::ComponentInteraction;
+use tracing::debug;
+
+use bismarck_core::{data::Data, error::Error};
+use bismarck_utilities::{paginate, role_menu, ticket, wiki};
+
+type ComponentFuture<'a> = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'a>>;
+
+type ComponentHandler =
+    for<'a> fn(&'a serenity::Context, &'a Data, &'a ComponentInteraction) -> ComponentFuture<'a>;
+
+/// Component handlers by the custom ID prefix they own.
+///
+/// Handlers keep their state in the database or in the custom ID itself rather than in a
+/// collector, so components keep working after the command returns and across restarts.
+const COMPONENT_HANDLERS: &[(&str, ComponentHandler)] = &[
+    (paginate::CUSTOM_ID_PREFIX, |context, data, interaction| {
+        Box::pin(paginate::handle_component(
+            context,
+            &data.sqlite,
+            interaction,
+        ))
+    }),
+    (role_menu::CUSTOM_ID_PREFIX, |context, data, interaction| {
+        Box::pin(role_menu::handle_component(
+            context,
+            &data.sqlite,
+            interaction,
+        ))
+    }),
+    (ticket::CUSTOM_ID_PREFIX, |context, data, interaction| {
+        Box::pin(ticket::handle_component(context, &data.sqlite, interaction))
+    }),
+    (wiki::CUSTOM_ID_PREFIX, |context, data, interaction| {
+        Box::pin(wiki::handle_component(
+            context,
+            &data.reqwest,
+            &data.config.api.wikipedia,
+            interaction,
+        ))
+    }),
+];
+
+/// The part of a custom ID before its first colon, which names the handler that owns it.
+fn prefix(custom_id: &str) -> &str {
+    custom_id.split(':').next().unwrap_or_default()
+}
+
+/// Sends a component interaction to the handler owning its custom ID.
+pub async fn route_component(
+    context: &serenity::Context,
+    data: &Data,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let custom_id = &interaction.data.custom_id;
+
+    match COMPONENT_HANDLERS
+        .iter()
+        .find(|(handler_prefix, _)| *handler_prefix == prefix(custom_id))
+    {
+        Some((_, handler)) => handler(context, data, interaction).await,
+        None => {
+            debug!("No handler for component {custom_id}");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod interactions_tests {
+    use super::*;
+
+    #[test]
+    fn prefix_test() {
+        assert_eq!(prefix("page:6f1c:next:1"), "page");
+        assert_eq!(prefix("wiki"), "wiki");
+        assert_eq!(prefix(""), "");
+    }
+
+    #[test]
+    fn unique_prefixes_test() {
+        let mut prefixes = COMPONENT_HANDLERS
+            .iter()
+            .map(|(prefix, _)| *prefix)
+            .collect::<Vec<_>>();
+        prefixes.sort_unstable();
+        prefixes.dedup();
+
+        assert_eq!(prefixes.len(), COMPONENT_HANDLERS.len());
+    }
+}
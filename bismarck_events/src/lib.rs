@@ -1,2 +1,3 @@
 pub mod event_handler;
+pub mod interactions;
 pub mod on_error;
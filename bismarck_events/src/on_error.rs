@@ -1,35 +1,136 @@
-use tracing::error;
+use tracing::{error, info};
 
-use bismarck_core::error::FrameworkError;
-use bismarck_utilities::messages;
+use bismarck_core::{
+    context::Context,
+    error::{error_id, BismarckError, FrameworkError},
+    metrics,
+};
+use bismarck_utilities::{command, messages};
+
+/// Name of an error's variant, to count errors by.
+fn error_kind(error: &FrameworkError<'_>) -> &'static str {
+    match error {
+        FrameworkError::Setup { .. } => "Setup",
+        FrameworkError::EventHandler { .. } => "EventHandler",
+        FrameworkError::Command { .. } => "Command",
+        FrameworkError::SubcommandRequired { .. } => "SubcommandRequired",
+        FrameworkError::CommandPanic { .. } => "CommandPanic",
+        FrameworkError::ArgumentParse { .. } => "ArgumentParse",
+        FrameworkError::CommandStructureMismatch { .. } => "CommandStructureMismatch",
+        FrameworkError::CooldownHit { .. } => "CooldownHit",
+        FrameworkError::MissingBotPermissions { .. } => "MissingBotPermissions",
+        FrameworkError::MissingUserPermissions { .. } => "MissingUserPermissions",
+        FrameworkError::NotAnOwner { .. } => "NotAnOwner",
+        FrameworkError::GuildOnly { .. } => "GuildOnly",
+        FrameworkError::DmOnly { .. } => "DmOnly",
+        FrameworkError::NsfwOnly { .. } => "NsfwOnly",
+        FrameworkError::CommandCheckFailed { .. } => "CommandCheckFailed",
+        FrameworkError::DynamicPrefix { .. } => "DynamicPrefix",
+        FrameworkError::UnknownCommand { .. } => "UnknownCommand",
+        FrameworkError::UnknownInteraction { .. } => "UnknownInteraction",
+        _ => "Other",
+    }
+}
+
+/// Logs an error returned by a command, returning what to tell the user about it. Errors on the
+/// bot's side are logged with an ID, which the user is given to report them with.
+fn command_error_message(command: &str, error: &BismarckError) -> String {
+    if error.is_user_error() {
+        info!("{command} was used wrongly: {error}");
+        return error.user_message();
+    }
+
+    let error_id = error_id();
+    error!("[{error_id}] {command} failed: {error:?}");
+
+    format!("{}\nError ID: `{error_id}`", error.user_message())
+}
+
+async fn send_error_reply(ctx: Context<'_>, message: String) {
+    let reply = messages::error_reply(message, true);
+    if let Err(why) = ctx.send(reply).await {
+        if why.to_string().contains("40060") {
+            // Interaction has already been acknowledged.
+            return;
+        }
+
+        error!("Couldn't send reply: {why:?}");
+    }
+}
 
 pub async fn on_error(error: FrameworkError<'_>) {
-    error!("Unhandled error occured: {error:?}");
+    // Errors of commands are logged by what caused them.
+    if !matches!(
+        error,
+        FrameworkError::Command { .. }
+            | FrameworkError::CommandPanic { .. }
+            | FrameworkError::ArgumentParse { .. }
+            | FrameworkError::CommandStructureMismatch { .. }
+    ) {
+        error!("Unhandled error occured: {error:?}");
+    }
+    metrics::framework_error(error_kind(&error));
+
+    if let Some(ctx) = error.ctx() {
+        command::record_failure(ctx).await;
+    }
+
     match error {
-        FrameworkError::Setup { .. } | FrameworkError::EventHandler { .. } => {}
-        FrameworkError::Command { ctx, .. } => {
-            let reply =
-                messages::error_reply("Oh no! There's a problem in executing this command.", true);
-            if let Err(why) = ctx.send(reply).await {
-                if why.to_string().contains("40060") {
-                    // Interaction has already been acknowledged.
-                    return;
-                }
+        FrameworkError::Setup { .. } => {}
+        FrameworkError::EventHandler { error, event, .. } => {
+            error!(
+                "Failed to handle {} event: {error}",
+                event.snake_case_name()
+            );
+        }
+        FrameworkError::Command { error, ctx, .. } => {
+            let message = command_error_message(&ctx.command().qualified_name, &error);
+            send_error_reply(ctx, message).await;
+        }
+        FrameworkError::CommandPanic { payload, ctx, .. } => {
+            let error_id = error_id();
+            error!(
+                "[{error_id}] {} panicked: {}",
+                ctx.command().qualified_name,
+                payload.as_deref().unwrap_or("unknown panic")
+            );
 
-                error!("Couldn't send reply: {why:?}");
-            }
+            let message = format!(
+                "Oh no! A panic occurred whilst executing this command.\nError ID: `{error_id}`"
+            );
+            send_error_reply(ctx, message).await;
         }
-        FrameworkError::CommandPanic { ctx, .. } => {
-            let reply = messages::error_reply(
-                "Oh no! A panic occurred whilst executing this command.",
-                true,
+        FrameworkError::ArgumentParse {
+            error, input, ctx, ..
+        } => {
+            let command = &ctx.command().qualified_name;
+            info!("Couldn't parse arguments of {command} from {input:?}: {error}");
+
+            let message = match input {
+                Some(input) => format!("I couldn't understand `{input}`: {error}."),
+                None => format!("I couldn't understand that: {error}."),
+            };
+            let message = format!(
+                "{message}\nSee `{}help {command}` for how to use it.",
+                ctx.prefix()
             );
-            if let Err(why) = ctx.send(reply).await {
-                error!("Couldn't send reply: {:?}", why);
-            }
+            send_error_reply(ctx, message).await;
+        }
+        FrameworkError::CommandStructureMismatch {
+            description, ctx, ..
+        } => {
+            // Discord has an outdated version of the command, until commands are registered again.
+            let error_id = error_id();
+            error!(
+                "[{error_id}] /{} doesn't match its registration: {description}",
+                ctx.command.qualified_name
+            );
+
+            let message = format!(
+                "This command has changed since Discord last loaded it. Please try again later.\nError ID: `{error_id}`"
+            );
+            send_error_reply(poise::Context::Application(ctx), message).await;
         }
-        FrameworkError::ArgumentParse { .. }
-        | poise::FrameworkError::CommandStructureMismatch { .. } => {}
         FrameworkError::MissingBotPermissions {
             missing_permissions,
             ctx,
@@ -61,3 +162,23 @@ pub async fn on_error(error: FrameworkError<'_>) {
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod on_error_tests {
+    use super::*;
+
+    #[test]
+    fn command_error_message_test() {
+        let error = BismarckError::UserInput("Prefix cannot contain spaces.".to_string());
+        assert_eq!(
+            command_error_message("prefix set", &error),
+            "Prefix cannot contain spaces."
+        );
+
+        let error = BismarckError::Internal("Ready without shard info".to_string());
+        let message = command_error_message("about", &error);
+        assert!(message.starts_with(&error.user_message()));
+        assert!(message.contains("Error ID: `"));
+        assert!(!message.contains("shard"));
+    }
+}
@@ -0,0 +1,913 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use lazy_static::lazy_static;
+use poise::{serenity_prelude as serenity, ChoiceParameter};
+use regex::Regex;
+use rustrict::{Censor, Type};
+use serenity::all::{ChannelId, GuildId, Http, MessageId, RoleId, Timestamp, User, UserId};
+use sqlx::{Row, SqlitePool};
+use tracing::{debug, error, info};
+
+use bismarck_core::{
+    data::{Data, RecentMessages},
+    error::Error,
+    metrics,
+    types::{AutomodAction, AutomodSettings, AutomodStrictness},
+};
+
+use crate::{
+    hierarchy,
+    modlog::{self, ModType, ModerationAction, ModerationOutcome},
+};
+
+/// How long sent messages are remembered for, and so the longest window spam is detected in.
+pub const HISTORY_WINDOW: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref INVITE: Regex =
+        Regex::new(r"(?i)(discord\.gg|discord(app)?\.com/invite)/[a-z0-9-]+").unwrap();
+    static ref LINK: Regex = Regex::new(r"(?i)https?://([^/\s:?#]+)").unwrap();
+}
+
+/// Why a message was caught by automod.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// The message contains a word from the guild's deny list.
+    DeniedWord,
+    /// The message contains a slur.
+    Slur,
+    /// The message contains inappropriate language.
+    Inappropriate,
+    /// The message tries to get inappropriate language past the filter.
+    Evasion,
+    /// The message mentions too many users and roles.
+    MentionFlood,
+    /// The message contains a Discord invite.
+    Invite,
+    /// The message links to a domain that isn't allowed.
+    Link,
+    /// The author sent too many messages too quickly.
+    Spam,
+    /// The author sent the same message too many times.
+    Duplicate,
+}
+
+impl Violation {
+    /// The reason recorded in the moderation logs.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Violation::DeniedWord => "Automod: blocked word.",
+            Violation::Slur => "Automod: slur.",
+            Violation::Inappropriate => "Automod: inappropriate language.",
+            Violation::Evasion => "Automod: filter evasion.",
+            Violation::MentionFlood => "Automod: mass mentions.",
+            Violation::Invite => "Automod: invite link.",
+            Violation::Link => "Automod: link to a domain that isn't allowed.",
+            Violation::Spam => "Automod: sending messages too quickly.",
+            Violation::Duplicate => "Automod: repeated messages.",
+        }
+    }
+}
+
+/// The rustrict thresholds for inappropriate language and evasion at each strictness.
+fn thresholds(strictness: AutomodStrictness) -> (Type, Type) {
+    match strictness {
+        AutomodStrictness::Low => (
+            (Type::PROFANE | Type::OFFENSIVE | Type::SEXUAL) & Type::SEVERE,
+            Type::EVASIVE & Type::SEVERE,
+        ),
+        AutomodStrictness::Medium => (
+            Type::INAPPROPRIATE & Type::MODERATE_OR_HIGHER,
+            Type::EVASIVE & Type::MODERATE_OR_HIGHER,
+        ),
+        AutomodStrictness::High => (Type::INAPPROPRIATE, Type::EVASIVE),
+    }
+}
+
+/// Lowercases the content and turns anything but letters and digits into single spaces, padded
+/// with a space on both ends so that whole words can be matched with `contains`.
+fn normalize(content: &str) -> String {
+    let mut normalized = String::from(" ");
+
+    for word in content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        normalized.push_str(&word.to_lowercase());
+        normalized.push(' ');
+    }
+
+    normalized
+}
+
+/// Normalizes a word before it is added to a guild's allow or deny list.
+pub fn normalize_word(word: &str) -> String {
+    normalize(word).trim().to_string()
+}
+
+/// The byte ranges of the words in the content, a word being a run of letters and digits as in
+/// [`normalize`].
+fn word_spans(content: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+
+    for (index, c) in content.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(begin)) => {
+                spans.push((begin, index));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(begin) = start {
+        spans.push((begin, content.len()));
+    }
+
+    spans
+}
+
+/// Replaces the allowed words in the content with spaces, leaving everything else as written.
+fn remove_allowed(content: &str, allowed_words: &[String]) -> String {
+    let spans = word_spans(content);
+    let words: Vec<String> = spans
+        .iter()
+        .map(|(start, end)| content[*start..*end].to_lowercase())
+        .collect();
+
+    let mut removed = Vec::new();
+
+    for allowed in allowed_words {
+        let parts: Vec<&str> = allowed.split(' ').collect();
+
+        for (index, window) in words.windows(parts.len()).enumerate() {
+            if window.iter().zip(&parts).all(|(word, part)| word == part) {
+                removed.push((spans[index].0, spans[index + parts.len() - 1].1));
+            }
+        }
+    }
+
+    removed.sort_unstable();
+
+    let mut kept = String::with_capacity(content.len());
+    let mut position = 0;
+
+    for (start, end) in removed {
+        if start >= position {
+            kept.push_str(&content[position..start]);
+            kept.push(' ');
+        }
+        position = position.max(end);
+    }
+
+    kept.push_str(&content[position..]);
+    kept
+}
+
+/// Checks a message against the guild's word lists, then against rustrict.
+///
+/// Denied words always match, even when allowed. Allowed words are removed before the message
+/// is analyzed, which is otherwise analyzed as written so that rustrict sees the symbols and
+/// spacing used to evade it.
+pub fn find_violation(content: &str, settings: &AutomodSettings) -> Option<Violation> {
+    let normalized = normalize(content);
+
+    if settings
+        .denied_words
+        .iter()
+        .any(|word| normalized.contains(&format!(" {word} ")))
+    {
+        return Some(Violation::DeniedWord);
+    }
+
+    let content = remove_allowed(content, &settings.allowed_words);
+
+    let analysis = Censor::from_str(&content).analyze();
+    let (inappropriate, evasive) = thresholds(settings.strictness);
+
+    if analysis.is(Type::OFFENSIVE & Type::SEVERE) {
+        Some(Violation::Slur)
+    } else if analysis.is(inappropriate) {
+        Some(Violation::Inappropriate)
+    } else if analysis.is(evasive) {
+        Some(Violation::Evasion)
+    } else {
+        None
+    }
+}
+
+/// Checks a message's mentions and links against the guild's limits.
+pub fn find_content_violation(
+    content: &str,
+    mentions: usize,
+    settings: &AutomodSettings,
+) -> Option<Violation> {
+    if settings.mention_limit > 0 && mentions > settings.mention_limit as usize {
+        return Some(Violation::MentionFlood);
+    }
+
+    if settings.filter_invites && INVITE.is_match(content) {
+        return Some(Violation::Invite);
+    }
+
+    if settings.filter_links {
+        let disallowed = LINK.captures_iter(content).any(|captures| {
+            let domain = captures[1].to_lowercase();
+
+            !settings
+                .allowed_domains
+                .iter()
+                .any(|allowed| domain == *allowed || domain.ends_with(&format!(".{allowed}")))
+        });
+
+        if disallowed {
+            return Some(Violation::Link);
+        }
+    }
+
+    None
+}
+
+/// Forgets the messages sent more than [`HISTORY_WINDOW`] before `now`.
+fn forget_old(recent: &mut VecDeque<(Instant, u64)>, now: Instant) {
+    while recent
+        .front()
+        .is_some_and(|(at, _)| now.saturating_duration_since(*at) > HISTORY_WINDOW)
+    {
+        recent.pop_front();
+    }
+}
+
+/// Forgets old messages of every author, along with the authors left without any, so that
+/// users who stopped talking aren't kept around.
+pub fn prune_recent_messages(recent_messages: &RecentMessages, now: Instant) {
+    recent_messages.retain(|_, recent| {
+        forget_old(recent, now);
+        !recent.is_empty()
+    });
+}
+
+/// Records a message in its author's recent messages, returning a violation if it puts them over
+/// the guild's rate or duplicate limits.
+///
+/// The author's recent messages are cleared on a violation, so that they are only punished once
+/// per burst.
+pub fn record_message(
+    recent: &mut VecDeque<(Instant, u64)>,
+    sent_at: Instant,
+    content: &str,
+    settings: &AutomodSettings,
+) -> Option<Violation> {
+    forget_old(recent, sent_at);
+
+    let mut hasher = DefaultHasher::new();
+    content.trim().to_lowercase().hash(&mut hasher);
+    let hash = hasher.finish();
+
+    recent.push_back((sent_at, hash));
+
+    let window = Duration::from_secs(settings.message_window);
+    let sent = recent
+        .iter()
+        .filter(|(at, _)| sent_at.duration_since(*at) <= window)
+        .count();
+
+    // Messages without content, such as attachments, aren't duplicates of each other.
+    let duplicates = if content.trim().is_empty() {
+        0
+    } else {
+        recent.iter().filter(|(_, other)| *other == hash).count()
+    };
+
+    let violation = if settings.message_limit > 0 && sent > settings.message_limit as usize {
+        Some(Violation::Spam)
+    } else if settings.duplicate_limit > 0 && duplicates > settings.duplicate_limit as usize {
+        Some(Violation::Duplicate)
+    } else {
+        None
+    };
+
+    if violation.is_some() {
+        recent.clear();
+    }
+
+    violation
+}
+
+/// The parts of a created or edited message automod looks at.
+pub struct ScannedMessage<'a> {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub author: &'a User,
+    pub roles: &'a [RoleId],
+    pub content: &'a str,
+    /// How many users and roles the message mentions.
+    pub mentions: usize,
+    /// Whether the message was just sent. Edits aren't counted towards rate limits.
+    pub is_new: bool,
+}
+
+/// A message caught by automod, moderated through [`modlog::execute`] with the bot as
+/// the moderator.
+struct FilteredMessage {
+    action: AutomodAction,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    until: Timestamp,
+}
+
+impl ModerationAction for FilteredMessage {
+    fn mod_type(&self) -> ModType {
+        match self.action {
+            AutomodAction::Delete => ModType::Delete,
+            AutomodAction::Warn => ModType::Warn,
+            AutomodAction::Timeout => ModType::Timeout,
+        }
+    }
+
+    fn verb(&self) -> &'static str {
+        match self.action {
+            AutomodAction::Delete => "delete messages from",
+            AutomodAction::Warn => "warn",
+            AutomodAction::Timeout => "timeout",
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self.action {
+            AutomodAction::Delete => "censored",
+            AutomodAction::Warn => "warned",
+            AutomodAction::Timeout => "timed out",
+        }
+    }
+
+    fn dm_template(&self) -> Option<&'static str> {
+        Some(match self.action {
+            AutomodAction::Delete => "Your message in {guild} was removed for {reason}",
+            AutomodAction::Warn => "You've been warned in {guild} by {moderator} for {reason}",
+            AutomodAction::Timeout => {
+                "You've been timed out in {guild} by {moderator} for {reason}"
+            }
+        })
+    }
+
+    async fn apply(
+        &self,
+        http: &Http,
+        guild_id: GuildId,
+        user_id: UserId,
+        reason: &str,
+    ) -> serenity::Result<()> {
+        self.channel_id
+            .delete_message(http, self.message_id)
+            .await?;
+
+        if self.action == AutomodAction::Timeout {
+            let timeout = modlog::Timeout { until: self.until };
+            timeout.apply(http, guild_id, user_id, reason).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs automod on a message, moderating its author if it is caught.
+pub async fn scan_message(
+    context: &serenity::Context,
+    data: &Data,
+    message: ScannedMessage<'_>,
+) -> Result<(), Error> {
+    if message.author.bot {
+        return Ok(());
+    }
+
+    let (violation, action) = {
+        let Some(settings) = data.automod.get(&message.guild_id.get()) else {
+            return Ok(());
+        };
+
+        if !settings.enabled
+            || settings.exempt_channels.contains(&message.channel_id.get())
+            || message
+                .roles
+                .iter()
+                .any(|role_id| settings.exempt_roles.contains(&role_id.get()))
+        {
+            return Ok(());
+        }
+
+        let violation = find_violation(message.content, &settings)
+            .or_else(|| find_content_violation(message.content, message.mentions, &settings))
+            .or_else(|| {
+                if !message.is_new {
+                    return None;
+                }
+
+                let mut recent = data
+                    .recent_messages
+                    .entry((message.guild_id.get(), message.author.id.get()))
+                    .or_default();
+
+                record_message(&mut recent, Instant::now(), message.content, &settings)
+            });
+
+        let Some(violation) = violation else {
+            return Ok(());
+        };
+
+        let action = FilteredMessage {
+            action: settings.action,
+            channel_id: message.channel_id,
+            message_id: message.message_id,
+            until: Timestamp::from(Utc::now() + Duration::from_secs(settings.timeout_duration)),
+        };
+
+        (violation, action)
+    };
+
+    let moderator = match hierarchy::bot_member(context, message.guild_id).await {
+        Ok(moderator) => moderator,
+        Err(why) => {
+            error!("Couldn't get bot member for automod: {why:?}");
+            return Ok(());
+        }
+    };
+
+    let outcome = modlog::execute(
+        context,
+        data,
+        &action,
+        message.guild_id,
+        &moderator,
+        message.author.id,
+        violation.reason(),
+    )
+    .await?;
+
+    if let ModerationOutcome::Refused(why) = outcome {
+        debug!(
+            "Automod skipped message from @{}: {why}",
+            message.author.name
+        );
+    }
+
+    Ok(())
+}
+
+/// Selects the automod settings of every guild, along with their word lists and exemptions.
+pub async fn select_automod_settings(
+    pool: &SqlitePool,
+) -> Result<HashMap<u64, AutomodSettings>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let mut settings = HashMap::new();
+
+    let rows = sqlx::query(
+        "SELECT guild_id, enabled, strictness, action, timeout_duration, message_limit, message_window, duplicate_limit, mention_limit, filter_invites, filter_links FROM automod_config",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for row in rows {
+        let guild_settings = AutomodSettings {
+            enabled: row.get::<i64, _>(1) == 1,
+            strictness: AutomodStrictness::from_name(&row.get::<String, _>(2))
+                .unwrap_or(AutomodStrictness::Medium),
+            action: AutomodAction::from_name(&row.get::<String, _>(3))
+                .unwrap_or(AutomodAction::Delete),
+            timeout_duration: row.get::<i64, _>(4) as u64,
+            message_limit: row.get::<i64, _>(5) as u32,
+            message_window: row.get::<i64, _>(6) as u64,
+            duplicate_limit: row.get::<i64, _>(7) as u32,
+            mention_limit: row.get::<i64, _>(8) as u32,
+            filter_invites: row.get::<i64, _>(9) == 1,
+            filter_links: row.get::<i64, _>(10) == 1,
+            ..Default::default()
+        };
+
+        settings.insert(row.get::<i64, _>(0) as u64, guild_settings);
+    }
+
+    let rows = sqlx::query("SELECT guild_id, word, allowed FROM automod_word")
+        .fetch_all(pool)
+        .await?;
+
+    for row in rows {
+        let guild_settings: &mut AutomodSettings =
+            settings.entry(row.get::<i64, _>(0) as u64).or_default();
+        let word = row.get::<String, _>(1);
+
+        if row.get::<i64, _>(2) == 1 {
+            guild_settings.allowed_words.push(word);
+        } else {
+            guild_settings.denied_words.push(word);
+        }
+    }
+
+    let rows = sqlx::query("SELECT guild_id, target_id, kind FROM automod_exemption")
+        .fetch_all(pool)
+        .await?;
+
+    for row in rows {
+        let guild_settings: &mut AutomodSettings =
+            settings.entry(row.get::<i64, _>(0) as u64).or_default();
+        let target_id = row.get::<i64, _>(1) as u64;
+
+        if row.get::<String, _>(2) == "role" {
+            guild_settings.exempt_roles.push(target_id);
+        } else {
+            guild_settings.exempt_channels.push(target_id);
+        }
+    }
+
+    let rows = sqlx::query("SELECT guild_id, domain FROM automod_domain")
+        .fetch_all(pool)
+        .await?;
+
+    for row in rows {
+        let guild_settings: &mut AutomodSettings =
+            settings.entry(row.get::<i64, _>(0) as u64).or_default();
+
+        guild_settings.allowed_domains.push(row.get::<String, _>(1));
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "automod_config", elapsed_time);
+    info!("Selected from Automod Settings in {elapsed_time:.2?}");
+
+    Ok(settings)
+}
+
+/// Inserts or updates the automod configuration of a guild. Word lists, exemptions and allowed
+/// domains are stored separately.
+pub async fn upsert_automod_config(
+    guild_id: &GuildId,
+    settings: &AutomodSettings,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO automod_config (guild_id, enabled, strictness, action, timeout_duration, message_limit, message_window, duplicate_limit, mention_limit, filter_invites, filter_links) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (guild_id) DO UPDATE SET enabled = excluded.enabled, strictness = excluded.strictness, action = excluded.action, timeout_duration = excluded.timeout_duration, message_limit = excluded.message_limit, message_window = excluded.message_window, duplicate_limit = excluded.duplicate_limit, mention_limit = excluded.mention_limit, filter_invites = excluded.filter_invites, filter_links = excluded.filter_links"
+    )
+        .bind(i64::from(*guild_id))
+        .bind(settings.enabled as i64)
+        .bind(settings.strictness.name())
+        .bind(settings.action.name())
+        .bind(settings.timeout_duration as i64)
+        .bind(settings.message_limit as i64)
+        .bind(settings.message_window as i64)
+        .bind(settings.duplicate_limit as i64)
+        .bind(settings.mention_limit as i64)
+        .bind(settings.filter_invites as i64)
+        .bind(settings.filter_links as i64);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("upsert", "automod_config", elapsed_time);
+    info!("Upserted into Automod Settings in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Adds a word to the allow or deny list of a guild, moving it if it's already on the other.
+pub async fn upsert_automod_word(
+    guild_id: &GuildId,
+    word: &str,
+    allowed: bool,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO automod_word (guild_id, word, allowed) VALUES (?, ?, ?)
+        ON CONFLICT (guild_id, word) DO UPDATE SET allowed = excluded.allowed",
+    )
+    .bind(i64::from(*guild_id))
+    .bind(word)
+    .bind(allowed as i64);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("upsert", "automod_word", elapsed_time);
+    info!("Upserted into Automod Words in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Removes a word from both lists of a guild, returning whether it was on either.
+pub async fn delete_automod_word(
+    guild_id: &GuildId,
+    word: &str,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM automod_word WHERE guild_id = ? AND word = ?")
+        .bind(i64::from(*guild_id))
+        .bind(word);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "automod_word", elapsed_time);
+    info!("Deleted from Automod Words in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Exempts a role or channel from automod. `kind` is either "role" or "channel".
+pub async fn insert_automod_exemption(
+    guild_id: &GuildId,
+    target_id: u64,
+    kind: &str,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT OR IGNORE INTO automod_exemption (guild_id, target_id, kind) VALUES (?, ?, ?)",
+    )
+    .bind(i64::from(*guild_id))
+    .bind(target_id as i64)
+    .bind(kind);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "automod_exemption", elapsed_time);
+    info!("Inserted into Automod Exemptions in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Removes the exemption of a role or channel, returning whether it was exempt.
+pub async fn delete_automod_exemption(
+    guild_id: &GuildId,
+    target_id: u64,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM automod_exemption WHERE guild_id = ? AND target_id = ?")
+        .bind(i64::from(*guild_id))
+        .bind(target_id as i64);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "automod_exemption", elapsed_time);
+    info!("Deleted from Automod Exemptions in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Allows links to a domain and its subdomains, returning `false` if it was already allowed.
+pub async fn insert_automod_domain(
+    guild_id: &GuildId,
+    domain: &str,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query =
+        sqlx::query("INSERT OR IGNORE INTO automod_domain (guild_id, domain) VALUES (?, ?)")
+            .bind(i64::from(*guild_id))
+            .bind(domain);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "automod_domain", elapsed_time);
+    info!("Inserted into Automod Domains in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Stops allowing links to a domain, returning whether it was allowed.
+pub async fn delete_automod_domain(
+    guild_id: &GuildId,
+    domain: &str,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM automod_domain WHERE guild_id = ? AND domain = ?")
+        .bind(i64::from(*guild_id))
+        .bind(domain);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "automod_domain", elapsed_time);
+    info!("Deleted from Automod Domains in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod automod_tests {
+    use super::*;
+
+    fn settings(denied: &[&str], allowed: &[&str]) -> AutomodSettings {
+        AutomodSettings {
+            enabled: true,
+            denied_words: denied.iter().map(|word| word.to_string()).collect(),
+            allowed_words: allowed.iter().map(|word| word.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalize_test() {
+        assert_eq!(normalize("Hello, WORLD!!"), " hello world ");
+        assert_eq!(normalize("..."), " ");
+    }
+
+    #[test]
+    fn denied_word_test() {
+        let settings = settings(&["grape", "green apple"], &[]);
+
+        assert_eq!(
+            find_violation("I ate a GRAPE.", &settings),
+            Some(Violation::DeniedWord)
+        );
+        assert_eq!(
+            find_violation("green-apple pie", &settings),
+            Some(Violation::DeniedWord)
+        );
+        assert_eq!(find_violation("grapefruit", &settings), None);
+    }
+
+    #[test]
+    fn content_violation_test() {
+        let settings = AutomodSettings {
+            filter_invites: true,
+            filter_links: true,
+            allowed_domains: vec!["example.com".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            find_content_violation("hi", 6, &settings),
+            Some(Violation::MentionFlood)
+        );
+        assert_eq!(
+            find_content_violation("join discord.gg/abc-123", 0, &settings),
+            Some(Violation::Invite)
+        );
+        assert_eq!(
+            find_content_violation("see https://evil.net/page", 0, &settings),
+            Some(Violation::Link)
+        );
+        assert_eq!(
+            find_content_violation("see https://docs.Example.com/page", 5, &settings),
+            None
+        );
+    }
+
+    #[test]
+    fn spam_window_test() {
+        let settings = AutomodSettings::default();
+        let mut recent = VecDeque::new();
+        let start = Instant::now();
+
+        for i in 0..5 {
+            let sent_at = start + Duration::from_millis(i * 100);
+            assert_eq!(
+                record_message(&mut recent, sent_at, &i.to_string(), &settings),
+                None
+            );
+        }
+
+        let sent_at = start + Duration::from_millis(500);
+        assert_eq!(
+            record_message(&mut recent, sent_at, "5", &settings),
+            Some(Violation::Spam)
+        );
+        assert!(recent.is_empty());
+
+        // Messages outside of the window no longer count.
+        for i in 0..5 {
+            let sent_at = start + Duration::from_secs(10 + i * 2);
+            assert_eq!(
+                record_message(&mut recent, sent_at, &i.to_string(), &settings),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn prune_recent_messages_test() {
+        let settings = AutomodSettings::default();
+        let recent_messages = RecentMessages::new();
+        let start = Instant::now();
+
+        for (key, sent_at) in [((1, 1), start), ((1, 2), start + HISTORY_WINDOW)] {
+            let mut recent = recent_messages.entry(key).or_default();
+            record_message(&mut recent, sent_at, "Hi", &settings);
+        }
+
+        prune_recent_messages(&recent_messages, start + HISTORY_WINDOW * 2);
+
+        assert!(!recent_messages.contains_key(&(1, 1)));
+        assert_eq!(recent_messages.get(&(1, 2)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn duplicate_test() {
+        let settings = AutomodSettings::default();
+        let mut recent = VecDeque::new();
+        let start = Instant::now();
+
+        for i in 0..3 {
+            let sent_at = start + Duration::from_secs(i * 10);
+            assert_eq!(record_message(&mut recent, sent_at, "Hi", &settings), None);
+            assert_eq!(record_message(&mut recent, sent_at, "", &settings), None);
+        }
+
+        let sent_at = start + Duration::from_secs(30);
+        assert_eq!(
+            record_message(&mut recent, sent_at, "hi ", &settings),
+            Some(Violation::Duplicate)
+        );
+    }
+
+    #[test]
+    fn remove_allowed_test() {
+        let allowed = ["grape".to_string(), "green apple".to_string()];
+
+        assert_eq!(
+            remove_allowed("A GRAPE, a grapefruit and a green-apple!", &allowed),
+            "A  , a grapefruit and a  !"
+        );
+        assert_eq!(remove_allowed("grape grape", &allowed), "   ");
+    }
+
+    #[test]
+    fn evasion_test() {
+        let settings = settings(&[], &[]);
+
+        // Symbols are kept for rustrict, rather than split off as separate words.
+        assert_eq!(
+            find_violation("a$$hole", &settings),
+            Some(Violation::Inappropriate)
+        );
+    }
+
+    #[test]
+    fn denied_word_over_allowed_test() {
+        let settings = settings(&["grape"], &["grape"]);
+
+        assert_eq!(
+            find_violation("grape", &settings),
+            Some(Violation::DeniedWord)
+        );
+    }
+}
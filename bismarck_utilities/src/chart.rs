@@ -0,0 +1,156 @@
+//! Renders simple bar charts as PNG images, for stats embeds.
+
+use font8x8::{UnicodeFonts, BASIC_FONTS};
+
+const WIDTH: usize = 640;
+const ROW_HEIGHT: usize = 28;
+const PADDING: usize = 12;
+/// Glyphs are drawn at twice their size.
+const SCALE: usize = 2;
+const GLYPH_SIZE: usize = 8;
+const CHAR_WIDTH: usize = GLYPH_SIZE * SCALE;
+/// Longest label drawn, in characters.
+const LABEL_CHARS: usize = 16;
+const LABEL_WIDTH: usize = LABEL_CHARS * CHAR_WIDTH;
+/// Space kept after the longest bar for its value.
+const VALUE_WIDTH: usize = 7 * CHAR_WIDTH;
+
+const BACKGROUND: u8 = 0;
+const TEXT: u8 = 1;
+const BAR: u8 = 2;
+const HIGHLIGHT: u8 = 3;
+
+/// Background, text, bar and highlighted part of a bar, in RGB.
+const PALETTE: [u8; 12] = [
+    0x2b, 0x2d, 0x31, 0xdb, 0xde, 0xe1, 0x8b, 0x00, 0x00, 0xff, 0xa5, 0x00,
+];
+
+/// A bar of a chart, part of which may be highlighted, such as the failed runs of a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bar {
+    pub label: String,
+    pub value: u64,
+    pub highlighted: u64,
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    /// Palette indices, row by row.
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![BACKGROUND; width * height],
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, colour: u8) {
+        for row in y..(y + height).min(self.height) {
+            let start = row * self.width;
+            self.pixels[start + x.min(self.width)..start + (x + width).min(self.width)]
+                .fill(colour);
+        }
+    }
+
+    fn text(&mut self, x: usize, y: usize, text: &str) {
+        for (index, character) in text.chars().enumerate() {
+            let left = x + index * CHAR_WIDTH;
+            let glyph = BASIC_FONTS
+                .get(character)
+                .or_else(|| BASIC_FONTS.get('?'))
+                .unwrap_or_default();
+
+            for (row, bits) in glyph.iter().enumerate() {
+                // The leftmost pixel is the lowest bit.
+                for column in (0..GLYPH_SIZE).filter(|column| bits & (1 << column) != 0) {
+                    self.fill(left + column * SCALE, y + row * SCALE, SCALE, SCALE, TEXT);
+                }
+            }
+        }
+    }
+}
+
+/// Draws a horizontal bar chart, a row per bar, with the value at the end of each bar.
+pub fn bar_chart(bars: &[Bar]) -> Result<Vec<u8>, png::EncodingError> {
+    let height = PADDING * 2 + ROW_HEIGHT * bars.len().max(1);
+    let mut canvas = Canvas::new(WIDTH, height);
+
+    let max = bars
+        .iter()
+        .map(|bar| bar.value)
+        .max()
+        .unwrap_or_default()
+        .max(1);
+    let bar_space = WIDTH - PADDING * 2 - LABEL_WIDTH - VALUE_WIDTH;
+    let text_offset = (ROW_HEIGHT - GLYPH_SIZE * SCALE) / 2;
+
+    for (index, bar) in bars.iter().enumerate() {
+        let top = PADDING + index * ROW_HEIGHT;
+        let label = bar.label.chars().take(LABEL_CHARS - 1).collect::<String>();
+        canvas.text(PADDING, top + text_offset, &label);
+
+        let left = PADDING + LABEL_WIDTH;
+        let length = (bar.value as usize * bar_space / max as usize).max(1);
+        let highlighted = bar.highlighted.min(bar.value) as usize * bar_space / max as usize;
+
+        canvas.fill(left, top + 4, length, ROW_HEIGHT - 8, BAR);
+        canvas.fill(
+            left + length - highlighted,
+            top + 4,
+            highlighted,
+            ROW_HEIGHT - 8,
+            HIGHLIGHT,
+        );
+        canvas.text(
+            left + length + CHAR_WIDTH / 2,
+            top + text_offset,
+            &bar.value.to_string(),
+        );
+    }
+
+    let mut image = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut image, canvas.width as u32, canvas.height as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(&PALETTE[..]);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&canvas.pixels)?;
+    writer.finish()?;
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod chart_tests {
+    use super::*;
+
+    #[test]
+    fn bar_chart_test() {
+        let bars = vec![
+            Bar {
+                label: "ping".to_string(),
+                value: 40,
+                highlighted: 2,
+            },
+            Bar {
+                label: "remind in".to_string(),
+                value: 7,
+                highlighted: 0,
+            },
+        ];
+
+        let image = bar_chart(&bars).unwrap();
+
+        let decoder = png::Decoder::new(std::io::Cursor::new(image));
+        let info = decoder.read_info().unwrap().info().clone();
+        assert_eq!(info.width, WIDTH as u32);
+        assert_eq!(info.height as usize, PADDING * 2 + ROW_HEIGHT * 2);
+    }
+}
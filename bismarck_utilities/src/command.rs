@@ -1,15 +1,27 @@
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::Ordering;
 
+use chrono::Utc;
 use sqlx::sqlite::SqliteQueryResult;
 use tokio::time::Instant;
-use tracing::{debug, error};
+use tracing::debug;
 
 use bismarck_core::{
     context::{Context, PartialContext},
-    error::Error,
+    counters::{CommandUsage, GLOBAL_ID},
+    error::{BismarckError, Error},
+    metrics,
+    shutdown::CommandGuard,
 };
 
+/// Kept for the length of a command's invocation.
+pub struct InvocationData {
+    started: Instant,
+    _running: CommandGuard,
+}
+
 pub async fn get_prefix(context: PartialContext<'_>) -> Result<Option<String>, Error> {
+    let default_prefix = &context.data.config.bot.default_prefix;
+
     if let Some(guild_id) = context.guild_id {
         let pf = &context.data.guild_data;
 
@@ -23,7 +35,9 @@ pub async fn get_prefix(context: PartialContext<'_>) -> Result<Option<String>, E
                 let (guild_id, owner_id) = {
                     let guild = guild_id
                         .to_guild_cached(&context.serenity_context.cache)
-                        .unwrap();
+                        .ok_or_else(|| {
+                            BismarckError::NotFound(format!("Guild {guild_id} in the cache"))
+                        })?;
                     (i64::from(guild.id), i64::from(guild.owner_id))
                 };
 
@@ -39,7 +53,7 @@ pub async fn get_prefix(context: PartialContext<'_>) -> Result<Option<String>, E
                     ) VALUES (?, ?, ?)",
                 )
                 .bind(guild_id)
-                .bind("+")
+                .bind(default_prefix)
                 .bind(owner_id)
                 .execute(database)
                 .await;
@@ -47,10 +61,10 @@ pub async fn get_prefix(context: PartialContext<'_>) -> Result<Option<String>, E
                 // this one ended up a bit weird
                 // we have to convert the sqlx::Error to our type alias Error
                 // if query_result is Err
-                // otherwise we just return Ok(Some("+".to_string))
+                // otherwise we just return Ok(Some(default_prefix.clone()))
                 // the inner query result is unused, but can be used in the closure if desired
                 match query_result {
-                    Ok(_query) => Ok(Some("+".to_string())),
+                    Ok(_query) => Ok(Some(default_prefix.clone())),
                     Err(sqlx_error) => Err(Error::from(sqlx_error)),
                 }
 
@@ -59,14 +73,14 @@ pub async fn get_prefix(context: PartialContext<'_>) -> Result<Option<String>, E
 
                 // query_result.map_or_else(
                 //     |sqlx_err| Err(Error::from(sqlx_err)),
-                //     |_query_result| Ok(Some("+".to_string())),
+                //     |_query_result| Ok(Some(default_prefix.clone())),
                 // )
             }
         }
     } else {
         // previously, without the else block, we were throwing away
-        // everything we did in the `if let` and always just returning Ok(Some("+".to_string()))
-        Ok(Some("+".to_string()))
+        // everything we did in the `if let` and always just returning the default prefix
+        Ok(Some(default_prefix.clone()))
     }
 }
 
@@ -80,68 +94,71 @@ pub async fn pre_command(context: Context<'_>) {
 
     let data = context.data();
 
-    let start_time = Instant::now();
+    // Dropped along with the invocation, whether the command succeeds or fails.
+    context
+        .set_invocation_data(InvocationData {
+            started: Instant::now(),
+            _running: data.shutdown.track_command(),
+        })
+        .await;
 
-    if let Some(guild_id) = context.guild_id() {
-        let commands_ran = data.commands_ran.get(&guild_id.get()).unwrap();
-        commands_ran.fetch_add(1, Ordering::Relaxed);
+    let start_time = Instant::now();
 
-        let id = guild_id.get() as i64;
+    let guild_id = context.guild_id().map(|guild_id| guild_id.get());
+    let author_id = context.author().id.get();
 
-        if let Err(query) =
-            sqlx::query("UPDATE guild SET commands_ran = commands_ran + 1 WHERE id = ?")
-                .bind(id)
-                .execute(&context.data().sqlite)
-                .await
-        {
-            error!("Failed to update guild commands ran: {}", query);
-        }
+    if let Some(guild_id) = guild_id {
+        data.commands_ran
+            .entry(guild_id)
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
     }
 
-    let commands_ran_global = data.commands_ran.get(&0).unwrap();
-    commands_ran_global.fetch_add(1, Ordering::Relaxed);
+    data.commands_ran
+        .entry(GLOBAL_ID)
+        .or_default()
+        .fetch_add(1, Ordering::Relaxed);
 
-    if let Err(query) = sqlx::query("UPDATE guild SET commands_ran = commands_ran + 1 WHERE id = 0")
-        .execute(&context.data().sqlite)
-        .await
-    {
-        error!("Failed to update global commands ran: {}", query);
-    }
+    data.commands_ran_users
+        .entry(author_id)
+        .or_default()
+        .fetch_add(1, Ordering::Relaxed);
 
-    let author_id = u64::from(context.author().id);
-    if let Some(commands_ran_user) = data.commands_ran_users.get(&author_id) {
-        commands_ran_user.fetch_add(1, Ordering::Relaxed);
+    // Written to the database in batches rather than on every command.
+    data.command_counters.record(guild_id, author_id);
+    metrics::command_ran(&context.command().qualified_name);
 
-        let author_id = i64::from(context.author().id);
+    let elapsed_time = start_time.elapsed();
 
-        if let Err(query) =
-            sqlx::query("UPDATE user SET commands_run = commands_run + 1 WHERE id = ?")
-                .bind(author_id)
-                .execute(&context.data().sqlite)
-                .await
-        {
-            error!("Failed to update user commands ran: {}", query);
-        }
+    debug!("Precommand ran in {elapsed_time:.2?}");
+}
 
+/// Logs how long a command took and whether it succeeded, for usage analytics.
+async fn record_usage(context: Context<'_>, success: bool) {
+    let Some(latency) = context
+        .invocation_data::<InvocationData>()
+        .await
+        .map(|invocation| invocation.started.elapsed())
+    else {
+        // Failed before `pre_command`, such as on a failed check.
         return;
-    }
-
-    data.commands_ran_users.insert(author_id, AtomicU64::new(1));
-
-    let author_id = i64::from(context.author().id);
-    if let Err(query) = sqlx::query(
-        "INSERT OR IGNORE INTO user (
-            id
-        ) VALUES (?)",
-    )
-    .bind(author_id)
-    .execute(&context.data().sqlite)
-    .await
-    {
-        error!("Failed to insert user: {}", query);
-    }
+    };
+
+    context.data().command_counters.record_usage(CommandUsage {
+        command: context.command().qualified_name.clone(),
+        guild_id: context.guild_id().map(|guild_id| guild_id.get()),
+        user_id: context.author().id.get(),
+        success,
+        latency_ms: latency.as_millis() as u64,
+        created_at: Utc::now().timestamp(),
+    });
+}
 
-    let elapsed_time = start_time.elapsed();
+pub async fn post_command(context: Context<'_>) {
+    record_usage(context, true).await;
+}
 
-    debug!("Precommand ran in {elapsed_time:.2?}");
+/// Records a command that failed or panicked.
+pub async fn record_failure(context: Context<'_>) {
+    record_usage(context, false).await;
 }
@@ -1,7 +1,7 @@
 use chrono::NaiveDateTime;
 use serenity::{
-    all::{colours::css, User},
-    builder::{CreateEmbed, CreateEmbedAuthor},
+    all::{colours::css, Timestamp, User, UserId},
+    builder::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter},
     model::Colour,
 };
 use std::fmt::Write;
@@ -64,6 +64,34 @@ pub fn warnings_command_embed(
         .fields(embed_fields)
 }
 
+pub fn mod_log_embed(
+    action: &str,
+    user: &User,
+    moderator_id: UserId,
+    reason: &str,
+    colour: Colour,
+) -> CreateEmbed {
+    let embed_author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
+
+    CreateEmbed::default()
+        .author(embed_author)
+        .title(action.to_uppercase())
+        .field("User", format!("<@{}>", user.id), true)
+        .field("Moderator", format!("<@{moderator_id}>"), true)
+        .field("Reason", reason, false)
+        .footer(CreateEmbedFooter::new(format!("User ID: {}", user.id)))
+        .timestamp(Timestamp::now())
+        .colour(colour)
+}
+
+pub fn alert_embed(title: &str, description: impl Into<String>, colour: Colour) -> CreateEmbed {
+    CreateEmbed::default()
+        .title(title)
+        .description(description)
+        .timestamp(Timestamp::now())
+        .colour(colour)
+}
+
 pub fn error_message_embed(message: &String) -> CreateEmbed {
     CreateEmbed::default()
         .description(message.to_string())
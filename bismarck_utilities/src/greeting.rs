@@ -0,0 +1,264 @@
+use poise::serenity_prelude as serenity;
+use serenity::all::{
+    ChannelId, Colour, CreateEmbed, CreateMessage, GuildId, Mentionable, Timestamp, User,
+};
+use sqlx::{Row, SqlitePool};
+use tokio::time::Instant;
+use tracing::{error, info};
+
+use bismarck_core::metrics;
+
+/// Which member event a greeting is sent on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum GreetingKind {
+    #[name = "welcome"]
+    Welcome,
+    #[name = "goodbye"]
+    Goodbye,
+}
+
+impl GreetingKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            GreetingKind::Welcome => "welcome",
+            GreetingKind::Goodbye => "goodbye",
+        }
+    }
+}
+
+/// A guild's welcome or goodbye message.
+#[derive(Debug, Clone)]
+pub struct Greeting {
+    /// The channel the message is posted to, if any.
+    pub channel_id: Option<ChannelId>,
+    /// The message, with `{user}`, `{mention}`, `{guild}`, `{member_count}` and `{account_age}`
+    /// replaced before sending.
+    pub template: String,
+    /// Whether the message is sent as an embed.
+    pub embed: bool,
+    /// Whether the message is also sent to the member by DM.
+    pub dm: bool,
+}
+
+/// Describes a duration in its largest whole unit, such as "3 days".
+fn describe_age(seconds: i64) -> String {
+    let (amount, unit) = match seconds.max(0) {
+        seconds if seconds >= 365 * 86400 => (seconds / (365 * 86400), "year"),
+        seconds if seconds >= 30 * 86400 => (seconds / (30 * 86400), "month"),
+        seconds if seconds >= 86400 => (seconds / 86400, "day"),
+        seconds if seconds >= 3600 => (seconds / 3600, "hour"),
+        seconds => (seconds / 60, "minute"),
+    };
+
+    if amount == 1 {
+        format!("{amount} {unit}")
+    } else {
+        format!("{amount} {unit}s")
+    }
+}
+
+/// Replaces the placeholders of a greeting template.
+pub fn render(
+    template: &str,
+    user: &User,
+    guild_name: &str,
+    member_count: u64,
+    now: Timestamp,
+) -> String {
+    let account_age = now.unix_timestamp() - user.id.created_at().unix_timestamp();
+
+    template
+        .replace("{user}", &user.name)
+        .replace("{mention}", &user.mention().to_string())
+        .replace("{guild}", guild_name)
+        .replace("{member_count}", &member_count.to_string())
+        .replace("{account_age}", &describe_age(account_age))
+}
+
+/// Sends a guild's greeting for a member joining or leaving, if it has one.
+pub async fn send_greeting(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    kind: GreetingKind,
+    guild_id: GuildId,
+    user: &User,
+) -> Result<(), sqlx::Error> {
+    let Some(greeting) = select_greeting(&guild_id, kind, pool).await? else {
+        return Ok(());
+    };
+
+    let Some((guild_name, member_count)) = context
+        .cache
+        .guild(guild_id)
+        .map(|guild| (guild.name.clone(), guild.member_count))
+    else {
+        return Ok(());
+    };
+
+    let content = render(
+        &greeting.template,
+        user,
+        &guild_name,
+        member_count,
+        Timestamp::now(),
+    );
+
+    let message = if greeting.embed {
+        let embed = CreateEmbed::new()
+            .description(content)
+            .thumbnail(user.face())
+            .colour(match kind {
+                GreetingKind::Welcome => Colour::DARK_GREEN,
+                GreetingKind::Goodbye => Colour::DARK_GREY,
+            });
+
+        CreateMessage::new().embed(embed)
+    } else {
+        CreateMessage::new().content(content)
+    };
+
+    if let Some(channel_id) = greeting.channel_id {
+        if let Err(why) = channel_id.send_message(context, message.clone()).await {
+            error!(
+                "Couldn't send {} message to {channel_id}: {why:?}",
+                kind.as_str()
+            );
+        }
+    }
+
+    if greeting.dm {
+        if let Err(why) = user.direct_message(context, message).await {
+            error!(
+                "Couldn't send {} DM to @{}: {why:?}",
+                kind.as_str(),
+                user.name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn select_greeting(
+    guild_id: &GuildId,
+    kind: GreetingKind,
+    pool: &SqlitePool,
+) -> Result<Option<Greeting>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let row = sqlx::query(
+        "SELECT channel_id, template, embed, dm FROM greeting WHERE guild_id = ? AND kind = ?",
+    )
+    .bind(i64::from(*guild_id))
+    .bind(kind.as_str())
+    .fetch_optional(pool)
+    .await?;
+
+    let greeting = row.map(|row| Greeting {
+        channel_id: row
+            .get::<Option<i64>, _>(0)
+            .map(|id| ChannelId::new(id as u64)),
+        template: row.get::<String, _>(1),
+        embed: row.get::<i64, _>(2) == 1,
+        dm: row.get::<i64, _>(3) == 1,
+    });
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "greeting", elapsed_time);
+    info!("Selected from Greetings in {elapsed_time:.2?}");
+
+    Ok(greeting)
+}
+
+pub async fn upsert_greeting(
+    guild_id: &GuildId,
+    kind: GreetingKind,
+    greeting: &Greeting,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO greeting (guild_id, kind, channel_id, template, embed, dm) VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (guild_id, kind) DO UPDATE SET channel_id = excluded.channel_id, template = excluded.template, embed = excluded.embed, dm = excluded.dm"
+    )
+        .bind(i64::from(*guild_id))
+        .bind(kind.as_str())
+        .bind(greeting.channel_id.map(i64::from))
+        .bind(&greeting.template)
+        .bind(greeting.embed as i64)
+        .bind(greeting.dm as i64);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("upsert", "greeting", elapsed_time);
+    info!("Upserted into Greetings in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Removes a guild's greeting, returning whether it had one.
+pub async fn delete_greeting(
+    guild_id: &GuildId,
+    kind: GreetingKind,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM greeting WHERE guild_id = ? AND kind = ?")
+        .bind(i64::from(*guild_id))
+        .bind(kind.as_str());
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "greeting", elapsed_time);
+    info!("Deleted from Greetings in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod greeting_tests {
+    use super::*;
+    use poise::serenity_prelude::UserId;
+
+    #[test]
+    fn describe_age_test() {
+        assert_eq!(describe_age(59), "0 minutes");
+        assert_eq!(describe_age(3600), "1 hour");
+        assert_eq!(describe_age(3 * 86400 + 5), "3 days");
+        assert_eq!(describe_age(800 * 86400), "2 years");
+    }
+
+    #[test]
+    fn render_test() {
+        let mut user = User::default();
+        user.id = UserId::new(175928847299117063);
+        user.name = "mei".to_string();
+
+        let now = Timestamp::from_unix_timestamp(user.id.created_at().unix_timestamp() + 2 * 86400)
+            .unwrap();
+
+        assert_eq!(
+            render(
+                "Welcome {user} ({mention}) to {guild}, member #{member_count}! Account: {account_age}.",
+                &user,
+                "Hamburg",
+                42,
+                now
+            ),
+            "Welcome mei (<@175928847299117063>) to Hamburg, member #42! Account: 2 days."
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::{HashMap, HashSet};
+
+use bismarck_core::context::Context;
+use poise::serenity_prelude as serenity;
+use serenity::all::{Guild, GuildId, Member, Role, StatusCode, UserId};
+use tracing::error;
+
+/// Where a member sits in a guild's role hierarchy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub is_owner: bool,
+    /// Position of the member's highest role, `0` being `@everyone`.
+    pub top_role: u16,
+}
+
+impl Position {
+    pub fn of(guild: &Guild, member: &Member) -> Self {
+        Self {
+            is_owner: guild.owner_id == member.user.id,
+            top_role: guild
+                .member_highest_role(member)
+                .map_or(0, |role| role.position),
+        }
+    }
+
+    /// Whether the member may hand out a role at `role_position`, which must be below their
+    /// highest role unless they own the guild.
+    pub fn outranks_role(&self, role_position: u16) -> bool {
+        self.is_owner || role_position < self.top_role
+    }
+}
+
+/// Reasons a moderation action is refused before it is attempted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyError {
+    /// The target owns the guild.
+    TargetIsOwner,
+    /// The target's highest role is equal to or above the moderator's.
+    ModeratorTooLow,
+    /// The target's highest role is equal to or above the bot's.
+    BotTooLow,
+    /// The guild or one of the members couldn't be resolved.
+    Unavailable,
+}
+
+impl HierarchyError {
+    /// A user facing explanation, `action` being the verb of the command, such as "ban".
+    pub fn message(&self, action: &str) -> String {
+        match self {
+            HierarchyError::TargetIsOwner => format!("Sorry, but you cannot {action} the server owner."),
+            HierarchyError::ModeratorTooLow => format!(
+                "Sorry, but you cannot {action} someone whose highest role is equal to or above yours."
+            ),
+            HierarchyError::BotTooLow => format!(
+                "Sorry, but I cannot {action} someone whose highest role is equal to or above mine."
+            ),
+            HierarchyError::Unavailable => {
+                format!("Sorry, but I couldn't verify the role hierarchy to {action} this user.")
+            }
+        }
+    }
+}
+
+/// Compares role positions of the moderator, the target and the bot.
+///
+/// A target that is not a member of the guild, such as a user being unbanned, has no position
+/// and passes every check.
+pub fn compare(
+    moderator: Position,
+    target: Option<Position>,
+    bot: Position,
+) -> Result<(), HierarchyError> {
+    let Some(target) = target else {
+        return Ok(());
+    };
+
+    if target.is_owner {
+        return Err(HierarchyError::TargetIsOwner);
+    }
+
+    if !moderator.is_owner && moderator.top_role <= target.top_role {
+        return Err(HierarchyError::ModeratorTooLow);
+    }
+
+    if !bot.is_owner && bot.top_role <= target.top_role {
+        return Err(HierarchyError::BotTooLow);
+    }
+
+    Ok(())
+}
+
+/// Checks that both the invoking moderator and the bot outrank the target.
+///
+/// Must be called before any DM or API call targeting the user.
+pub async fn check_hierarchy(
+    context: Context<'_>,
+    target: Option<&Member>,
+) -> Result<(), HierarchyError> {
+    let Some(guild_id) = context.guild_id() else {
+        return Err(HierarchyError::Unavailable);
+    };
+
+    let Some(moderator) = context.author_member().await else {
+        return Err(HierarchyError::Unavailable);
+    };
+
+    check_members(context.serenity_context(), guild_id, &moderator, target).await
+}
+
+/// Checks that both the given moderator and the bot outrank the target.
+pub async fn check_members(
+    context: &serenity::Context,
+    guild_id: GuildId,
+    moderator: &Member,
+    target: Option<&Member>,
+) -> Result<(), HierarchyError> {
+    let bot = bot_member(context, guild_id).await?;
+
+    let Some(guild) = context.cache.guild(guild_id) else {
+        return Err(HierarchyError::Unavailable);
+    };
+
+    compare(
+        Position::of(&guild, moderator),
+        target.map(|target| Position::of(&guild, target)),
+        Position::of(&guild, &bot),
+    )
+}
+
+/// Checks that the invoking member outranks `role`, so they cannot give themselves or others
+/// roles above their own through the bot.
+pub async fn check_role(context: Context<'_>, role: &Role) -> Result<(), HierarchyError> {
+    let Some(moderator) = context.author_member().await else {
+        return Err(HierarchyError::Unavailable);
+    };
+
+    let Some(guild) = context.guild() else {
+        return Err(HierarchyError::Unavailable);
+    };
+
+    if Position::of(&guild, &moderator).outranks_role(role.position) {
+        Ok(())
+    } else {
+        Err(HierarchyError::ModeratorTooLow)
+    }
+}
+
+/// Removes the targets that the moderator or the bot cannot act on, returning how many were
+/// removed.
+///
+/// Targets missing from the cache are fetched, and kept only if they turn out not to be
+/// members. Targets whose membership can't be verified are removed.
+pub async fn retain_outranked(
+    context: Context<'_>,
+    targets: &mut Vec<UserId>,
+) -> Result<usize, HierarchyError> {
+    let Some(guild_id) = context.guild_id() else {
+        return Err(HierarchyError::Unavailable);
+    };
+
+    let Some(moderator) = context.author_member().await else {
+        return Err(HierarchyError::Unavailable);
+    };
+
+    let bot = bot_member(context.serenity_context(), guild_id).await?;
+
+    let uncached: Vec<UserId> = {
+        let Some(guild) = context.guild() else {
+            return Err(HierarchyError::Unavailable);
+        };
+
+        targets
+            .iter()
+            .filter(|user_id| !guild.members.contains_key(user_id))
+            .copied()
+            .collect()
+    };
+
+    let mut fetched = HashMap::new();
+    let mut unverified = HashSet::new();
+
+    for user_id in uncached {
+        match fetch_member(context.serenity_context(), guild_id, user_id).await {
+            Ok(Some(member)) => {
+                fetched.insert(user_id, member);
+            }
+            Ok(None) => {}
+            Err(why) => {
+                error!("Couldn't get member {user_id} of guild {guild_id}: {why:?}");
+                unverified.insert(user_id);
+            }
+        }
+    }
+
+    let Some(guild) = context.guild() else {
+        return Err(HierarchyError::Unavailable);
+    };
+
+    let (moderator, bot) = (Position::of(&guild, &moderator), Position::of(&guild, &bot));
+
+    let before = targets.len();
+    targets.retain(|user_id| {
+        if unverified.contains(user_id) {
+            return false;
+        }
+
+        let target = guild
+            .members
+            .get(user_id)
+            .or_else(|| fetched.get(user_id))
+            .map(|member| Position::of(&guild, member));
+
+        compare(moderator, target, bot).is_ok()
+    });
+
+    Ok(before - targets.len())
+}
+
+/// Resolves a member of a guild, preferring the cache. Users that aren't members, which
+/// Discord answers with a 404, resolve to `None`.
+pub async fn fetch_member(
+    context: &serenity::Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<Option<Member>, serenity::Error> {
+    if let Some(member) = context
+        .cache
+        .guild(guild_id)
+        .and_then(|guild| guild.members.get(&user_id).cloned())
+    {
+        return Ok(Some(member));
+    }
+
+    match guild_id.member(context, user_id).await {
+        Ok(member) => Ok(Some(member)),
+        Err(serenity::Error::Http(why)) if why.status_code() == Some(StatusCode::NOT_FOUND) => {
+            Ok(None)
+        }
+        Err(why) => Err(why),
+    }
+}
+
+/// Resolves the bot's own member, preferring the cache.
+pub async fn bot_member(
+    context: &serenity::Context,
+    guild_id: GuildId,
+) -> Result<Member, HierarchyError> {
+    let bot_id = context.cache.current_user().id;
+
+    if let Some(member) = context
+        .cache
+        .guild(guild_id)
+        .and_then(|guild| guild.members.get(&bot_id).cloned())
+    {
+        return Ok(member);
+    }
+
+    match guild_id.member(context, bot_id).await {
+        Ok(member) => Ok(member),
+        Err(why) => {
+            error!("Couldn't get bot member: {why:?}");
+            Err(HierarchyError::Unavailable)
+        }
+    }
+}
+
+#[cfg(test)]
+mod hierarchy_tests {
+    use super::*;
+
+    fn member(top_role: u16) -> Position {
+        Position {
+            is_owner: false,
+            top_role,
+        }
+    }
+
+    const OWNER: Position = Position {
+        is_owner: true,
+        top_role: 0,
+    };
+
+    #[test]
+    fn outranked_target_test() {
+        assert_eq!(compare(member(5), Some(member(2)), member(10)), Ok(()));
+        assert_eq!(compare(OWNER, Some(member(9)), member(10)), Ok(()));
+    }
+
+    #[test]
+    fn refused_target_test() {
+        assert_eq!(
+            compare(member(5), Some(OWNER), member(10)),
+            Err(HierarchyError::TargetIsOwner)
+        );
+        assert_eq!(
+            compare(member(5), Some(member(5)), member(10)),
+            Err(HierarchyError::ModeratorTooLow)
+        );
+        assert_eq!(
+            compare(OWNER, Some(member(10)), member(10)),
+            Err(HierarchyError::BotTooLow)
+        );
+    }
+
+    #[test]
+    fn non_member_target_test() {
+        assert_eq!(compare(member(0), None, member(0)), Ok(()));
+    }
+
+    #[test]
+    fn outranks_role_test() {
+        assert!(member(5).outranks_role(4));
+        assert!(!member(5).outranks_role(5));
+        assert!(!member(5).outranks_role(6));
+        assert!(OWNER.outranks_role(10));
+    }
+}
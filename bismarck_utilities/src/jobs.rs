@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use poise::serenity_prelude as serenity;
+use serenity::all::{ChannelId, GuildId, RoleId, UserId};
+use sqlx::SqlitePool;
+
+use bismarck_core::{
+    error::Error,
+    scheduler::{Job, Scheduler},
+    types::RaidSettings,
+};
+
+use crate::{raid, reminder, roles};
+
+/// What scheduled jobs run with, cloned from the bot's data as jobs outlive any one event.
+#[derive(Clone)]
+pub struct JobContext {
+    pub context: serenity::Context,
+    pub pool: SqlitePool,
+    pub raids: Arc<DashMap<u64, RaidSettings>>,
+    pub scheduler: Scheduler,
+}
+
+/// Runs a scheduled job. Errors are retried by the scheduler.
+pub async fn run(job_context: JobContext, job: Job) -> Result<(), Error> {
+    let JobContext {
+        context,
+        pool,
+        raids,
+        scheduler,
+    } = &job_context;
+
+    match job {
+        Job::Reminder { reminder_id } => {
+            reminder::remind(context, pool, scheduler, reminder_id).await
+        }
+        Job::EndRaid {
+            guild_id,
+            until,
+            mod_log_channel,
+        } => {
+            raid::end_scheduled_raid(
+                &context.http,
+                raids,
+                pool,
+                mod_log_channel.map(ChannelId::new),
+                GuildId::new(guild_id),
+                until,
+            )
+            .await
+        }
+        Job::GiveAutoroles {
+            guild_id,
+            user_id,
+            role_ids,
+        } => {
+            let role_ids = role_ids.into_iter().map(RoleId::new).collect::<Vec<_>>();
+            roles::give_delayed_autoroles(
+                &context.http,
+                GuildId::new(guild_id),
+                UserId::new(user_id),
+                &role_ids,
+            )
+            .await
+        }
+    }
+}
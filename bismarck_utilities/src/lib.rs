@@ -1,7 +1,21 @@
+pub mod automod;
+pub mod chart;
 pub mod command;
 pub mod embeds;
 pub mod git;
+pub mod greeting;
+pub mod hierarchy;
+pub mod jobs;
+pub mod lockdown;
 pub mod messages;
 pub mod models;
 pub mod modlog;
 pub mod paginate;
+pub mod raid;
+pub mod reminder;
+pub mod role_menu;
+pub mod roles;
+pub mod starboard;
+pub mod ticket;
+pub mod usage;
+pub mod wiki;
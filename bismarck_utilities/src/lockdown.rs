@@ -0,0 +1,117 @@
+use poise::serenity_prelude as serenity;
+use serenity::all::{ChannelId, GuildId, PermissionOverwrite, Permissions};
+use sqlx::{Row, SqlitePool};
+use tokio::time::Instant;
+use tracing::{error, info};
+
+use bismarck_core::metrics;
+
+/// The `@everyone` overwrite a channel had before it was locked.
+pub struct SavedOverwrite {
+    pub channel_id: ChannelId,
+    /// `None` when the channel had no `@everyone` overwrite at all.
+    pub overwrite: Option<(Permissions, Permissions)>,
+}
+
+/// Saves the previous `@everyone` overwrite of a channel about to be locked.
+///
+/// Returns `false` without overwriting anything if the channel is already locked,
+/// so that unlocking always restores the state from before the first lockdown.
+pub async fn insert_lockdown(
+    guild_id: &GuildId,
+    channel_id: &ChannelId,
+    previous: Option<&PermissionOverwrite>,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let (had_overwrite, allow, deny) = match previous {
+        Some(overwrite) => (1, overwrite.allow.bits(), overwrite.deny.bits()),
+        None => (0, 0, 0),
+    };
+
+    let query = sqlx::query(
+        "INSERT OR IGNORE INTO channel_lockdown (guild_id, channel_id, had_overwrite, allow, deny) VALUES (?, ?, ?, ?, ?)"
+    )
+        .bind(i64::from(*guild_id))
+        .bind(i64::from(*channel_id))
+        .bind(had_overwrite)
+        .bind(allow as i64)
+        .bind(deny as i64);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "channel_lockdown", elapsed_time);
+    info!("Inserted into Channel Lockdowns in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Selects every saved overwrite of the locked channels in a guild.
+pub async fn select_lockdowns(
+    guild_id: &GuildId,
+    pool: &SqlitePool,
+) -> Result<Vec<SavedOverwrite>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let rows = sqlx::query(
+        "SELECT channel_id, had_overwrite, allow, deny FROM channel_lockdown WHERE guild_id = ?",
+    )
+    .bind(i64::from(*guild_id))
+    .fetch_all(pool)
+    .await?;
+
+    let saved = rows
+        .iter()
+        .map(|row| {
+            let channel_id = ChannelId::new(row.get::<i64, _>(0) as u64);
+            let overwrite = (row.get::<i64, _>(1) == 1).then(|| {
+                (
+                    Permissions::from_bits_truncate(row.get::<i64, _>(2) as u64),
+                    Permissions::from_bits_truncate(row.get::<i64, _>(3) as u64),
+                )
+            });
+
+            SavedOverwrite {
+                channel_id,
+                overwrite,
+            }
+        })
+        .collect();
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "channel_lockdown", elapsed_time);
+    info!("Selected from Channel Lockdowns in {elapsed_time:.2?}");
+
+    Ok(saved)
+}
+
+pub async fn delete_lockdown(
+    guild_id: &GuildId,
+    channel_id: &ChannelId,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM channel_lockdown WHERE guild_id = ? AND channel_id = ?")
+        .bind(i64::from(*guild_id))
+        .bind(i64::from(*channel_id));
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "channel_lockdown", elapsed_time);
+    info!("Deleted from Channel Lockdowns in {elapsed_time:.2?}");
+
+    Ok(())
+}
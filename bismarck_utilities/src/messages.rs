@@ -17,6 +17,18 @@ pub async fn error_response(
     CreateInteractionResponse::Message(response_message)
 }
 
+pub async fn info_response(
+    message: impl Into<String>,
+    ephemeral: bool,
+) -> CreateInteractionResponse {
+    let embed = embeds::info_message_embed(&message.into());
+
+    let response_message = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(ephemeral);
+    CreateInteractionResponse::Message(response_message)
+}
+
 pub fn info_message(message: impl Into<String>) -> CreateMessage {
     let embed = embeds::info_message_embed(&message.into());
 
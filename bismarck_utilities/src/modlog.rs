@@ -1,12 +1,26 @@
+use std::future::Future;
+
 use ::serenity::all::Member;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use poise::serenity_prelude as serenity;
-use serenity::all::{GuildId, UserId};
-use sqlx::{Row, SqlitePool};
+use serenity::all::{
+    ChannelId, Colour, CreateMessage, EditMember, GuildId, Http, Mentionable, Timestamp, User,
+    UserId,
+};
+use sqlx::{Row, SqliteExecutor, SqlitePool};
 use tokio::time::Instant;
 use tracing::{debug, error, info};
 use uuid::Uuid;
 
+use bismarck_core::{
+    context::Context,
+    data::Data,
+    error::{BismarckError, Error},
+    metrics,
+};
+
+use crate::{embeds, hierarchy, messages};
+
 pub enum ModType {
     Warn,
     Timeout,
@@ -14,6 +28,11 @@ pub enum ModType {
     Kick,
     Ban,
     Unban,
+    Purge,
+    Lockdown,
+    Unlock,
+    Slowmode,
+    Delete,
 }
 
 impl ModType {
@@ -25,6 +44,11 @@ impl ModType {
             ModType::Kick => "kick",
             ModType::Ban => "ban",
             ModType::Unban => "unban",
+            ModType::Purge => "purge",
+            ModType::Lockdown => "lockdown",
+            ModType::Unlock => "unlock",
+            ModType::Slowmode => "slowmode",
+            ModType::Delete => "delete",
         }
     }
 }
@@ -38,7 +62,7 @@ pub async fn select_modlog(
     let start_time = Instant::now();
 
     let rows = sqlx::query(
-        "SELECT uuid, action_type, user_id, moderator_id, reason, time_created, guild_id FROM guild_log WHERE user_id = ? AND guild_id = ? AND action_type = ?"
+        "SELECT uuid, guild_id, user_id, moderator_id, reason, time_created, action_type FROM guild_log WHERE user_id = ? AND guild_id = ? AND action_type = ?"
     )
         .bind(i64::from(*user_id))
         .bind(i64::from(*guild_id))
@@ -52,7 +76,7 @@ pub async fn select_modlog(
             return Err(sqlx::Error::RowNotFound);
         }
 
-        let (uuid, guild_id, user_id, moderator_id, action_type, created_at, reason) = (
+        let (uuid, guild_id, user_id, moderator_id, reason, created_at, action_type) = (
             row.get::<String, _>(0),
             row.get::<i64, _>(1),
             row.get::<i64, _>(2),
@@ -67,13 +91,14 @@ pub async fn select_modlog(
             guild_id,
             user_id,
             moderator_id,
-            action_type,
-            created_at,
             reason,
+            created_at,
+            action_type,
         ));
     }
 
     let elapsed_time = start_time.elapsed();
+    metrics::query("select", "guild_log", elapsed_time);
     info!("Selected from Moderation Logs in {elapsed_time:.2?}");
 
     Ok(logs)
@@ -96,6 +121,7 @@ pub async fn delete_mod_log(
     }
 
     let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "guild_log", elapsed_time);
     info!("Deleted from Moderation Logs in {elapsed_time:.2?}");
 
     Ok(())
@@ -112,6 +138,8 @@ pub async fn insert_modlog(
 ) -> Result<(), sqlx::Error> {
     let start_time = Instant::now();
 
+    ensure_moderator(moderator_id, pool).await?;
+
     let uuid = Uuid::new_v4().to_string();
 
     let query = sqlx::query(
@@ -131,28 +159,38 @@ pub async fn insert_modlog(
     }
 
     let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "guild_log", elapsed_time);
 
     info!("Inserted into Moderation Logs in {elapsed_time:.2?}");
 
     Ok(())
 }
 
-pub async fn ensure_user(
-    member: &Member,
-    user_id: &UserId,
+/// Inserts a guild-wide moderation log entry that is not tied to a single user,
+/// such as the summary of a purge.
+pub async fn insert_modlog_summary(
+    action_type: ModType,
     guild_id: &GuildId,
+    moderator_id: &UserId,
+    reason: &str,
+    created_at: NaiveDateTime,
     pool: &SqlitePool,
 ) -> Result<(), sqlx::Error> {
     let start_time = Instant::now();
 
-    let join = member.joined_at.unwrap().to_rfc2822();
+    ensure_moderator(moderator_id, pool).await?;
+
+    let uuid = Uuid::new_v4().to_string();
 
     let query = sqlx::query(
-        "INSERT OR IGNORE INTO user_guild (user_id, guild_id, infractions, join_date) VALUES (?, ?, ?, ?)")
-        .bind(i64::from(*user_id))
-        .bind(i64::from(*guild_id))
-        .bind(0)
-        .bind(join);
+        "INSERT INTO guild_log (uuid, action_type, user_id, moderator_id, reason, time_created, guild_id) VALUES (?, ?, NULL, ?, ?, ?, ?)"
+    )
+        .bind(uuid)
+        .bind(action_type.as_str())
+        .bind(i64::from(*moderator_id))
+        .bind(reason)
+        .bind(created_at)
+        .bind(i64::from(*guild_id));
 
     if let Err(why) = query.execute(pool).await {
         error!("Failed to execute query: {:?}", why);
@@ -160,59 +198,793 @@ pub async fn ensure_user(
     }
 
     let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "guild_log", elapsed_time);
 
-    info!("Ensured user in Users in {elapsed_time:.2?}");
+    info!("Inserted summary into Moderation Logs in {elapsed_time:.2?}");
 
     Ok(())
 }
 
-pub async fn select_modlog_from_users(
+/// Records when a user joined a guild, keeping their infractions if they joined before.
+pub async fn upsert_join_date(
     user_id: &UserId,
+    guild_id: &GuildId,
+    joined_at: Timestamp,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO user_guild (user_id, guild_id, infractions, join_date) VALUES (?, ?, 0, ?)
+        ON CONFLICT (user_id, guild_id) DO UPDATE SET join_date = excluded.join_date",
+    )
+    .bind(i64::from(*user_id))
+    .bind(i64::from(*guild_id))
+    .bind(joined_at.to_rfc2822());
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("upsert", "user_guild", elapsed_time);
+
+    info!("Upserted join date in Users in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Makes sure a moderator exists in Users, as moderation logs reference them.
+///
+/// Users are otherwise only inserted when command counters are flushed, so a moderator's first
+/// command, or the bot moderating on its own, may not have a row yet. Every insert into
+/// Moderation Logs calls this first.
+async fn ensure_moderator<'e>(
+    moderator_id: &UserId,
+    executor: impl SqliteExecutor<'e>,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query =
+        sqlx::query("INSERT OR IGNORE INTO user (id) VALUES (?)").bind(i64::from(*moderator_id));
+
+    if let Err(why) = query.execute(executor).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "user", elapsed_time);
+
+    debug!("Ensured moderator in Users in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Inserts a moderation log entry and increments the user's infractions in the same guild,
+/// both in a single transaction.
+///
+/// Users never seen in the guild before are recorded with the time of the infraction as their
+/// join date. Returns the user's new infraction count.
+pub async fn insert_infraction(
+    action_type: ModType,
+    guild_id: &GuildId,
+    user_id: &UserId,
+    moderator_id: &UserId,
+    reason: &str,
+    created_at: NaiveDateTime,
     pool: &SqlitePool,
 ) -> Result<i32, sqlx::Error> {
     let start_time = Instant::now();
 
-    let query = sqlx::query("SELECT infractions FROM user_guild WHERE user_id = ?")
-        .bind(i64::from(*user_id));
-    let row = match query.fetch_one(pool).await {
-        Ok(infractions) => infractions,
+    let mut transaction = pool.begin().await?;
+
+    ensure_moderator(moderator_id, &mut *transaction).await?;
+
+    let uuid = Uuid::new_v4().to_string();
+
+    let query = sqlx::query(
+        "INSERT INTO guild_log (uuid, action_type, user_id, moderator_id, reason, time_created, guild_id) VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+        .bind(uuid)
+        .bind(action_type.as_str())
+        .bind(i64::from(*user_id))
+        .bind(i64::from(*moderator_id))
+        .bind(reason)
+        .bind(created_at)
+        .bind(i64::from(*guild_id));
+
+    if let Err(why) = query.execute(&mut *transaction).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let query = sqlx::query(
+        "INSERT INTO user_guild (user_id, guild_id, join_date, infractions) VALUES (?, ?, ?, 1)
+        ON CONFLICT (user_id, guild_id) DO UPDATE SET infractions = infractions + 1
+        RETURNING infractions",
+    )
+    .bind(i64::from(*user_id))
+    .bind(i64::from(*guild_id))
+    .bind(created_at.and_utc().to_rfc2822());
+
+    let infractions = match query.fetch_one(&mut *transaction).await {
+        Ok(row) => row.get::<i32, _>("infractions"),
         Err(why) => {
-            error!("Couldn't select infractions from Users: {why:?}");
+            error!("Couldn't increment infractions in Users: {why:?}");
             return Err(why);
         }
     };
 
-    let infractions = match row.try_get::<i32, _>("infractions") {
-        Ok(infractions) => infractions,
+    transaction.commit().await?;
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "guild_log", elapsed_time);
+    info!("Inserted infraction into Moderation Logs in {elapsed_time:.2?}");
+
+    Ok(infractions)
+}
+
+/// Selects the number of infractions a user has in a guild.
+pub async fn select_infractions(
+    user_id: &UserId,
+    guild_id: &GuildId,
+    pool: &SqlitePool,
+) -> Result<i32, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query =
+        sqlx::query("SELECT infractions FROM user_guild WHERE user_id = ? AND guild_id = ?")
+            .bind(i64::from(*user_id))
+            .bind(i64::from(*guild_id));
+
+    let infractions = match query.fetch_optional(pool).await {
+        Ok(row) => row.map_or(0, |row| row.get::<i32, _>("infractions")),
         Err(why) => {
-            error!("Couldn't get infractions: {why:?}");
+            error!("Couldn't select infractions from Users: {why:?}");
             return Err(why);
         }
     };
 
     let elapsed_time = start_time.elapsed();
+    metrics::query("select", "user_guild", elapsed_time);
     debug!("Selected infractions from Users in {elapsed_time:.2?}");
 
     Ok(infractions)
 }
 
-pub async fn update_users_set_modlog(
-    user_id: &UserId,
-    infractions: i32,
-    pool: &SqlitePool,
-) -> Result<(), sqlx::Error> {
-    let start_time = Instant::now();
+/// What happened to a moderation action run through [`execute`].
+pub enum ModerationOutcome {
+    /// The action was applied. Holds the confirmation shown to the moderator.
+    Applied(String),
+    /// The action was refused or failed. Holds the explanation shown to the moderator.
+    Refused(String),
+}
 
-    let query = sqlx::query("UPDATE user_guild SET infractions = ? WHERE user_id = ?")
-        .bind(infractions)
-        .bind(i64::from(*user_id));
-    if let Err(why) = query.execute(pool).await {
-        error!("Couldn't update infractions for user(s) in Users: {why:?}");
-        return Err(why);
+/// A moderation action that can be run through [`execute`].
+///
+/// Implementors only describe what is specific to the action; fetching the target, validation,
+/// the DM, logging and infraction tracking are handled by the pipeline.
+pub trait ModerationAction: Sync {
+    /// The type recorded in the moderation logs.
+    fn mod_type(&self) -> ModType;
+
+    /// The verb used in messages, such as "ban".
+    fn verb(&self) -> &'static str;
+
+    /// The past tense used in messages, such as "banned".
+    fn past_tense(&self) -> &'static str;
+
+    /// Whether the target has to be a member of the guild.
+    fn requires_member(&self) -> bool {
+        true
     }
 
-    let elapsed_time = start_time.elapsed();
-    debug!("Updated infractions for user(s) within Users in {elapsed_time:.2?}");
+    /// Whether the action counts towards the target's infractions.
+    fn is_infraction(&self) -> bool {
+        true
+    }
+
+    /// The DM sent to the target before the action is applied, if any.
+    ///
+    /// `{guild}`, `{moderator}` and `{reason}` are replaced before sending.
+    fn dm_template(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// The colour of the embed posted to the mod log channel.
+    fn colour(&self) -> Colour {
+        Colour::ORANGE
+    }
+
+    /// Applies the action through the Discord API.
+    fn apply(
+        &self,
+        http: &Http,
+        guild_id: GuildId,
+        user_id: UserId,
+        reason: &str,
+    ) -> impl Future<Output = serenity::Result<()>> + Send;
+}
+
+pub struct Ban {
+    pub delete_message_days: u8,
+}
+
+impl ModerationAction for Ban {
+    fn mod_type(&self) -> ModType {
+        ModType::Ban
+    }
+
+    fn verb(&self) -> &'static str {
+        "ban"
+    }
+
+    fn past_tense(&self) -> &'static str {
+        "banned"
+    }
+
+    fn requires_member(&self) -> bool {
+        false
+    }
+
+    fn dm_template(&self) -> Option<&'static str> {
+        Some("You've been banned from {guild} by {moderator} for {reason}.")
+    }
+
+    fn colour(&self) -> Colour {
+        Colour::RED
+    }
+
+    async fn apply(
+        &self,
+        http: &Http,
+        guild_id: GuildId,
+        user_id: UserId,
+        reason: &str,
+    ) -> serenity::Result<()> {
+        guild_id
+            .ban_with_reason(http, user_id, self.delete_message_days, reason)
+            .await
+    }
+}
+
+pub struct Kick;
+
+impl ModerationAction for Kick {
+    fn mod_type(&self) -> ModType {
+        ModType::Kick
+    }
+
+    fn verb(&self) -> &'static str {
+        "kick"
+    }
+
+    fn past_tense(&self) -> &'static str {
+        "kicked"
+    }
+
+    fn dm_template(&self) -> Option<&'static str> {
+        Some("You've been kicked from {guild} by {moderator} for {reason}.")
+    }
+
+    async fn apply(
+        &self,
+        http: &Http,
+        guild_id: GuildId,
+        user_id: UserId,
+        reason: &str,
+    ) -> serenity::Result<()> {
+        guild_id.kick_with_reason(http, user_id, reason).await
+    }
+}
+
+pub struct Unban;
+
+impl ModerationAction for Unban {
+    fn mod_type(&self) -> ModType {
+        ModType::Unban
+    }
+
+    fn verb(&self) -> &'static str {
+        "unban"
+    }
+
+    fn past_tense(&self) -> &'static str {
+        "unbanned"
+    }
+
+    fn requires_member(&self) -> bool {
+        false
+    }
+
+    fn is_infraction(&self) -> bool {
+        false
+    }
+
+    fn colour(&self) -> Colour {
+        Colour::DARK_GREEN
+    }
+
+    async fn apply(
+        &self,
+        http: &Http,
+        guild_id: GuildId,
+        user_id: UserId,
+        _reason: &str,
+    ) -> serenity::Result<()> {
+        guild_id.unban(http, user_id).await
+    }
+}
+
+pub struct Timeout {
+    pub until: Timestamp,
+}
+
+impl ModerationAction for Timeout {
+    fn mod_type(&self) -> ModType {
+        ModType::Timeout
+    }
+
+    fn verb(&self) -> &'static str {
+        "timeout"
+    }
+
+    fn past_tense(&self) -> &'static str {
+        "timed out"
+    }
+
+    fn dm_template(&self) -> Option<&'static str> {
+        Some("You've been timed out in {guild} by {moderator} for {reason}.")
+    }
+
+    async fn apply(
+        &self,
+        http: &Http,
+        guild_id: GuildId,
+        user_id: UserId,
+        reason: &str,
+    ) -> serenity::Result<()> {
+        let builder = EditMember::new()
+            .disable_communication_until_datetime(self.until)
+            .audit_log_reason(reason);
+
+        guild_id.edit_member(http, user_id, builder).await?;
+        Ok(())
+    }
+}
+
+pub struct Untimeout;
+
+impl ModerationAction for Untimeout {
+    fn mod_type(&self) -> ModType {
+        ModType::Untimeout
+    }
+
+    fn verb(&self) -> &'static str {
+        "untimeout"
+    }
+
+    fn past_tense(&self) -> &'static str {
+        "untimed out"
+    }
+
+    fn is_infraction(&self) -> bool {
+        false
+    }
+
+    fn colour(&self) -> Colour {
+        Colour::DARK_GREEN
+    }
+
+    async fn apply(
+        &self,
+        http: &Http,
+        guild_id: GuildId,
+        user_id: UserId,
+        reason: &str,
+    ) -> serenity::Result<()> {
+        let builder = EditMember::new()
+            .enable_communication()
+            .audit_log_reason(reason);
+
+        guild_id.edit_member(http, user_id, builder).await?;
+        Ok(())
+    }
+}
+
+pub struct Warn;
+
+impl ModerationAction for Warn {
+    fn mod_type(&self) -> ModType {
+        ModType::Warn
+    }
+
+    fn verb(&self) -> &'static str {
+        "warn"
+    }
+
+    fn past_tense(&self) -> &'static str {
+        "warned"
+    }
+
+    fn dm_template(&self) -> Option<&'static str> {
+        Some("You've been warned in {guild} by {moderator} for {reason}.")
+    }
+
+    fn colour(&self) -> Colour {
+        Colour::GOLD
+    }
+
+    async fn apply(
+        &self,
+        _http: &Http,
+        _guild_id: GuildId,
+        _user_id: UserId,
+        _reason: &str,
+    ) -> serenity::Result<()> {
+        // A warning only exists in the moderation logs.
+        Ok(())
+    }
+}
+
+/// Runs a moderation action invoked by a command, replying to the moderator with the outcome.
+pub async fn moderate(
+    context: Context<'_>,
+    action: &impl ModerationAction,
+    user_id: UserId,
+    reason: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = context.guild_id().ok_or_else(|| {
+        BismarckError::UserInput("This command can only be used in a server.".to_string())
+    })?;
+
+    let Some(moderator) = context.author_member().await else {
+        let reply = messages::error_reply("Sorry, but I couldn't find you in this server.", true);
+        context.send(reply).await?;
+        return Ok(());
+    };
+
+    let reason = reason.unwrap_or_else(|| "No reason provided.".to_string());
+
+    let outcome = execute(
+        context.serenity_context(),
+        context.data(),
+        action,
+        guild_id,
+        &moderator,
+        user_id,
+        &reason,
+    )
+    .await?;
+
+    let reply = match outcome {
+        ModerationOutcome::Applied(message) => messages::info_reply(message, true),
+        ModerationOutcome::Refused(message) => messages::error_reply(message, true),
+    };
+    context.send(reply).await?;
 
     Ok(())
 }
+
+/// Runs a moderation action against a user.
+///
+/// The target is validated and the role hierarchy checked before anything is sent to them. The
+/// target is then notified, the action applied and recorded in the moderation logs, their
+/// infractions updated, and the action posted to the guild's mod log channel.
+pub async fn execute(
+    context: &serenity::Context,
+    data: &Data,
+    action: &impl ModerationAction,
+    guild_id: GuildId,
+    moderator: &Member,
+    user_id: UserId,
+    reason: &str,
+) -> Result<ModerationOutcome, Error> {
+    let database = &data.sqlite;
+    let verb = action.verb();
+    let moderator_id = moderator.user.id;
+
+    let user = match user_id.to_user(context).await {
+        Ok(user) => user,
+        Err(why) => {
+            error!("Couldn't get user: {why:?}");
+            return Ok(ModerationOutcome::Refused(
+                "Sorry, but I couldn't find that user.".to_string(),
+            ));
+        }
+    };
+
+    let member = match hierarchy::fetch_member(context, guild_id, user_id).await {
+        Ok(member) => member,
+        Err(why) => {
+            error!("Couldn't get member: {why:?}");
+            return Err(why.into());
+        }
+    };
+
+    if member.is_none() && action.requires_member() {
+        return Ok(ModerationOutcome::Refused(format!(
+            "<@{user_id}> is not a member of this server."
+        )));
+    }
+
+    if user.system {
+        return Ok(ModerationOutcome::Refused(format!(
+            "Cannot {verb} a system user."
+        )));
+    }
+
+    if user_id == moderator_id {
+        return Ok(ModerationOutcome::Refused(format!(
+            "Sorry, but you cannot {verb} yourself."
+        )));
+    }
+
+    if reason.chars().count() > 80 {
+        return Ok(ModerationOutcome::Refused(
+            "Reason must be no more than 80 characters long.".to_string(),
+        ));
+    }
+
+    if let Err(why) = hierarchy::check_members(context, guild_id, moderator, member.as_ref()).await
+    {
+        return Ok(ModerationOutcome::Refused(why.message(verb)));
+    }
+
+    let guild_name = context
+        .cache
+        .guild(guild_id)
+        .map(|guild| guild.name.clone())
+        .unwrap_or_default();
+
+    if let Some(template) = action.dm_template() {
+        let message = template
+            .replace("{guild}", &guild_name)
+            .replace("{moderator}", &moderator_id.mention().to_string())
+            .replace("{reason}", reason);
+
+        if let Err(why) = user
+            .direct_message(context, messages::info_message(message))
+            .await
+        {
+            error!("Couldn't send DM to @{}: {why:?}", user.name);
+        }
+    }
+
+    if let Err(why) = action.apply(&context.http, guild_id, user_id, reason).await {
+        error!("Couldn't {verb} @{}: {why:?}", user.name);
+        return Ok(ModerationOutcome::Refused(format!(
+            "Sorry, but I couldn't {verb} {}.",
+            user_id.mention()
+        )));
+    }
+
+    let created_at = Utc::now().naive_utc();
+
+    if action.is_infraction() {
+        insert_infraction(
+            action.mod_type(),
+            &guild_id,
+            &user_id,
+            &moderator_id,
+            reason,
+            created_at,
+            database,
+        )
+        .await?;
+    } else {
+        insert_modlog(
+            action.mod_type(),
+            &guild_id,
+            &user_id,
+            &moderator_id,
+            reason,
+            created_at,
+            database,
+        )
+        .await?;
+    }
+
+    info!(
+        "@{} {} @{} from {guild_name}: {reason}",
+        moderator.user.name,
+        action.past_tense(),
+        user.name
+    );
+
+    post_to_mod_log_channel(context, data, action, guild_id, &user, moderator_id, reason).await;
+
+    Ok(ModerationOutcome::Applied(format!(
+        "{} has been {}.",
+        user_id.mention(),
+        action.past_tense()
+    )))
+}
+
+/// Posts a moderation action to the guild's mod log channel, if one is set.
+async fn post_to_mod_log_channel(
+    context: &serenity::Context,
+    data: &Data,
+    action: &impl ModerationAction,
+    guild_id: GuildId,
+    user: &User,
+    moderator_id: UserId,
+    reason: &str,
+) {
+    let Some(channel_id) = data
+        .guild_data
+        .get(&guild_id.get())
+        .and_then(|settings| settings.mod_log_channel)
+    else {
+        return;
+    };
+
+    let embed = embeds::mod_log_embed(
+        action.mod_type().as_str(),
+        user,
+        moderator_id,
+        reason,
+        action.colour(),
+    );
+
+    if let Err(why) = ChannelId::new(channel_id)
+        .send_message(context, CreateMessage::new().embed(embed))
+        .await
+    {
+        error!("Couldn't post to mod log channel {channel_id}: {why:?}");
+    }
+}
+
+#[cfg(test)]
+mod modlog_tests {
+    use super::*;
+    use tokio::task::JoinSet;
+
+    const GUILD_A: GuildId = GuildId::new(1);
+    const GUILD_B: GuildId = GuildId::new(2);
+    const USER: UserId = UserId::new(10);
+    const MODERATOR: UserId = UserId::new(20);
+
+    async fn pool() -> SqlitePool {
+        seed(bismarck_core::testing::pool().await).await
+    }
+
+    async fn seed(pool: SqlitePool) -> SqlitePool {
+        sqlx::query("INSERT INTO user (id) VALUES (?)")
+            .bind(i64::from(MODERATOR))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        for guild_id in [GUILD_A, GUILD_B] {
+            sqlx::query(
+                "INSERT INTO guild (id, owner, commands_ran, songs_played) VALUES (?, ?, 0, 0)",
+            )
+            .bind(i64::from(guild_id))
+            .bind(i64::from(MODERATOR))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        pool
+    }
+
+    async fn infraction(
+        guild_id: GuildId,
+        moderator_id: UserId,
+        pool: &SqlitePool,
+    ) -> Result<i32, sqlx::Error> {
+        insert_infraction(
+            ModType::Warn,
+            &guild_id,
+            &USER,
+            &moderator_id,
+            "test",
+            Utc::now().naive_utc(),
+            pool,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn per_guild_infractions_test() {
+        let pool = pool().await;
+
+        assert_eq!(infraction(GUILD_A, MODERATOR, &pool).await.unwrap(), 1);
+        assert_eq!(infraction(GUILD_A, MODERATOR, &pool).await.unwrap(), 2);
+        assert_eq!(infraction(GUILD_B, MODERATOR, &pool).await.unwrap(), 1);
+
+        assert_eq!(select_infractions(&USER, &GUILD_A, &pool).await.unwrap(), 2);
+        assert_eq!(select_infractions(&USER, &GUILD_B, &pool).await.unwrap(), 1);
+        assert_eq!(
+            select_infractions(&MODERATOR, &GUILD_A, &pool)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn concurrent_infractions_test() {
+        let pool = seed(bismarck_core::testing::shared_pool(5).await).await;
+
+        let mut tasks = JoinSet::new();
+        for _ in 0..25 {
+            let pool = pool.clone();
+            tasks.spawn(async move { infraction(GUILD_A, MODERATOR, &pool).await });
+        }
+
+        let mut counts = Vec::new();
+        while let Some(result) = tasks.join_next().await {
+            counts.push(result.unwrap().unwrap());
+        }
+
+        // The infractions were inserted through several connections at once.
+        assert!(pool.size() > 1);
+
+        // Every infraction got its own count, so none were lost to a concurrent one.
+        counts.sort_unstable();
+        assert_eq!(counts, (1..=25).collect::<Vec<_>>());
+
+        assert_eq!(
+            select_infractions(&USER, &GUILD_A, &pool).await.unwrap(),
+            25
+        );
+
+        let logs = select_modlog(ModType::Warn, &USER, &GUILD_A, &pool)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 25);
+    }
+
+    #[tokio::test]
+    async fn new_moderator_test() {
+        let pool = pool().await;
+        let moderator_id = UserId::new(30);
+
+        // Moderators without a row in the user table yet are inserted along with the log.
+        assert_eq!(infraction(GUILD_A, moderator_id, &pool).await.unwrap(), 1);
+        insert_modlog(
+            ModType::Kick,
+            &GUILD_A,
+            &USER,
+            &moderator_id,
+            "test",
+            Utc::now().naive_utc(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        insert_modlog_summary(
+            ModType::Purge,
+            &GUILD_B,
+            &UserId::new(31),
+            "test",
+            Utc::now().naive_utc(),
+            &pool,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_log_rolls_back_test() {
+        let pool = pool().await;
+        let moderator_id = UserId::new(99);
+
+        // The guild must exist in the guild table, so the log insert fails.
+        assert!(infraction(GuildId::new(99), moderator_id, &pool)
+            .await
+            .is_err());
+
+        let moderators = sqlx::query("SELECT id FROM user WHERE id = ?")
+            .bind(i64::from(moderator_id))
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert!(moderators.is_empty());
+    }
+}
@@ -1,66 +1,208 @@
+use std::time::Instant;
+
+use chrono::Utc;
 use poise::serenity_prelude as serenity;
+use serenity::all::{ComponentInteraction, CreateEmbed, Embed};
+use sqlx::{Row, SqlitePool};
+use tracing::{error, info};
 use uuid::Uuid;
 
-use bismarck_core::context::Context;
-
-/// Paginates a list of embeds using UUID as custom ID to identify the buttons.
-pub async fn paginate(
-    ctx: Context<'_>,
-    pages: Vec<serenity::all::CreateEmbed>,
-) -> Result<(), serenity::Error> {
-    // Define some unique identifiers for the navigation buttons
-    let ctx_id = Uuid::new_v4();
-    let prev_button_id = format!("{}prev", ctx_id);
-    let next_button_id = format!("{}next", ctx_id);
-
-    // Send the embed with the first page as content
-    let reply = {
-        let components = serenity::CreateActionRow::Buttons(vec![
-            serenity::CreateButton::new(&prev_button_id).emoji('◀'),
-            serenity::CreateButton::new(&next_button_id).emoji('▶'),
-        ]);
-
-        poise::CreateReply::default()
-            .embed(pages[0].clone())
-            .components(vec![components])
+use bismarck_core::{
+    context::Context,
+    error::{BismarckError, Error},
+    metrics,
+};
+
+use crate::messages;
+
+/// Custom IDs of navigation buttons start with this, followed by the ID of the pages, the
+/// button's direction and the page it goes to, all separated by colons.
+pub const CUSTOM_ID_PREFIX: &str = "page";
+
+/// How long pages are kept for, after which their buttons stop working.
+const PAGES_LIFETIME: i64 = 30 * 24 * 60 * 60;
+
+/// The previous and next buttons for the page at `current` out of `len`, wrapping around.
+fn buttons(pages_id: &str, current: usize, len: usize) -> serenity::CreateActionRow {
+    let previous = current.checked_sub(1).unwrap_or(len - 1);
+    let next = if current + 1 >= len { 0 } else { current + 1 };
+
+    serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(format!("{CUSTOM_ID_PREFIX}:{pages_id}:prev:{previous}"))
+            .emoji('◀'),
+        serenity::CreateButton::new(format!("{CUSTOM_ID_PREFIX}:{pages_id}:next:{next}"))
+            .emoji('▶'),
+    ])
+}
+
+/// Splits a navigation button's custom ID into the ID of its pages and the page it goes to.
+fn parse_custom_id(custom_id: &str) -> Option<(&str, usize)> {
+    let mut parts = custom_id.split(':');
+
+    if parts.next()? != CUSTOM_ID_PREFIX {
+        return None;
+    }
+
+    let pages_id = parts.next()?;
+    parts.next()?;
+    let page = parts.next()?.parse().ok()?;
+
+    Some((pages_id, page))
+}
+
+/// Paginates a list of embeds, storing them so the navigation buttons keep working after the
+/// command has returned and across restarts.
+pub async fn paginate(ctx: Context<'_>, pages: Vec<CreateEmbed>) -> Result<(), Error> {
+    let Some(first) = pages.first().cloned() else {
+        return Ok(());
     };
 
+    let pages_id = Uuid::new_v4().to_string();
+    insert_pages(&pages_id, &pages, &ctx.data().sqlite).await?;
+
+    let reply = poise::CreateReply::default()
+        .embed(first)
+        .components(vec![buttons(&pages_id, 0, pages.len())]);
+
     ctx.send(reply).await?;
 
-    // Loop through incoming interactions with the navigation buttons
-    let mut current_page = 0;
-    while let Some(press) = serenity::collector::ComponentInteractionCollector::new(ctx)
-        // We defined our button IDs to start with `ctx_id`. If they don't, some other command's
-        // button was pressed
-        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
-        // Timeout when no navigation button has been pressed for 24 hours
-        .timeout(std::time::Duration::from_secs(3600 * 24))
-        .await
-    {
-        // Depending on which button was pressed, go to next or previous page
-        if press.data.custom_id == next_button_id {
-            current_page += 1;
-            if current_page >= pages.len() {
-                current_page = 0;
-            }
-        } else if press.data.custom_id == prev_button_id {
-            current_page = current_page.checked_sub(1).unwrap_or(pages.len() - 1);
-        } else {
-            // This is an unrelated button interaction
-            continue;
-        }
-
-        // Update the message with the new page contents
-        press
-            .create_response(
-                ctx.serenity_context(),
-                serenity::CreateInteractionResponse::UpdateMessage(
-                    serenity::CreateInteractionResponseMessage::new()
-                        .embed(pages[current_page].clone()),
-                ),
-            )
-            .await?;
+    Ok(())
+}
+
+/// Handles a click on a navigation button, showing the page it goes to.
+pub async fn handle_component(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let Some((pages_id, page)) = parse_custom_id(&interaction.data.custom_id) else {
+        return Ok(());
+    };
+
+    let pages = select_pages(pages_id, pool).await?;
+
+    let Some(embed) = pages.get(page).cloned() else {
+        let response = messages::error_response("These pages are no longer available.", true).await;
+        interaction.create_response(context, response).await?;
+        return Ok(());
+    };
+
+    let response = serenity::CreateInteractionResponse::UpdateMessage(
+        serenity::CreateInteractionResponseMessage::new()
+            .embed(embed)
+            .components(vec![buttons(pages_id, page, pages.len())]),
+    );
+    interaction.create_response(context, response).await?;
+
+    Ok(())
+}
+
+/// Stores the pages of a paginated message, removing pages that have expired.
+pub async fn insert_pages(
+    pages_id: &str,
+    pages: &[CreateEmbed],
+    pool: &SqlitePool,
+) -> Result<(), Error> {
+    let start_time = Instant::now();
+    let now = Utc::now().timestamp();
+
+    let pages = serde_json::to_string(pages)
+        .map_err(|why| BismarckError::Internal(format!("Couldn't serialize pages: {why}")))?;
+
+    sqlx::query("DELETE FROM paginated_message WHERE created_at < ?")
+        .bind(now - PAGES_LIFETIME)
+        .execute(pool)
+        .await?;
+
+    let query =
+        sqlx::query("INSERT INTO paginated_message (id, pages, created_at) VALUES (?, ?, ?)")
+            .bind(pages_id)
+            .bind(pages)
+            .bind(now);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why.into());
     }
 
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "paginated_message", elapsed_time);
+    info!("Inserted into Paginated Messages in {elapsed_time:.2?}");
+
     Ok(())
 }
+
+/// Selects the pages of a paginated message, none if they have expired.
+pub async fn select_pages(pages_id: &str, pool: &SqlitePool) -> Result<Vec<CreateEmbed>, Error> {
+    let start_time = Instant::now();
+
+    let row = sqlx::query("SELECT pages FROM paginated_message WHERE id = ?")
+        .bind(pages_id)
+        .fetch_optional(pool)
+        .await?;
+
+    let pages = match row {
+        Some(row) => deserialize_pages(&row.get::<String, _>(0))
+            .map_err(|why| BismarckError::Internal(format!("Couldn't deserialize pages: {why}")))?,
+        None => Vec::new(),
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "paginated_message", elapsed_time);
+    info!("Selected from Paginated Messages in {elapsed_time:.2?}");
+
+    Ok(pages)
+}
+
+/// Embeds are only serializable as builders, so pages are read back as embeds and turned into
+/// builders again.
+fn deserialize_pages(pages: &str) -> Result<Vec<CreateEmbed>, serde_json::Error> {
+    let embeds: Vec<Embed> = serde_json::from_str(pages)?;
+
+    Ok(embeds.into_iter().map(CreateEmbed::from).collect())
+}
+
+#[cfg(test)]
+mod paginate_tests {
+    use super::*;
+
+    #[test]
+    fn buttons_test() {
+        let custom_ids = |row: serenity::CreateActionRow| {
+            let row = serde_json::to_value(row).unwrap();
+            row["components"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|button| button["custom_id"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            custom_ids(buttons("id", 0, 3)),
+            vec!["page:id:prev:2", "page:id:next:1"]
+        );
+        assert_eq!(
+            custom_ids(buttons("id", 2, 3)),
+            vec!["page:id:prev:1", "page:id:next:0"]
+        );
+        assert_eq!(parse_custom_id("page:id:next:1"), Some(("id", 1)));
+        assert_eq!(parse_custom_id("rolemenu:1:2"), None);
+    }
+
+    #[test]
+    fn pages_round_trip_test() {
+        let pages = vec![
+            CreateEmbed::new()
+                .title("Warnings")
+                .color(0x008b_0000)
+                .field("Reason", "Spam", false),
+            CreateEmbed::new().description("Second page"),
+        ];
+
+        let serialized = serde_json::to_string(&pages).unwrap();
+        let deserialized = deserialize_pages(&serialized).unwrap();
+
+        assert_eq!(deserialized, pages);
+    }
+}
@@ -0,0 +1,514 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use dashmap::DashMap;
+use poise::{serenity_prelude as serenity, ChoiceParameter};
+use serenity::all::{
+    ChannelId, Colour, CreateMessage, EditGuild, GuildId, Http, Member, Timestamp,
+    VerificationLevel,
+};
+use sqlx::{Row, SqlitePool};
+use tracing::{debug, error, info};
+
+use bismarck_core::{
+    data::Data,
+    error::Error,
+    metrics,
+    scheduler::Job,
+    types::{RaidAction, RaidSettings},
+};
+
+use crate::{
+    embeds, hierarchy,
+    modlog::{self, ModerationOutcome},
+};
+
+/// What set raid mode off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaidTrigger {
+    /// Too many members joined. Holds how many.
+    Joins(usize),
+    /// Too many new accounts joined. Holds how many.
+    NewAccounts(usize),
+}
+
+impl RaidTrigger {
+    pub fn description(&self, join_window: u64) -> String {
+        match self {
+            RaidTrigger::Joins(joins) => format!("{joins} members joined within {join_window}s"),
+            RaidTrigger::NewAccounts(joins) => {
+                format!("{joins} new accounts joined within {join_window}s")
+            }
+        }
+    }
+}
+
+/// Records a join in the guild's recent joins, returning a trigger if it puts the guild over
+/// its join limits.
+///
+/// The guild's recent joins are cleared on a trigger.
+pub fn record_join(
+    recent: &mut VecDeque<(Instant, bool)>,
+    joined_at: Instant,
+    is_new_account: bool,
+    settings: &RaidSettings,
+) -> Option<RaidTrigger> {
+    let window = Duration::from_secs(settings.join_window);
+
+    while recent
+        .front()
+        .is_some_and(|(at, _)| joined_at.duration_since(*at) > window)
+    {
+        recent.pop_front();
+    }
+
+    recent.push_back((joined_at, is_new_account));
+
+    let joins = recent.len();
+    let new_accounts = recent.iter().filter(|(_, is_new)| *is_new).count();
+
+    let trigger = if settings.join_limit > 0 && joins > settings.join_limit as usize {
+        Some(RaidTrigger::Joins(joins))
+    } else if settings.new_account_limit > 0 && new_accounts > settings.new_account_limit as usize {
+        Some(RaidTrigger::NewAccounts(new_accounts))
+    } else {
+        None
+    };
+
+    if trigger.is_some() {
+        recent.clear();
+    }
+
+    trigger
+}
+
+/// Watches a member joining, turning raid mode on when the guild's join limits are exceeded and
+/// moderating members joining while it is on. Returns whether the member was moderated.
+pub async fn on_member_join(
+    context: &serenity::Context,
+    data: &Data,
+    member: &Member,
+) -> Result<bool, Error> {
+    if member.user.bot {
+        return Ok(false);
+    }
+
+    let guild_id = member.guild_id;
+
+    let (trigger, join_window) = {
+        let Some(settings) = data.raid.get(&guild_id.get()) else {
+            return Ok(false);
+        };
+
+        if !settings.enabled {
+            return Ok(false);
+        }
+
+        if settings.raid_until.is_some() {
+            (None, settings.join_window)
+        } else {
+            let account_age = Utc::now().timestamp() - member.user.id.created_at().unix_timestamp();
+            let is_new_account = account_age < settings.new_account_age as i64;
+
+            let mut recent = data.recent_joins.entry(guild_id.get()).or_default();
+            let trigger = record_join(&mut recent, Instant::now(), is_new_account, &settings);
+
+            if trigger.is_none() {
+                return Ok(false);
+            }
+
+            (trigger, settings.join_window)
+        }
+    };
+
+    if let Some(trigger) = trigger {
+        start_raid(context, data, guild_id, &trigger.description(join_window)).await?;
+    }
+
+    let Some((action, until)) = data.raid.get(&guild_id.get()).and_then(|settings| {
+        settings
+            .raid_until
+            .map(|raid_until| (settings.action, raid_until))
+    }) else {
+        return Ok(false);
+    };
+
+    let moderator = match hierarchy::bot_member(context, guild_id).await {
+        Ok(moderator) => moderator,
+        Err(why) => {
+            error!("Couldn't get bot member for anti-raid: {why:?}");
+            return Ok(false);
+        }
+    };
+
+    let reason = "Anti-raid: joined during a raid.";
+    let user_id = member.user.id;
+
+    let outcome = match action {
+        RaidAction::Kick => {
+            modlog::execute(
+                context,
+                data,
+                &modlog::Kick,
+                guild_id,
+                &moderator,
+                user_id,
+                reason,
+            )
+            .await?
+        }
+        RaidAction::Timeout => {
+            modlog::execute(
+                context,
+                data,
+                &modlog::Timeout { until },
+                guild_id,
+                &moderator,
+                user_id,
+                reason,
+            )
+            .await?
+        }
+    };
+
+    match outcome {
+        ModerationOutcome::Applied(_) => Ok(true),
+        ModerationOutcome::Refused(why) => {
+            debug!("Anti-raid skipped @{}: {why}", member.user.name);
+            Ok(false)
+        }
+    }
+}
+
+/// Turns raid mode on, raising the guild's verification level and alerting the mod log
+/// channel. Returns `false` if raid mode was already on.
+pub async fn start_raid(
+    context: &serenity::Context,
+    data: &Data,
+    guild_id: GuildId,
+    reason: &str,
+) -> Result<bool, Error> {
+    let current_level = context
+        .cache
+        .guild(guild_id)
+        .map(|guild| guild.verification_level);
+
+    let (until, settings) = {
+        let mut settings = data.raid.entry(guild_id.get()).or_default();
+
+        if settings.raid_until.is_some() {
+            return Ok(false);
+        }
+
+        let until = Timestamp::from(Utc::now() + Duration::from_secs(settings.cooldown));
+        settings.raid_until = Some(until);
+        settings.previous_verification = current_level
+            .filter(|level| *level < VerificationLevel::High)
+            .map(u8::from);
+
+        (until, settings.clone())
+    };
+
+    upsert_raid_settings(&guild_id, &settings, &data.sqlite).await?;
+
+    if settings.previous_verification.is_some() {
+        let builder = EditGuild::new()
+            .verification_level(VerificationLevel::High)
+            .audit_log_reason("Raid mode turned on.");
+
+        if let Err(why) = guild_id.edit(context, builder).await {
+            error!("Couldn't raise verification level of guild {guild_id}: {why:?}");
+        }
+    }
+
+    info!("Raid mode turned on in guild {guild_id}: {reason}");
+
+    let mod_log_channel = mod_log_channel(data, guild_id);
+    let action = match settings.action {
+        RaidAction::Kick => "kicked",
+        RaidAction::Timeout => "timed out",
+    };
+
+    alert(
+        &context.http,
+        mod_log_channel,
+        "Raid mode on",
+        format!(
+            "{reason}. New members will be {action} until <t:{}:t>.",
+            until.unix_timestamp()
+        ),
+        Colour::RED,
+    )
+    .await;
+
+    schedule_end(data, guild_id, until).await?;
+
+    Ok(true)
+}
+
+/// Turns raid mode off, restoring the guild's verification level and alerting the mod log
+/// channel. Returns `false` if raid mode was already off.
+pub async fn end_raid(
+    http: &Http,
+    raids: &DashMap<u64, RaidSettings>,
+    pool: &SqlitePool,
+    mod_log_channel: Option<ChannelId>,
+    guild_id: GuildId,
+) -> Result<bool, Error> {
+    let (settings, previous_verification) = {
+        let Some(mut settings) = raids.get_mut(&guild_id.get()) else {
+            return Ok(false);
+        };
+
+        if settings.raid_until.is_none() {
+            return Ok(false);
+        }
+
+        settings.raid_until = None;
+        let previous_verification = settings.previous_verification.take();
+
+        (settings.clone(), previous_verification)
+    };
+
+    upsert_raid_settings(&guild_id, &settings, pool).await?;
+
+    if let Some(level) = previous_verification {
+        let builder = EditGuild::new()
+            .verification_level(VerificationLevel::from(level))
+            .audit_log_reason("Raid mode turned off.");
+
+        if let Err(why) = guild_id.edit(http, builder).await {
+            error!("Couldn't restore verification level of guild {guild_id}: {why:?}");
+        }
+    }
+
+    info!("Raid mode turned off in guild {guild_id}");
+
+    alert(
+        http,
+        mod_log_channel,
+        "Raid mode off",
+        "New members can join again.",
+        Colour::DARK_GREEN,
+    )
+    .await;
+
+    Ok(true)
+}
+
+/// Resumes the countdown of a raid that was on when the bot last stopped.
+pub async fn resume_raid(data: &Data, guild_id: GuildId) -> Result<(), Error> {
+    let until = data
+        .raid
+        .get(&guild_id.get())
+        .and_then(|settings| settings.raid_until);
+
+    if let Some(until) = until {
+        schedule_end(data, guild_id, until).await?;
+    }
+
+    Ok(())
+}
+
+/// Schedules raid mode to turn off once `until` passes.
+async fn schedule_end(data: &Data, guild_id: GuildId, until: Timestamp) -> Result<(), Error> {
+    let job = Job::EndRaid {
+        guild_id: guild_id.get(),
+        until: until.unix_timestamp(),
+        mod_log_channel: mod_log_channel(data, guild_id).map(|channel_id| channel_id.get()),
+    };
+
+    data.scheduler
+        .schedule(&format!("raid:{guild_id}"), &job, *until)
+        .await
+}
+
+/// Turns raid mode off once its time is up, unless it was turned off or restarted in the
+/// meantime.
+pub async fn end_scheduled_raid(
+    http: &Http,
+    raids: &DashMap<u64, RaidSettings>,
+    pool: &SqlitePool,
+    mod_log_channel: Option<ChannelId>,
+    guild_id: GuildId,
+    until: i64,
+) -> Result<(), Error> {
+    let is_same_raid = raids.get(&guild_id.get()).is_some_and(|settings| {
+        settings
+            .raid_until
+            .is_some_and(|raid_until| raid_until.unix_timestamp() == until)
+    });
+
+    if is_same_raid {
+        end_raid(http, raids, pool, mod_log_channel, guild_id).await?;
+    }
+
+    Ok(())
+}
+
+pub fn mod_log_channel(data: &Data, guild_id: GuildId) -> Option<ChannelId> {
+    data.guild_data
+        .get(&guild_id.get())
+        .and_then(|settings| settings.mod_log_channel)
+        .map(ChannelId::new)
+}
+
+async fn alert(
+    http: &Http,
+    channel_id: Option<ChannelId>,
+    title: &str,
+    description: impl Into<String>,
+    colour: Colour,
+) {
+    let Some(channel_id) = channel_id else {
+        return;
+    };
+
+    let embed = embeds::alert_embed(title, description, colour);
+
+    if let Err(why) = channel_id
+        .send_message(http, CreateMessage::new().embed(embed))
+        .await
+    {
+        error!("Couldn't post to mod log channel {channel_id}: {why:?}");
+    }
+}
+
+/// Selects the anti-raid settings of every guild.
+pub async fn select_raid_settings(
+    pool: &SqlitePool,
+) -> Result<HashMap<u64, RaidSettings>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let rows = sqlx::query(
+        "SELECT guild_id, enabled, join_limit, join_window, new_account_limit, new_account_age, action, cooldown, raid_until, previous_verification FROM raid_config",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let settings = rows
+        .iter()
+        .map(|row| {
+            let settings = RaidSettings {
+                enabled: row.get::<i64, _>(1) == 1,
+                join_limit: row.get::<i64, _>(2) as u32,
+                join_window: row.get::<i64, _>(3) as u64,
+                new_account_limit: row.get::<i64, _>(4) as u32,
+                new_account_age: row.get::<i64, _>(5) as u64,
+                action: RaidAction::from_name(&row.get::<String, _>(6)).unwrap_or(RaidAction::Kick),
+                cooldown: row.get::<i64, _>(7) as u64,
+                raid_until: row
+                    .get::<Option<i64>, _>(8)
+                    .and_then(|until| Timestamp::from_unix_timestamp(until).ok()),
+                previous_verification: row.get::<Option<i64>, _>(9).map(|level| level as u8),
+            };
+
+            (row.get::<i64, _>(0) as u64, settings)
+        })
+        .collect();
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "raid_config", elapsed_time);
+    info!("Selected from Raid Settings in {elapsed_time:.2?}");
+
+    Ok(settings)
+}
+
+/// Inserts or updates the anti-raid settings of a guild, including whether raid mode is on.
+pub async fn upsert_raid_settings(
+    guild_id: &GuildId,
+    settings: &RaidSettings,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO raid_config (guild_id, enabled, join_limit, join_window, new_account_limit, new_account_age, action, cooldown, raid_until, previous_verification) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (guild_id) DO UPDATE SET enabled = excluded.enabled, join_limit = excluded.join_limit, join_window = excluded.join_window, new_account_limit = excluded.new_account_limit, new_account_age = excluded.new_account_age, action = excluded.action, cooldown = excluded.cooldown, raid_until = excluded.raid_until, previous_verification = excluded.previous_verification"
+    )
+        .bind(i64::from(*guild_id))
+        .bind(settings.enabled as i64)
+        .bind(settings.join_limit as i64)
+        .bind(settings.join_window as i64)
+        .bind(settings.new_account_limit as i64)
+        .bind(settings.new_account_age as i64)
+        .bind(settings.action.name())
+        .bind(settings.cooldown as i64)
+        .bind(settings.raid_until.map(|until| until.unix_timestamp()))
+        .bind(settings.previous_verification.map(i64::from));
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("upsert", "raid_config", elapsed_time);
+    info!("Upserted into Raid Settings in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod raid_tests {
+    use super::*;
+
+    #[test]
+    fn join_limit_test() {
+        let settings = RaidSettings {
+            join_limit: 3,
+            join_window: 10,
+            ..Default::default()
+        };
+        let mut recent = VecDeque::new();
+        let start = Instant::now();
+
+        for i in 0..3 {
+            let joined_at = start + Duration::from_secs(i);
+            assert_eq!(record_join(&mut recent, joined_at, false, &settings), None);
+        }
+
+        let joined_at = start + Duration::from_secs(3);
+        assert_eq!(
+            record_join(&mut recent, joined_at, false, &settings),
+            Some(RaidTrigger::Joins(4))
+        );
+        assert!(recent.is_empty());
+    }
+
+    #[test]
+    fn join_window_test() {
+        let settings = RaidSettings {
+            join_limit: 3,
+            join_window: 10,
+            ..Default::default()
+        };
+        let mut recent = VecDeque::new();
+        let start = Instant::now();
+
+        for i in 0..10 {
+            let joined_at = start + Duration::from_secs(i * 5);
+            assert_eq!(record_join(&mut recent, joined_at, false, &settings), None);
+        }
+    }
+
+    #[test]
+    fn new_account_test() {
+        let settings = RaidSettings {
+            join_limit: 10,
+            new_account_limit: 2,
+            ..Default::default()
+        };
+        let mut recent = VecDeque::new();
+        let start = Instant::now();
+
+        assert_eq!(record_join(&mut recent, start, true, &settings), None);
+        assert_eq!(record_join(&mut recent, start, false, &settings), None);
+        assert_eq!(record_join(&mut recent, start, true, &settings), None);
+        assert_eq!(
+            record_join(&mut recent, start, true, &settings),
+            Some(RaidTrigger::NewAccounts(3))
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use poise::serenity_prelude as serenity;
+use serenity::all::{
+    ChannelId, CreateAllowedMentions, CreateEmbed, CreateMessage, Mentionable, UserId,
+};
+use sqlx::{Row, SqlitePool};
+use tracing::{error, info, warn};
+
+use bismarck_core::{
+    error::{BismarckError, Error},
+    metrics,
+    scheduler::{Job, Scheduler},
+};
+
+/// Shortest time between repeats, so a reminder can't be used to spam.
+pub const MIN_REPEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Most reminders a user can have pending at once.
+pub const MAX_REMINDERS: i64 = 25;
+
+#[derive(Debug, Clone)]
+pub struct Reminder {
+    pub id: i64,
+    pub user_id: UserId,
+    /// Where the reminder is sent, or the user's DMs if not set.
+    pub channel_id: Option<ChannelId>,
+    pub message: String,
+    pub remind_at: i64,
+    /// Seconds between repeats, for repeating reminders.
+    pub repeat_interval: Option<i64>,
+    pub created_at: i64,
+}
+
+/// Parses an absolute time such as `2026-11-01 18:00`, read as UTC.
+pub fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    let time = time.trim();
+
+    ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(time, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(time, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .map(|time| time.and_utc())
+}
+
+/// The first time after `now` a repeating reminder is due, skipping repeats missed while the
+/// bot was offline.
+pub fn next_occurrence(remind_at: i64, interval: i64, now: i64) -> i64 {
+    if remind_at > now {
+        return remind_at;
+    }
+
+    let missed = (now - remind_at) / interval + 1;
+    remind_at + missed * interval
+}
+
+/// Key of the job sending a reminder.
+pub fn job_key(reminder_id: i64) -> String {
+    format!("reminder:{reminder_id}")
+}
+
+/// Schedules a reminder to be sent at its time.
+pub async fn schedule(
+    scheduler: &Scheduler,
+    reminder_id: i64,
+    remind_at: i64,
+) -> Result<(), Error> {
+    let Some(remind_at) = DateTime::from_timestamp(remind_at, 0) else {
+        return Err(BismarckError::UserInput(
+            "Invalid reminder time.".to_string(),
+        ));
+    };
+
+    scheduler
+        .schedule(
+            &job_key(reminder_id),
+            &Job::Reminder { reminder_id },
+            remind_at,
+        )
+        .await
+}
+
+/// Sends a due reminder, then schedules its next repeat or removes it. Reminders that came due
+/// while the bot was offline are sent as soon as it is back.
+pub async fn remind(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    scheduler: &Scheduler,
+    reminder_id: i64,
+) -> Result<(), Error> {
+    // Cancelled since it was scheduled.
+    let Some(reminder) = select_reminder(reminder_id, pool).await? else {
+        return Ok(());
+    };
+
+    deliver(context, &reminder).await;
+
+    match reminder.repeat_interval {
+        Some(repeat_interval) => {
+            let next = next_occurrence(reminder.remind_at, repeat_interval, Utc::now().timestamp());
+            update_reminder_time(reminder.id, next, pool).await?;
+            schedule(scheduler, reminder.id, next).await?;
+        }
+        None => {
+            delete_reminder(reminder.id, pool).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends a reminder where it was set, falling back to the user's DMs if the channel is gone or
+/// can't be sent to.
+async fn deliver(context: &serenity::Context, reminder: &Reminder) {
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title("Reminder")
+        .description(&reminder.message)
+        .field("Set", format!("<t:{}:R>", reminder.created_at), true);
+
+    if let Some(channel_id) = reminder.channel_id {
+        let message = CreateMessage::new()
+            .content(reminder.user_id.mention().to_string())
+            .embed(embed.clone())
+            .allowed_mentions(CreateAllowedMentions::new().users([reminder.user_id]));
+
+        match channel_id.send_message(context, message).await {
+            Ok(_) => return,
+            Err(why) => warn!(
+                "Couldn't send reminder {} in {channel_id}, sending it in DMs: {why:?}",
+                reminder.id
+            ),
+        }
+    }
+
+    if let Err(why) = reminder
+        .user_id
+        .direct_message(context, CreateMessage::new().embed(embed))
+        .await
+    {
+        error!("Couldn't send reminder {}: {why:?}", reminder.id);
+    }
+}
+
+const REMINDER_COLUMNS: &str =
+    "id, user_id, channel_id, message, remind_at, repeat_interval, created_at";
+
+fn reminder_from_row(row: &sqlx::sqlite::SqliteRow) -> Reminder {
+    Reminder {
+        id: row.get(0),
+        user_id: UserId::new(row.get::<i64, _>(1) as u64),
+        channel_id: row
+            .get::<Option<i64>, _>(2)
+            .map(|channel_id| ChannelId::new(channel_id as u64)),
+        message: row.get(3),
+        remind_at: row.get(4),
+        repeat_interval: row.get(5),
+        created_at: row.get(6),
+    }
+}
+
+pub async fn select_reminder(
+    reminder_id: i64,
+    pool: &SqlitePool,
+) -> Result<Option<Reminder>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let row = sqlx::query(&format!(
+        "SELECT {REMINDER_COLUMNS} FROM reminder WHERE id = ?"
+    ))
+    .bind(reminder_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "reminder", elapsed_time);
+    info!("Selected from Reminders in {elapsed_time:.2?}");
+
+    Ok(row.as_ref().map(reminder_from_row))
+}
+
+/// Selects a user's pending reminders, soonest first.
+pub async fn select_user_reminders(
+    user_id: &UserId,
+    pool: &SqlitePool,
+) -> Result<Vec<Reminder>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let rows = sqlx::query(&format!(
+        "SELECT {REMINDER_COLUMNS} FROM reminder WHERE user_id = ? ORDER BY remind_at"
+    ))
+    .bind(i64::from(*user_id))
+    .fetch_all(pool)
+    .await?;
+
+    let reminders = rows.iter().map(reminder_from_row).collect();
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "reminder", elapsed_time);
+    info!("Selected from Reminders in {elapsed_time:.2?}");
+
+    Ok(reminders)
+}
+
+/// Inserts a reminder, returning its ID.
+pub async fn insert_reminder(
+    user_id: &UserId,
+    channel_id: Option<&ChannelId>,
+    message: &str,
+    remind_at: i64,
+    repeat_interval: Option<i64>,
+    pool: &SqlitePool,
+) -> Result<i64, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO reminder (user_id, channel_id, message, remind_at, repeat_interval, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(i64::from(*user_id))
+    .bind(channel_id.map(|channel_id| i64::from(*channel_id)))
+    .bind(message)
+    .bind(remind_at)
+    .bind(repeat_interval)
+    .bind(Utc::now().timestamp());
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "reminder", elapsed_time);
+    info!("Inserted into Reminders in {elapsed_time:.2?}");
+
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn update_reminder_time(
+    reminder_id: i64,
+    remind_at: i64,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("UPDATE reminder SET remind_at = ? WHERE id = ?")
+        .bind(remind_at)
+        .bind(reminder_id);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("update", "reminder", elapsed_time);
+    info!("Updated Reminders in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+pub async fn delete_reminder(reminder_id: i64, pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM reminder WHERE id = ?").bind(reminder_id);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "reminder", elapsed_time);
+    info!("Deleted from Reminders in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Removes one of a user's reminders, returning whether they had it.
+pub async fn delete_user_reminder(
+    user_id: &UserId,
+    reminder_id: i64,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM reminder WHERE user_id = ? AND id = ?")
+        .bind(i64::from(*user_id))
+        .bind(reminder_id);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "reminder", elapsed_time);
+    info!("Deleted from Reminders in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod reminder_tests {
+    use super::*;
+
+    #[test]
+    fn parse_time_test() {
+        let expected = DateTime::from_timestamp(1_793_556_000, 0).unwrap();
+
+        assert_eq!(parse_time("2026-11-01 18:00"), Some(expected));
+        assert_eq!(parse_time(" 2026-11-01T18:00 "), Some(expected));
+        assert_eq!(
+            parse_time("2026-11-01"),
+            DateTime::from_timestamp(1_793_491_200, 0)
+        );
+        assert_eq!(parse_time("tomorrow"), None);
+    }
+
+    #[test]
+    fn next_occurrence_test() {
+        assert_eq!(next_occurrence(100, 60, 50), 100);
+        assert_eq!(next_occurrence(100, 60, 100), 160);
+        // Missed repeats are skipped rather than sent all at once.
+        assert_eq!(next_occurrence(100, 60, 400), 460);
+    }
+}
@@ -0,0 +1,818 @@
+use std::time::Instant;
+
+use poise::{serenity_prelude as serenity, ChoiceParameter};
+use serenity::all::{
+    ChannelId, ComponentInteraction, ComponentInteractionDataKind, CreateActionRow, CreateButton,
+    CreateEmbed, CreateMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+    EditMessage, GuildId, Http, Mentionable, MessageId, Reaction, ReactionType, RoleId, UserId,
+};
+use sqlx::{Row, SqlitePool};
+use tracing::{debug, error, info};
+
+use bismarck_core::{error::Error, metrics};
+
+use crate::messages;
+
+/// Custom IDs of role menu components start with this, followed by the menu ID and, for
+/// buttons, the role ID, all separated by colons.
+pub const CUSTOM_ID_PREFIX: &str = "rolemenu";
+
+/// How a role menu is picked from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum MenuKind {
+    #[name = "reactions"]
+    Reactions,
+    #[name = "buttons"]
+    Buttons,
+    #[name = "select"]
+    Select,
+}
+
+impl MenuKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MenuKind::Reactions => "reactions",
+            MenuKind::Buttons => "buttons",
+            MenuKind::Select => "select",
+        }
+    }
+
+    /// Most roles a menu of this kind can hold, bound by Discord's limits on reactions and
+    /// components.
+    pub fn max_options(&self) -> usize {
+        match self {
+            MenuKind::Reactions => 20,
+            MenuKind::Buttons | MenuKind::Select => 25,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MenuOption {
+    pub role_id: RoleId,
+    /// Required for reaction menus.
+    pub emoji: Option<ReactionType>,
+    pub label: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoleMenu {
+    pub id: i64,
+    pub guild_id: GuildId,
+    /// Where the menu is published, if it is.
+    pub channel_id: Option<ChannelId>,
+    pub message_id: Option<MessageId>,
+    pub kind: MenuKind,
+    /// Most roles a member may hold from the menu, `1` for a single choice and `0` for any.
+    pub max_roles: u32,
+    pub title: String,
+    pub description: Option<String>,
+    pub options: Vec<MenuOption>,
+}
+
+/// What picking a role from a menu does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoleChange {
+    /// The role is given, and the menu's other roles in `replaced` are taken away.
+    Add {
+        role_id: RoleId,
+        replaced: Vec<RoleId>,
+    },
+    /// The member already has the role, so it is taken away.
+    Remove(RoleId),
+    /// The member holds as many of the menu's roles as it allows.
+    Full,
+}
+
+/// Works out what picking `role_id` does for a member holding `held` of the menu's roles.
+pub fn toggle(held: &[RoleId], role_id: RoleId, max_roles: u32) -> RoleChange {
+    if held.contains(&role_id) {
+        return RoleChange::Remove(role_id);
+    }
+
+    match max_roles {
+        1 => RoleChange::Add {
+            role_id,
+            replaced: held.to_vec(),
+        },
+        0 => RoleChange::Add {
+            role_id,
+            replaced: Vec::new(),
+        },
+        max_roles if held.len() < max_roles as usize => RoleChange::Add {
+            role_id,
+            replaced: Vec::new(),
+        },
+        _ => RoleChange::Full,
+    }
+}
+
+/// Identifies an emoji regardless of its name, which can change for custom emojis.
+pub fn emoji_key(emoji: &ReactionType) -> String {
+    match emoji {
+        ReactionType::Custom { id, .. } => id.to_string(),
+        ReactionType::Unicode(unicode) => unicode.clone(),
+        _ => emoji.to_string(),
+    }
+}
+
+pub fn custom_id(menu_id: i64, role_id: Option<RoleId>) -> String {
+    match role_id {
+        Some(role_id) => format!("{CUSTOM_ID_PREFIX}:{menu_id}:{role_id}"),
+        None => format!("{CUSTOM_ID_PREFIX}:{menu_id}"),
+    }
+}
+
+/// Splits a role menu custom ID into its menu ID and, for buttons, role ID.
+pub fn parse_custom_id(custom_id: &str) -> Option<(i64, Option<RoleId>)> {
+    let mut parts = custom_id.split(':');
+
+    if parts.next()? != CUSTOM_ID_PREFIX {
+        return None;
+    }
+
+    let menu_id = parts.next()?.parse().ok()?;
+    let role_id = match parts.next() {
+        Some(role_id) => Some(RoleId::new(role_id.parse().ok().filter(|id| *id != 0)?)),
+        None => None,
+    };
+
+    Some((menu_id, role_id))
+}
+
+/// Builds the embed and components of a menu.
+pub fn render(menu: &RoleMenu) -> (CreateEmbed, Vec<CreateActionRow>) {
+    let limit = match menu.max_roles {
+        0 => "Pick any roles.".to_string(),
+        1 => "Pick one role.".to_string(),
+        max_roles => format!("Pick up to {max_roles} roles."),
+    };
+
+    let lines = menu
+        .options
+        .iter()
+        .map(|option| match &option.emoji {
+            Some(emoji) => format!("{emoji} {}", option.role_id.mention()),
+            None => option.role_id.mention().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let description = match &menu.description {
+        Some(description) => format!("{description}\n\n{lines}"),
+        None => lines,
+    };
+
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title(&menu.title)
+        .description(description)
+        .footer(serenity::CreateEmbedFooter::new(limit));
+
+    let components = match menu.kind {
+        MenuKind::Reactions => Vec::new(),
+        MenuKind::Buttons => menu
+            .options
+            .chunks(5)
+            .map(|options| {
+                let buttons = options
+                    .iter()
+                    .map(|option| {
+                        let button = CreateButton::new(custom_id(menu.id, Some(option.role_id)))
+                            .label(&option.label)
+                            .style(serenity::ButtonStyle::Secondary);
+
+                        match &option.emoji {
+                            Some(emoji) => button.emoji(emoji.clone()),
+                            None => button,
+                        }
+                    })
+                    .collect();
+
+                CreateActionRow::Buttons(buttons)
+            })
+            .collect(),
+        MenuKind::Select if menu.options.is_empty() => Vec::new(),
+        MenuKind::Select => {
+            let options = menu
+                .options
+                .iter()
+                .map(|option| {
+                    let select_option =
+                        CreateSelectMenuOption::new(&option.label, option.role_id.to_string());
+
+                    match &option.emoji {
+                        Some(emoji) => select_option.emoji(emoji.clone()),
+                        None => select_option,
+                    }
+                })
+                .collect();
+
+            let max_values = match menu.max_roles {
+                0 => menu.options.len(),
+                max_roles => (max_roles as usize).min(menu.options.len()),
+            };
+
+            let select = CreateSelectMenu::new(
+                custom_id(menu.id, None),
+                CreateSelectMenuKind::String { options },
+            )
+            .min_values(0)
+            .max_values(max_values as u8)
+            .placeholder("Select your roles");
+
+            vec![CreateActionRow::SelectMenu(select)]
+        }
+    };
+
+    (embed, components)
+}
+
+/// Posts a menu to a channel, or edits its message if it is already posted there, returning
+/// the message's ID.
+pub async fn publish(
+    http: &Http,
+    menu: &RoleMenu,
+    channel_id: ChannelId,
+) -> Result<MessageId, serenity::Error> {
+    let (embed, components) = render(menu);
+
+    let message = match (menu.channel_id, menu.message_id) {
+        (Some(published_in), Some(message_id)) if published_in == channel_id => {
+            let edit = EditMessage::new().embed(embed).components(components);
+            channel_id.edit_message(http, message_id, edit).await?
+        }
+        _ => {
+            let create = CreateMessage::new().embed(embed).components(components);
+            channel_id.send_message(http, create).await?
+        }
+    };
+
+    if menu.kind == MenuKind::Reactions {
+        for emoji in menu
+            .options
+            .iter()
+            .filter_map(|option| option.emoji.clone())
+        {
+            message.react(http, emoji).await?;
+        }
+    }
+
+    Ok(message.id)
+}
+
+async fn apply_change(
+    http: &Http,
+    guild_id: GuildId,
+    user_id: UserId,
+    change: &RoleChange,
+) -> Result<(), serenity::Error> {
+    match change {
+        RoleChange::Add { role_id, replaced } => {
+            for replaced in replaced {
+                http.remove_member_role(guild_id, user_id, *replaced, Some("Role menu"))
+                    .await?;
+            }
+
+            http.add_member_role(guild_id, user_id, *role_id, Some("Role menu"))
+                .await
+        }
+        RoleChange::Remove(role_id) => {
+            http.remove_member_role(guild_id, user_id, *role_id, Some("Role menu"))
+                .await
+        }
+        RoleChange::Full => Ok(()),
+    }
+}
+
+/// Handles a click on a role menu button or select menu.
+pub async fn handle_component(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let Some((menu_id, role_id)) = parse_custom_id(&interaction.data.custom_id) else {
+        return Ok(());
+    };
+
+    let (Some(guild_id), Some(member)) = (interaction.guild_id, &interaction.member) else {
+        return Ok(());
+    };
+
+    let Some(menu) = select_role_menu(&guild_id, menu_id, pool).await? else {
+        let response = messages::error_response("This role menu no longer exists.", true).await;
+        interaction.create_response(context, response).await?;
+        return Ok(());
+    };
+
+    let held = menu
+        .options
+        .iter()
+        .map(|option| option.role_id)
+        .filter(|role_id| member.roles.contains(role_id))
+        .collect::<Vec<_>>();
+
+    let changes = match (&interaction.data.kind, role_id) {
+        (ComponentInteractionDataKind::Button, Some(role_id))
+            if menu.options.iter().any(|option| option.role_id == role_id) =>
+        {
+            vec![toggle(&held, role_id, menu.max_roles)]
+        }
+        (ComponentInteractionDataKind::StringSelect { values }, None) => {
+            let selected = values
+                .iter()
+                .filter_map(|value| value.parse::<u64>().ok())
+                .filter(|id| *id != 0)
+                .map(RoleId::new)
+                .filter(|role_id| menu.options.iter().any(|option| option.role_id == *role_id))
+                .collect::<Vec<_>>();
+
+            let added = selected
+                .iter()
+                .filter(|role_id| !held.contains(role_id))
+                .map(|role_id| RoleChange::Add {
+                    role_id: *role_id,
+                    replaced: Vec::new(),
+                });
+            let removed = held
+                .iter()
+                .filter(|role_id| !selected.contains(role_id))
+                .map(|role_id| RoleChange::Remove(*role_id));
+
+            removed.chain(added).collect()
+        }
+        _ => {
+            let response =
+                messages::error_response("This role is no longer in the menu.", true).await;
+            interaction.create_response(context, response).await?;
+            return Ok(());
+        }
+    };
+
+    let mut summary = Vec::new();
+
+    for change in &changes {
+        if let Err(why) = apply_change(&context.http, guild_id, member.user.id, change).await {
+            error!("Couldn't apply role menu {menu_id} change {change:?}: {why:?}");
+
+            let response =
+                messages::error_response("Sorry, but I couldn't update your roles.", true).await;
+            interaction.create_response(context, response).await?;
+            return Ok(());
+        }
+
+        match change {
+            RoleChange::Add { role_id, replaced } => {
+                summary.extend(
+                    replaced
+                        .iter()
+                        .map(|role_id| format!("Removed {}", role_id.mention())),
+                );
+                summary.push(format!("Added {}", role_id.mention()));
+            }
+            RoleChange::Remove(role_id) => summary.push(format!("Removed {}", role_id.mention())),
+            RoleChange::Full => summary.push(format!(
+                "You can only pick up to {} roles from this menu.",
+                menu.max_roles
+            )),
+        }
+    }
+
+    if summary.is_empty() {
+        summary.push("Your roles are unchanged.".to_string());
+    }
+
+    let response = messages::info_response(summary.join("\n"), true).await;
+    interaction.create_response(context, response).await?;
+
+    Ok(())
+}
+
+/// Handles a reaction being added to or removed from a reaction role menu.
+pub async fn handle_reaction(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    reaction: &Reaction,
+    added: bool,
+) -> Result<(), Error> {
+    let (Some(guild_id), Some(user_id)) = (reaction.guild_id, reaction.user_id) else {
+        return Ok(());
+    };
+
+    if user_id == context.cache.current_user().id {
+        return Ok(());
+    }
+
+    let Some(menu) = select_role_menu_by_message(&reaction.message_id, pool).await? else {
+        return Ok(());
+    };
+
+    if menu.guild_id != guild_id || menu.kind != MenuKind::Reactions {
+        return Ok(());
+    }
+
+    let key = emoji_key(&reaction.emoji);
+    let Some(option) = menu.options.iter().find(|option| {
+        option
+            .emoji
+            .as_ref()
+            .is_some_and(|emoji| emoji_key(emoji) == key)
+    }) else {
+        return Ok(());
+    };
+
+    if !added {
+        let change = RoleChange::Remove(option.role_id);
+        if let Err(why) = apply_change(&context.http, guild_id, user_id, &change).await {
+            error!(
+                "Couldn't apply role menu {} change {change:?}: {why:?}",
+                menu.id
+            );
+        }
+        return Ok(());
+    }
+
+    let Some(member) = &reaction.member else {
+        return Ok(());
+    };
+
+    if member.user.bot {
+        return Ok(());
+    }
+
+    let held = menu
+        .options
+        .iter()
+        .map(|option| option.role_id)
+        .filter(|role_id| member.roles.contains(role_id))
+        .collect::<Vec<_>>();
+
+    let change = toggle(&held, option.role_id, menu.max_roles);
+
+    match &change {
+        // The reaction has just been added, so the member picked the role again.
+        RoleChange::Remove(_) => return Ok(()),
+        RoleChange::Full => {
+            debug!("Role menu {} is full for {user_id}", menu.id);
+            reaction.delete(context).await?;
+            return Ok(());
+        }
+        RoleChange::Add { replaced, .. } => {
+            // Take the member's reactions for the replaced roles away along with the roles.
+            for replaced in replaced {
+                let emoji = menu
+                    .options
+                    .iter()
+                    .find(|option| option.role_id == *replaced)
+                    .and_then(|option| option.emoji.clone());
+
+                if let Some(emoji) = emoji {
+                    if let Err(why) = reaction
+                        .channel_id
+                        .delete_reaction(context, reaction.message_id, Some(user_id), emoji)
+                        .await
+                    {
+                        debug!("Couldn't remove replaced reaction: {why:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    if let Err(why) = apply_change(&context.http, guild_id, user_id, &change).await {
+        error!(
+            "Couldn't apply role menu {} change {change:?}: {why:?}",
+            menu.id
+        );
+    }
+
+    Ok(())
+}
+
+fn menu_from_row(row: &sqlx::sqlite::SqliteRow) -> RoleMenu {
+    RoleMenu {
+        id: row.get::<i64, _>(0),
+        guild_id: GuildId::new(row.get::<i64, _>(1) as u64),
+        channel_id: row
+            .get::<Option<i64>, _>(2)
+            .map(|id| ChannelId::new(id as u64)),
+        message_id: row
+            .get::<Option<i64>, _>(3)
+            .map(|id| MessageId::new(id as u64)),
+        kind: MenuKind::from_name(&row.get::<String, _>(4)).unwrap_or(MenuKind::Select),
+        max_roles: row.get::<i64, _>(5) as u32,
+        title: row.get::<String, _>(6),
+        description: row.get::<Option<String>, _>(7),
+        options: Vec::new(),
+    }
+}
+
+async fn select_menu_options(
+    menu_id: i64,
+    pool: &SqlitePool,
+) -> Result<Vec<MenuOption>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT role_id, emoji, label FROM role_menu_option WHERE menu_id = ? ORDER BY position",
+    )
+    .bind(menu_id)
+    .fetch_all(pool)
+    .await?;
+
+    let options = rows
+        .iter()
+        .map(|row| MenuOption {
+            role_id: RoleId::new(row.get::<i64, _>(0) as u64),
+            emoji: row
+                .get::<Option<String>, _>(1)
+                .and_then(|emoji| ReactionType::try_from(emoji).ok()),
+            label: row.get::<String, _>(2),
+        })
+        .collect();
+
+    Ok(options)
+}
+
+const MENU_COLUMNS: &str =
+    "id, guild_id, channel_id, message_id, kind, max_roles, title, description";
+
+/// Selects a guild's role menu along with its options.
+pub async fn select_role_menu(
+    guild_id: &GuildId,
+    menu_id: i64,
+    pool: &SqlitePool,
+) -> Result<Option<RoleMenu>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let row = sqlx::query(&format!(
+        "SELECT {MENU_COLUMNS} FROM role_menu WHERE guild_id = ? AND id = ?"
+    ))
+    .bind(i64::from(*guild_id))
+    .bind(menu_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(mut menu) = row.as_ref().map(menu_from_row) else {
+        return Ok(None);
+    };
+
+    menu.options = select_menu_options(menu.id, pool).await?;
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "role_menu", elapsed_time);
+    info!("Selected from Role Menus in {elapsed_time:.2?}");
+
+    Ok(Some(menu))
+}
+
+/// Selects the role menu posted as a message, along with its options.
+pub async fn select_role_menu_by_message(
+    message_id: &MessageId,
+    pool: &SqlitePool,
+) -> Result<Option<RoleMenu>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let row = sqlx::query(&format!(
+        "SELECT {MENU_COLUMNS} FROM role_menu WHERE message_id = ?"
+    ))
+    .bind(i64::from(*message_id))
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(mut menu) = row.as_ref().map(menu_from_row) else {
+        return Ok(None);
+    };
+
+    menu.options = select_menu_options(menu.id, pool).await?;
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "role_menu", elapsed_time);
+    info!("Selected from Role Menus in {elapsed_time:.2?}");
+
+    Ok(Some(menu))
+}
+
+/// Selects every role menu of a guild, without their options.
+pub async fn select_role_menus(
+    guild_id: &GuildId,
+    pool: &SqlitePool,
+) -> Result<Vec<RoleMenu>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let rows = sqlx::query(&format!(
+        "SELECT {MENU_COLUMNS} FROM role_menu WHERE guild_id = ? ORDER BY id"
+    ))
+    .bind(i64::from(*guild_id))
+    .fetch_all(pool)
+    .await?;
+
+    let menus = rows.iter().map(menu_from_row).collect();
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "role_menu", elapsed_time);
+    info!("Selected from Role Menus in {elapsed_time:.2?}");
+
+    Ok(menus)
+}
+
+/// Inserts a role menu, returning its ID.
+pub async fn insert_role_menu(
+    guild_id: &GuildId,
+    kind: MenuKind,
+    max_roles: u32,
+    title: &str,
+    description: Option<&str>,
+    pool: &SqlitePool,
+) -> Result<i64, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO role_menu (guild_id, kind, max_roles, title, description) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(i64::from(*guild_id))
+    .bind(kind.as_str())
+    .bind(max_roles as i64)
+    .bind(title)
+    .bind(description);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "role_menu", elapsed_time);
+    info!("Inserted into Role Menus in {elapsed_time:.2?}");
+
+    Ok(result.last_insert_rowid())
+}
+
+/// Records where a role menu is published.
+pub async fn update_role_menu_message(
+    menu_id: i64,
+    channel_id: &ChannelId,
+    message_id: &MessageId,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("UPDATE role_menu SET channel_id = ?, message_id = ? WHERE id = ?")
+        .bind(i64::from(*channel_id))
+        .bind(i64::from(*message_id))
+        .bind(menu_id);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("update", "role_menu", elapsed_time);
+    info!("Updated Role Menus in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Removes a guild's role menu and its options, returning whether it existed.
+pub async fn delete_role_menu(
+    guild_id: &GuildId,
+    menu_id: i64,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM role_menu WHERE guild_id = ? AND id = ?")
+        .bind(i64::from(*guild_id))
+        .bind(menu_id);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "role_menu", elapsed_time);
+    info!("Deleted from Role Menus in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Adds a role to a menu, or updates its emoji and label if it is already in it.
+pub async fn upsert_menu_option(
+    menu_id: i64,
+    option: &MenuOption,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO role_menu_option (menu_id, role_id, emoji, label, position)
+        VALUES (?, ?, ?, ?, (SELECT COUNT(*) FROM role_menu_option WHERE menu_id = ?))
+        ON CONFLICT (menu_id, role_id) DO UPDATE SET emoji = excluded.emoji, label = excluded.label"
+    )
+        .bind(menu_id)
+        .bind(i64::from(option.role_id))
+        .bind(option.emoji.as_ref().map(ToString::to_string))
+        .bind(&option.label)
+        .bind(menu_id);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("upsert", "role_menu_option", elapsed_time);
+    info!("Upserted into Role Menu Options in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Removes a role from a menu, returning whether it was in it.
+pub async fn delete_menu_option(
+    menu_id: i64,
+    role_id: &RoleId,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM role_menu_option WHERE menu_id = ? AND role_id = ?")
+        .bind(menu_id)
+        .bind(i64::from(*role_id));
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "role_menu_option", elapsed_time);
+    info!("Deleted from Role Menu Options in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod role_menu_tests {
+    use super::*;
+
+    #[test]
+    fn toggle_test() {
+        let (a, b, c) = (RoleId::new(1), RoleId::new(2), RoleId::new(3));
+
+        assert_eq!(toggle(&[a], a, 0), RoleChange::Remove(a));
+        assert_eq!(
+            toggle(&[a, b], c, 0),
+            RoleChange::Add {
+                role_id: c,
+                replaced: Vec::new()
+            }
+        );
+        assert_eq!(
+            toggle(&[a], b, 1),
+            RoleChange::Add {
+                role_id: b,
+                replaced: vec![a]
+            }
+        );
+        assert_eq!(toggle(&[a, b], c, 2), RoleChange::Full);
+        assert_eq!(
+            toggle(&[a], c, 2),
+            RoleChange::Add {
+                role_id: c,
+                replaced: Vec::new()
+            }
+        );
+    }
+
+    #[test]
+    fn custom_id_test() {
+        let role_id = RoleId::new(175928847299117063);
+
+        assert_eq!(
+            parse_custom_id(&custom_id(4, Some(role_id))),
+            Some((4, Some(role_id)))
+        );
+        assert_eq!(parse_custom_id(&custom_id(4, None)), Some((4, None)));
+        assert_eq!(parse_custom_id("rolemenu:x"), None);
+        assert_eq!(parse_custom_id("rolemenu:4:0"), None);
+        assert_eq!(parse_custom_id("6f1c2b4aprev"), None);
+    }
+
+    #[test]
+    fn emoji_key_test() {
+        let renamed = ReactionType::try_from("<:old:600404340292059257>").unwrap();
+        let current = ReactionType::try_from("<:new:600404340292059257>").unwrap();
+
+        assert_eq!(emoji_key(&renamed), emoji_key(&current));
+        assert_eq!(emoji_key(&ReactionType::from('🍎')), "🍎");
+    }
+}
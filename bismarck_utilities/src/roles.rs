@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use poise::serenity_prelude as serenity;
+use serenity::all::{GuildId, Http, HttpError, Member, Role, RoleId, UserId};
+use sqlx::{Row, SqlitePool};
+use tracing::{error, info, warn};
+
+use bismarck_core::{
+    error::Error,
+    metrics,
+    scheduler::{Job, Scheduler},
+};
+
+use crate::hierarchy::{self, Position};
+
+/// Discord's error code for a member that isn't in the guild.
+const UNKNOWN_MEMBER: isize = 10007;
+
+/// A guild's autoroles and whether it gives members their roles back when they rejoin.
+#[derive(Debug, Clone, Default)]
+pub struct RoleSettings {
+    /// How long after joining autoroles are given, in seconds.
+    pub autorole_delay: u64,
+    pub persist_roles: bool,
+    /// Roles given to members joining.
+    pub member_roles: Vec<RoleId>,
+    /// Roles given to bots joining.
+    pub bot_roles: Vec<RoleId>,
+}
+
+/// Keeps the roles that a bot whose highest role is at `bot_top_role` can give, dropping
+/// `@everyone`, roles managed by an integration and roles that no longer exist.
+pub fn assignable(
+    role_ids: &[RoleId],
+    guild_id: GuildId,
+    guild_roles: &HashMap<RoleId, Role>,
+    bot_top_role: u16,
+) -> Vec<RoleId> {
+    role_ids
+        .iter()
+        .filter(|role_id| role_id.get() != guild_id.get())
+        .filter(|role_id| {
+            guild_roles
+                .get(role_id)
+                .is_some_and(|role| !role.managed && role.position < bot_top_role)
+        })
+        .copied()
+        .collect()
+}
+
+/// Resolves the roles the bot can give in a guild, or none if the guild or the bot's member
+/// can't be found.
+async fn assignable_in(
+    context: &serenity::Context,
+    guild_id: GuildId,
+    role_ids: &[RoleId],
+) -> Option<Vec<RoleId>> {
+    if role_ids.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let bot = match hierarchy::bot_member(context, guild_id).await {
+        Ok(bot) => bot,
+        Err(why) => {
+            error!("Couldn't get bot member to give roles: {why:?}");
+            return None;
+        }
+    };
+
+    let guild = context.cache.guild(guild_id)?;
+
+    let bot_top_role = Position::of(&guild, &bot).top_role;
+
+    Some(assignable(role_ids, guild_id, &guild.roles, bot_top_role))
+}
+
+/// Whether the bot can give a role in a guild.
+pub async fn is_assignable(
+    context: &serenity::Context,
+    guild_id: GuildId,
+    role_id: RoleId,
+) -> bool {
+    assignable_in(context, guild_id, &[role_id])
+        .await
+        .is_some_and(|role_ids| !role_ids.is_empty())
+}
+
+/// Gives a member each role, returning the ones that were given and the last error, if any
+/// role couldn't be.
+async fn add_roles(
+    http: &Http,
+    guild_id: GuildId,
+    user_id: UserId,
+    role_ids: &[RoleId],
+    reason: &str,
+) -> (Vec<RoleId>, Option<serenity::Error>) {
+    let mut given = Vec::with_capacity(role_ids.len());
+    let mut failure = None;
+
+    for role_id in role_ids {
+        match http
+            .add_member_role(guild_id, user_id, *role_id, Some(reason))
+            .await
+        {
+            Ok(()) => given.push(*role_id),
+            Err(why) => {
+                error!("Couldn't give role {role_id} to {user_id} in guild {guild_id}: {why:?}");
+                failure = Some(why);
+            }
+        }
+    }
+
+    (given, failure)
+}
+
+/// Whether Discord refused a request because the member has left the guild.
+fn is_unknown_member(why: &serenity::Error) -> bool {
+    matches!(
+        why,
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response))
+            if response.error.code == UNKNOWN_MEMBER
+    )
+}
+
+/// Gives a member back the roles they had when they last left, if the guild kept them. Saved
+/// roles are only removed once given back, so they aren't lost if the guild isn't cached or
+/// Discord refuses some of them.
+pub async fn restore_roles(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    member: &Member,
+) -> Result<(), Error> {
+    let (guild_id, user_id) = (member.guild_id, member.user.id);
+
+    let persisted = select_persisted_roles(&guild_id, &user_id, pool).await?;
+
+    if persisted.is_empty() {
+        return Ok(());
+    }
+
+    let Some(role_ids) = assignable_in(context, guild_id, &persisted).await else {
+        warn!("Keeping saved roles of {user_id} in {guild_id} until they can be given back");
+        return Ok(());
+    };
+
+    let (given, _) = add_roles(
+        &context.http,
+        guild_id,
+        user_id,
+        &role_ids,
+        "Restoring roles from before leaving",
+    )
+    .await;
+
+    if !given.is_empty() {
+        delete_persisted_roles(&guild_id, &user_id, &given, pool).await?;
+    }
+
+    Ok(())
+}
+
+/// Gives a member joining the guild's autoroles, after its delay if it has one.
+pub async fn give_autoroles(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    scheduler: &Scheduler,
+    member: &Member,
+) -> Result<(), Error> {
+    let guild_id = member.guild_id;
+    let user_id = member.user.id;
+
+    let settings = select_role_settings(&guild_id, pool).await?;
+
+    let role_ids = if member.user.bot {
+        &settings.bot_roles
+    } else {
+        &settings.member_roles
+    };
+
+    let role_ids = assignable_in(context, guild_id, role_ids)
+        .await
+        .unwrap_or_default();
+
+    if role_ids.is_empty() {
+        return Ok(());
+    }
+
+    if settings.autorole_delay == 0 {
+        add_roles(&context.http, guild_id, user_id, &role_ids, "Autorole").await;
+        return Ok(());
+    }
+
+    let job = Job::GiveAutoroles {
+        guild_id: guild_id.get(),
+        user_id: user_id.get(),
+        role_ids: role_ids.iter().map(|role_id| role_id.get()).collect(),
+    };
+    let run_at = Utc::now() + Duration::from_secs(settings.autorole_delay);
+
+    scheduler
+        .schedule(&format!("autorole:{guild_id}:{user_id}"), &job, run_at)
+        .await?;
+
+    Ok(())
+}
+
+/// Gives a member the autoroles held back by the guild's delay. Fails if any of them couldn't
+/// be given, so that the job is retried, unless the member has left since.
+pub async fn give_delayed_autoroles(
+    http: &Http,
+    guild_id: GuildId,
+    user_id: UserId,
+    role_ids: &[RoleId],
+) -> Result<(), Error> {
+    match add_roles(http, guild_id, user_id, role_ids, "Autorole").await {
+        (_, Some(why)) if is_unknown_member(&why) => {
+            info!("Not giving autoroles to {user_id}, who left guild {guild_id}");
+            Ok(())
+        }
+        (_, Some(why)) => Err(why.into()),
+        (_, None) => Ok(()),
+    }
+}
+
+/// Saves the roles of a member leaving, if the guild gives them back on rejoin.
+pub async fn save_roles(
+    pool: &SqlitePool,
+    guild_id: GuildId,
+    user_id: UserId,
+    member: Option<&Member>,
+) -> Result<(), Error> {
+    let settings = select_role_settings(&guild_id, pool).await?;
+
+    if !settings.persist_roles {
+        return Ok(());
+    }
+
+    let Some(member) = member else {
+        warn!("Couldn't save roles of {user_id} in {guild_id}, as they weren't cached");
+        return Ok(());
+    };
+
+    if !member.roles.is_empty() {
+        replace_persisted_roles(&guild_id, &user_id, &member.roles, pool).await?;
+    }
+
+    Ok(())
+}
+
+/// Selects the role settings of a guild, defaulting if it has none.
+pub async fn select_role_settings(
+    guild_id: &GuildId,
+    pool: &SqlitePool,
+) -> Result<RoleSettings, sqlx::Error> {
+    let start_time = Instant::now();
+    let guild_id = i64::from(*guild_id);
+
+    let mut settings =
+        sqlx::query("SELECT autorole_delay, persist_roles FROM role_config WHERE guild_id = ?")
+            .bind(guild_id)
+            .fetch_optional(pool)
+            .await?
+            .map(|row| RoleSettings {
+                autorole_delay: row.get::<i64, _>(0) as u64,
+                persist_roles: row.get::<i64, _>(1) == 1,
+                ..Default::default()
+            })
+            .unwrap_or_default();
+
+    let rows = sqlx::query("SELECT role_id, bots FROM autorole WHERE guild_id = ?")
+        .bind(guild_id)
+        .fetch_all(pool)
+        .await?;
+
+    for row in rows {
+        let role_id = RoleId::new(row.get::<i64, _>(0) as u64);
+
+        if row.get::<i64, _>(1) == 1 {
+            settings.bot_roles.push(role_id);
+        } else {
+            settings.member_roles.push(role_id);
+        }
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "role_config", elapsed_time);
+    info!("Selected from Role Settings in {elapsed_time:.2?}");
+
+    Ok(settings)
+}
+
+pub async fn upsert_role_config(
+    guild_id: &GuildId,
+    settings: &RoleSettings,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO role_config (guild_id, autorole_delay, persist_roles) VALUES (?, ?, ?)
+        ON CONFLICT (guild_id) DO UPDATE SET autorole_delay = excluded.autorole_delay, persist_roles = excluded.persist_roles"
+    )
+        .bind(i64::from(*guild_id))
+        .bind(settings.autorole_delay as i64)
+        .bind(settings.persist_roles as i64);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("upsert", "role_config", elapsed_time);
+    info!("Upserted into Role Settings in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Adds an autorole, returning whether it wasn't one already.
+pub async fn insert_autorole(
+    guild_id: &GuildId,
+    role_id: &RoleId,
+    bots: bool,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO autorole (guild_id, role_id, bots) VALUES (?, ?, ?) ON CONFLICT DO NOTHING",
+    )
+    .bind(i64::from(*guild_id))
+    .bind(i64::from(*role_id))
+    .bind(bots as i64);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "autorole", elapsed_time);
+    info!("Inserted into Autoroles in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Removes an autorole, returning whether it was one.
+pub async fn delete_autorole(
+    guild_id: &GuildId,
+    role_id: &RoleId,
+    bots: bool,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("DELETE FROM autorole WHERE guild_id = ? AND role_id = ? AND bots = ?")
+        .bind(i64::from(*guild_id))
+        .bind(i64::from(*role_id))
+        .bind(bots as i64);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "autorole", elapsed_time);
+    info!("Deleted from Autoroles in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Replaces the roles saved for a member with the roles they have now.
+pub async fn replace_persisted_roles(
+    guild_id: &GuildId,
+    user_id: &UserId,
+    role_ids: &[RoleId],
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+    let (guild_id, user_id) = (i64::from(*guild_id), i64::from(*user_id));
+
+    let mut transaction = pool.begin().await?;
+
+    sqlx::query("DELETE FROM persisted_role WHERE guild_id = ? AND user_id = ?")
+        .bind(guild_id)
+        .bind(user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    for role_id in role_ids {
+        sqlx::query("INSERT INTO persisted_role (guild_id, user_id, role_id) VALUES (?, ?, ?)")
+            .bind(guild_id)
+            .bind(user_id)
+            .bind(i64::from(*role_id))
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    if let Err(why) = transaction.commit().await {
+        error!("Failed to commit transaction: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("replace", "persisted_role", elapsed_time);
+    info!("Replaced Persisted Roles in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Selects the roles saved for a member.
+pub async fn select_persisted_roles(
+    guild_id: &GuildId,
+    user_id: &UserId,
+    pool: &SqlitePool,
+) -> Result<Vec<RoleId>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let rows = sqlx::query("SELECT role_id FROM persisted_role WHERE guild_id = ? AND user_id = ?")
+        .bind(i64::from(*guild_id))
+        .bind(i64::from(*user_id))
+        .fetch_all(pool)
+        .await?;
+
+    let role_ids = rows
+        .iter()
+        .map(|row| RoleId::new(row.get::<i64, _>(0) as u64))
+        .collect();
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "persisted_role", elapsed_time);
+    info!("Selected from Persisted Roles in {elapsed_time:.2?}");
+
+    Ok(role_ids)
+}
+
+/// Removes roles saved for a member, once they have been given back.
+pub async fn delete_persisted_roles(
+    guild_id: &GuildId,
+    user_id: &UserId,
+    role_ids: &[RoleId],
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+    let (guild_id, user_id) = (i64::from(*guild_id), i64::from(*user_id));
+
+    let mut transaction = pool.begin().await?;
+
+    for role_id in role_ids {
+        sqlx::query(
+            "DELETE FROM persisted_role WHERE guild_id = ? AND user_id = ? AND role_id = ?",
+        )
+        .bind(guild_id)
+        .bind(user_id)
+        .bind(i64::from(*role_id))
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    if let Err(why) = transaction.commit().await {
+        error!("Failed to commit transaction: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "persisted_role", elapsed_time);
+    info!("Deleted from Persisted Roles in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Removes the roles saved for every member of a guild.
+pub async fn clear_persisted_roles(
+    guild_id: &GuildId,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query =
+        sqlx::query("DELETE FROM persisted_role WHERE guild_id = ?").bind(i64::from(*guild_id));
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "persisted_role", elapsed_time);
+    info!("Cleared Persisted Roles in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod roles_tests {
+    use super::*;
+
+    fn role(id: u64, position: u16, managed: bool) -> (RoleId, Role) {
+        let mut role = Role::default();
+        role.id = RoleId::new(id);
+        role.position = position;
+        role.managed = managed;
+
+        (role.id, role)
+    }
+
+    #[test]
+    fn assignable_test() {
+        let guild_id = GuildId::new(1);
+        let guild_roles = HashMap::from([
+            role(1, 0, false),
+            role(2, 3, false),
+            role(3, 5, false),
+            role(4, 2, true),
+            role(5, 8, false),
+        ]);
+
+        let role_ids = [1, 2, 3, 4, 5, 6].map(RoleId::new);
+
+        assert_eq!(
+            assignable(&role_ids, guild_id, &guild_roles, 5),
+            vec![RoleId::new(2)]
+        );
+        assert_eq!(
+            assignable(&role_ids, guild_id, &guild_roles, 10),
+            vec![RoleId::new(2), RoleId::new(3), RoleId::new(5)]
+        );
+    }
+
+    #[tokio::test]
+    async fn persisted_roles_test() {
+        let pool = bismarck_core::testing::pool().await;
+        let (guild_id, user_id) = (GuildId::new(1), UserId::new(10));
+
+        sqlx::query("INSERT INTO user (id) VALUES (10)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO guild (id, owner, commands_ran, songs_played) VALUES (1, 10, 0, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let role_ids = [RoleId::new(2), RoleId::new(3)];
+        replace_persisted_roles(&guild_id, &user_id, &role_ids, &pool)
+            .await
+            .unwrap();
+
+        // Selecting keeps them, in case they can't be given back yet.
+        for _ in 0..2 {
+            let mut persisted = select_persisted_roles(&guild_id, &user_id, &pool)
+                .await
+                .unwrap();
+            persisted.sort_unstable();
+            assert_eq!(persisted, role_ids);
+        }
+
+        // Roles that couldn't be given back stay saved.
+        delete_persisted_roles(&guild_id, &user_id, &role_ids[..1], &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            select_persisted_roles(&guild_id, &user_id, &pool)
+                .await
+                .unwrap(),
+            [RoleId::new(3)]
+        );
+
+        delete_persisted_roles(&guild_id, &user_id, &role_ids[1..], &pool)
+            .await
+            .unwrap();
+        assert!(select_persisted_roles(&guild_id, &user_id, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
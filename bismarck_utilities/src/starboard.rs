@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use poise::serenity_prelude as serenity;
+use serenity::all::{
+    ChannelId, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateMessage, EditMessage,
+    Guild, GuildId, Message, MessageId, Reaction, ReactionType,
+};
+use sqlx::{Row, SqlitePool};
+use tracing::{debug, error, info};
+
+use bismarck_core::{data::Data, error::Error, metrics, types::StarboardSettings};
+
+use crate::role_menu::emoji_key;
+
+/// Most reacting users fetched when counting stars, 100 being the most Discord returns at once.
+const MAX_COUNTED_STARS: usize = 1000;
+
+/// What a change in stars does to a message's starboard post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StarAction {
+    Post,
+    Update,
+    Remove,
+    Nothing,
+}
+
+/// Works out what happens to a message's starboard post now that it has `stars`.
+pub fn star_action(stars: u32, threshold: u32, is_posted: bool) -> StarAction {
+    match (stars >= threshold, is_posted) {
+        (true, false) => StarAction::Post,
+        (true, true) => StarAction::Update,
+        (false, true) => StarAction::Remove,
+        (false, false) => StarAction::Nothing,
+    }
+}
+
+/// Whether a channel, or the channel a thread is in, is age restricted.
+fn is_nsfw(guild: &Guild, channel_id: ChannelId) -> bool {
+    if let Some(channel) = guild.channels.get(&channel_id) {
+        return channel.nsfw;
+    }
+
+    guild
+        .threads
+        .iter()
+        .find(|thread| thread.id == channel_id)
+        .and_then(|thread| thread.parent_id)
+        .and_then(|parent_id| guild.channels.get(&parent_id))
+        .is_some_and(|channel| channel.nsfw)
+}
+
+/// Counts the users who starred a message, leaving out its author and bots.
+async fn count_stars(
+    context: &serenity::Context,
+    message: &Message,
+    emoji: &ReactionType,
+) -> Result<u32, serenity::Error> {
+    let key = emoji_key(emoji);
+
+    if !message
+        .reactions
+        .iter()
+        .any(|reaction| emoji_key(&reaction.reaction_type) == key)
+    {
+        return Ok(0);
+    }
+
+    let mut stars = 0;
+    let mut counted = 0;
+    let mut after = None;
+
+    while counted < MAX_COUNTED_STARS {
+        let users = message
+            .channel_id
+            .reaction_users(context, message.id, emoji.clone(), Some(100), after)
+            .await?;
+
+        counted += users.len();
+        stars += users
+            .iter()
+            .filter(|user| !user.bot && user.id != message.author.id)
+            .count() as u32;
+
+        match users.last() {
+            Some(last) if users.len() == 100 => after = Some(last.id),
+            _ => break,
+        }
+    }
+
+    Ok(stars)
+}
+
+fn starboard_embed(message: &Message) -> CreateEmbed {
+    let author = CreateEmbedAuthor::new(&message.author.name).icon_url(message.author.face());
+
+    let mut embed = CreateEmbed::new()
+        .color(0x00ff_c83d)
+        .author(author)
+        .description(&message.content)
+        .field(
+            "Source",
+            format!("[Jump to message]({})", message.link()),
+            false,
+        )
+        .footer(CreateEmbedFooter::new(message.id.to_string()))
+        .timestamp(message.timestamp);
+
+    let image = message.attachments.iter().find(|attachment| {
+        attachment
+            .content_type
+            .as_deref()
+            .is_some_and(|content_type| content_type.starts_with("image/"))
+    });
+
+    if let Some(image) = image {
+        embed = embed.image(&image.url);
+    }
+
+    embed
+}
+
+fn star_line(emoji: &str, stars: u32, channel_id: ChannelId) -> String {
+    format!("{emoji} **{stars}** <#{channel_id}>")
+}
+
+/// Updates the starboard after a reaction on a message changes, posting, updating or removing
+/// its starboard post if the reaction is a star.
+pub async fn update_stars(
+    context: &serenity::Context,
+    data: &Data,
+    reaction: &Reaction,
+) -> Result<(), Error> {
+    let Some(guild_id) = reaction.guild_id else {
+        return Ok(());
+    };
+
+    let (channel_id, message_id) = (reaction.channel_id, reaction.message_id);
+
+    let Some(settings) = data
+        .starboard
+        .get(&guild_id.get())
+        .map(|settings| settings.clone())
+    else {
+        return Ok(());
+    };
+
+    let Some(starboard_id) = settings.channel_id.map(ChannelId::new) else {
+        return Ok(());
+    };
+
+    let Ok(emoji) = ReactionType::try_from(settings.emoji.as_str()) else {
+        return Ok(());
+    };
+
+    if channel_id == starboard_id || emoji_key(&reaction.emoji) != emoji_key(&emoji) {
+        return Ok(());
+    }
+
+    // Age restricted messages are only reposted when the starboard is age restricted too.
+    let allowed = context
+        .cache
+        .guild(guild_id)
+        .is_some_and(|guild| !is_nsfw(&guild, channel_id) || is_nsfw(&guild, starboard_id));
+
+    if !allowed {
+        debug!("Skipped starring age restricted message {message_id}");
+        return Ok(());
+    }
+
+    let message = channel_id.message(context, message_id).await?;
+    let stars = count_stars(context, &message, &emoji).await?;
+
+    let posted = select_starboard_message(&message_id, &data.sqlite).await?;
+
+    match (
+        star_action(stars, settings.threshold, posted.is_some()),
+        posted,
+    ) {
+        (StarAction::Post, _) => {
+            let create = CreateMessage::new()
+                .content(star_line(&settings.emoji, stars, channel_id))
+                .embed(starboard_embed(&message));
+            let post = starboard_id.send_message(context, create).await?;
+
+            let inserted = insert_starboard_message(
+                &guild_id,
+                &channel_id,
+                &message_id,
+                &post.id,
+                stars,
+                &data.sqlite,
+            )
+            .await?;
+
+            // Another star got the message posted first.
+            if !inserted {
+                post.delete(context).await?;
+            }
+        }
+        (StarAction::Update, Some(post_id)) => {
+            let edit = EditMessage::new().content(star_line(&settings.emoji, stars, channel_id));
+
+            if let Err(why) = starboard_id.edit_message(context, post_id, edit).await {
+                error!("Couldn't update starboard post {post_id}: {why:?}");
+            }
+
+            update_starboard_stars(&message_id, stars, &data.sqlite).await?;
+        }
+        (StarAction::Remove, _) => {
+            remove_post(context, data, message_id).await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Removes the starboard post of a message, if it has one.
+pub async fn remove_post(
+    context: &serenity::Context,
+    data: &Data,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    let Some((guild_id, post_id)) = delete_starboard_message(&message_id, &data.sqlite).await?
+    else {
+        return Ok(());
+    };
+
+    let starboard_id = data
+        .starboard
+        .get(&guild_id.get())
+        .and_then(|settings| settings.channel_id)
+        .map(ChannelId::new);
+
+    if let Some(starboard_id) = starboard_id {
+        if let Err(why) = starboard_id.delete_message(context, post_id).await {
+            error!("Couldn't delete starboard post {post_id}: {why:?}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Selects the starboard settings of every guild.
+pub async fn select_starboard_settings(
+    pool: &SqlitePool,
+) -> Result<HashMap<u64, StarboardSettings>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let rows = sqlx::query("SELECT guild_id, channel_id, emoji, threshold FROM starboard_config")
+        .fetch_all(pool)
+        .await?;
+
+    let settings = rows
+        .iter()
+        .map(|row| {
+            let settings = StarboardSettings {
+                channel_id: row.get::<Option<i64>, _>(1).map(|id| id as u64),
+                emoji: row.get::<String, _>(2),
+                threshold: row.get::<i64, _>(3) as u32,
+            };
+
+            (row.get::<i64, _>(0) as u64, settings)
+        })
+        .collect();
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "starboard_config", elapsed_time);
+    info!("Selected from Starboard Settings in {elapsed_time:.2?}");
+
+    Ok(settings)
+}
+
+pub async fn upsert_starboard_settings(
+    guild_id: &GuildId,
+    settings: &StarboardSettings,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO starboard_config (guild_id, channel_id, emoji, threshold) VALUES (?, ?, ?, ?)
+        ON CONFLICT (guild_id) DO UPDATE SET channel_id = excluded.channel_id, emoji = excluded.emoji, threshold = excluded.threshold"
+    )
+        .bind(i64::from(*guild_id))
+        .bind(settings.channel_id.map(|id| id as i64))
+        .bind(&settings.emoji)
+        .bind(settings.threshold as i64);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("upsert", "starboard_config", elapsed_time);
+    info!("Upserted into Starboard Settings in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Selects the ID of a message's starboard post, if it has one.
+pub async fn select_starboard_message(
+    message_id: &MessageId,
+    pool: &SqlitePool,
+) -> Result<Option<MessageId>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let row =
+        sqlx::query("SELECT starboard_message_id FROM starboard_message WHERE message_id = ?")
+            .bind(i64::from(*message_id))
+            .fetch_optional(pool)
+            .await?;
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "starboard_message", elapsed_time);
+    info!("Selected from Starboard Messages in {elapsed_time:.2?}");
+
+    Ok(row.map(|row| MessageId::new(row.get::<i64, _>(0) as u64)))
+}
+
+/// Records a message's starboard post, returning whether it didn't have one already.
+pub async fn insert_starboard_message(
+    guild_id: &GuildId,
+    channel_id: &ChannelId,
+    message_id: &MessageId,
+    starboard_message_id: &MessageId,
+    stars: u32,
+    pool: &SqlitePool,
+) -> Result<bool, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO starboard_message (message_id, guild_id, channel_id, starboard_message_id, stars) VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT DO NOTHING"
+    )
+        .bind(i64::from(*message_id))
+        .bind(i64::from(*guild_id))
+        .bind(i64::from(*channel_id))
+        .bind(i64::from(*starboard_message_id))
+        .bind(stars as i64);
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "starboard_message", elapsed_time);
+    info!("Inserted into Starboard Messages in {elapsed_time:.2?}");
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn update_starboard_stars(
+    message_id: &MessageId,
+    stars: u32,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("UPDATE starboard_message SET stars = ? WHERE message_id = ?")
+        .bind(stars as i64)
+        .bind(i64::from(*message_id));
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("update", "starboard_message", elapsed_time);
+    info!("Updated Starboard Messages in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Removes a message's starboard post record, returning the guild and the post's ID if it had
+/// one.
+pub async fn delete_starboard_message(
+    message_id: &MessageId,
+    pool: &SqlitePool,
+) -> Result<Option<(GuildId, MessageId)>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let row = sqlx::query(
+        "DELETE FROM starboard_message WHERE message_id = ? RETURNING guild_id, starboard_message_id",
+    )
+    .bind(i64::from(*message_id))
+    .fetch_optional(pool)
+    .await?;
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "starboard_message", elapsed_time);
+    info!("Deleted from Starboard Messages in {elapsed_time:.2?}");
+
+    Ok(row.map(|row| {
+        (
+            GuildId::new(row.get::<i64, _>(0) as u64),
+            MessageId::new(row.get::<i64, _>(1) as u64),
+        )
+    }))
+}
+
+/// Removes the records of every starboard post of a guild, such as when its starboard moves.
+pub async fn clear_starboard_messages(
+    guild_id: &GuildId,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query =
+        sqlx::query("DELETE FROM starboard_message WHERE guild_id = ?").bind(i64::from(*guild_id));
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("delete", "starboard_message", elapsed_time);
+    info!("Cleared Starboard Messages in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod starboard_tests {
+    use super::*;
+
+    #[test]
+    fn star_action_test() {
+        assert_eq!(star_action(3, 3, false), StarAction::Post);
+        assert_eq!(star_action(4, 3, true), StarAction::Update);
+        assert_eq!(star_action(2, 3, true), StarAction::Remove);
+        assert_eq!(star_action(2, 3, false), StarAction::Nothing);
+    }
+
+    #[test]
+    fn star_line_test() {
+        assert_eq!(star_line("⭐", 5, ChannelId::new(42)), "⭐ **5** <#42>");
+    }
+}
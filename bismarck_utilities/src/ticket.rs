@@ -0,0 +1,688 @@
+use std::time::Instant;
+
+use chrono::Utc;
+use poise::serenity_prelude as serenity;
+use serenity::all::{
+    AutoArchiveDuration, ButtonStyle, ChannelId, ChannelType, ComponentInteraction,
+    CreateActionRow, CreateAllowedMentions, CreateAttachment, CreateButton, CreateEmbed,
+    CreateMessage, CreateThread, EditInteractionResponse, EditThread, GetMessages, GuildId, Member,
+    Mentionable, Message, RoleId, UserId,
+};
+use sqlx::{Row, SqlitePool};
+use tracing::{error, info};
+
+use bismarck_core::{error::Error, metrics};
+
+use crate::messages;
+
+/// Custom IDs of ticket buttons start with this, followed by the action and, for every action
+/// but opening, the ticket ID, all separated by colons.
+pub const CUSTOM_ID_PREFIX: &str = "ticket";
+
+/// Most messages saved to a transcript, so a runaway ticket can't stall closing it.
+const MAX_TRANSCRIPT_MESSAGES: usize = 5000;
+
+#[derive(Debug, Clone, Default)]
+pub struct TicketConfig {
+    /// Role pinged on new tickets and allowed to claim, close and reopen them.
+    pub staff_role_id: Option<RoleId>,
+    /// Channel transcripts are saved to when a ticket closes.
+    pub log_channel_id: Option<ChannelId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ticket {
+    pub id: i64,
+    pub guild_id: GuildId,
+    pub thread_id: ChannelId,
+    pub user_id: UserId,
+    pub claimed_by: Option<UserId>,
+    pub open: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TicketAction {
+    Open,
+    Claim(i64),
+    Close(i64),
+    Reopen(i64),
+}
+
+fn custom_id(action: TicketAction) -> String {
+    match action {
+        TicketAction::Open => format!("{CUSTOM_ID_PREFIX}:open"),
+        TicketAction::Claim(id) => format!("{CUSTOM_ID_PREFIX}:claim:{id}"),
+        TicketAction::Close(id) => format!("{CUSTOM_ID_PREFIX}:close:{id}"),
+        TicketAction::Reopen(id) => format!("{CUSTOM_ID_PREFIX}:reopen:{id}"),
+    }
+}
+
+fn parse_custom_id(custom_id: &str) -> Option<TicketAction> {
+    let mut parts = custom_id.split(':');
+
+    if parts.next()? != CUSTOM_ID_PREFIX {
+        return None;
+    }
+
+    let action = parts.next()?;
+    if action == "open" {
+        return Some(TicketAction::Open);
+    }
+
+    let id = parts.next()?.parse().ok()?;
+
+    match action {
+        "claim" => Some(TicketAction::Claim(id)),
+        "close" => Some(TicketAction::Close(id)),
+        "reopen" => Some(TicketAction::Reopen(id)),
+        _ => None,
+    }
+}
+
+/// The message members open tickets from.
+pub fn panel(title: &str, description: &str) -> CreateMessage {
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title(title)
+        .description(description);
+
+    let button = CreateButton::new(custom_id(TicketAction::Open))
+        .label("Open a ticket")
+        .emoji('🎫')
+        .style(ButtonStyle::Primary);
+
+    CreateMessage::new()
+        .embed(embed)
+        .components(vec![CreateActionRow::Buttons(vec![button])])
+}
+
+fn controls(ticket_id: i64) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(custom_id(TicketAction::Claim(ticket_id)))
+            .label("Claim")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(custom_id(TicketAction::Close(ticket_id)))
+            .label("Close")
+            .style(ButtonStyle::Danger),
+    ])
+}
+
+/// Whether a member handles tickets, either through the staff role or by managing the server.
+pub fn is_staff(member: &Member, config: &TicketConfig) -> bool {
+    let has_role = config
+        .staff_role_id
+        .is_some_and(|role_id| member.roles.contains(&role_id));
+
+    has_role
+        || member
+            .permissions
+            .is_some_and(|permissions| permissions.manage_guild())
+}
+
+/// A message as a line of a transcript.
+fn transcript_line(message: &Message) -> String {
+    let mut line = format!(
+        "[{}] {}: {}",
+        message.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+        message.author.name,
+        message.content
+    );
+
+    for attachment in &message.attachments {
+        line.push_str(&format!(" [{}]", attachment.url));
+    }
+
+    if !message.embeds.is_empty() {
+        line.push_str(&format!(" [{} embed(s)]", message.embeds.len()));
+    }
+
+    line
+}
+
+/// Reads a ticket's messages, oldest first, into a plain text transcript.
+async fn transcript(
+    context: &serenity::Context,
+    thread_id: ChannelId,
+) -> Result<String, serenity::Error> {
+    let mut messages = Vec::new();
+    let mut before = None;
+
+    while messages.len() < MAX_TRANSCRIPT_MESSAGES {
+        let mut request = GetMessages::new().limit(100);
+        if let Some(before) = before {
+            request = request.before(before);
+        }
+
+        let page = thread_id.messages(context, request).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+
+        before = Some(last.id);
+        let done = page.len() < 100;
+        messages.extend(page);
+
+        if done {
+            break;
+        }
+    }
+
+    Ok(messages
+        .iter()
+        .rev()
+        .map(transcript_line)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Handles a click on one of the ticket buttons.
+pub async fn handle_component(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let Some(action) = parse_custom_id(&interaction.data.custom_id) else {
+        return Ok(());
+    };
+
+    let (Some(guild_id), Some(member)) = (interaction.guild_id, &interaction.member) else {
+        return Ok(());
+    };
+
+    let config = select_ticket_config(&guild_id, pool)
+        .await?
+        .unwrap_or_default();
+
+    let ticket_id = match action {
+        TicketAction::Open => return open(context, pool, interaction, member, &config).await,
+        TicketAction::Claim(id) | TicketAction::Close(id) | TicketAction::Reopen(id) => id,
+    };
+
+    let Some(ticket) = select_ticket(&guild_id, ticket_id, pool).await? else {
+        let response = messages::error_response("This ticket no longer exists.", true).await;
+        interaction.create_response(context, response).await?;
+        return Ok(());
+    };
+
+    let staff = is_staff(member, &config);
+    let allowed = match action {
+        TicketAction::Close(_) => staff || member.user.id == ticket.user_id,
+        _ => staff,
+    };
+
+    if !allowed {
+        let response =
+            messages::error_response("Only staff can do that to this ticket.", true).await;
+        interaction.create_response(context, response).await?;
+        return Ok(());
+    }
+
+    match action {
+        TicketAction::Claim(_) => claim(context, pool, interaction, &ticket).await,
+        TicketAction::Close(_) => close(context, pool, interaction, &ticket, &config).await,
+        TicketAction::Reopen(_) => reopen(context, pool, interaction, &ticket).await,
+        TicketAction::Open => Ok(()),
+    }
+}
+
+async fn open(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    interaction: &ComponentInteraction,
+    member: &Member,
+    config: &TicketConfig,
+) -> Result<(), Error> {
+    let Some(staff_role_id) = config.staff_role_id else {
+        let response =
+            messages::error_response("Tickets aren't set up in this server.", true).await;
+        interaction.create_response(context, response).await?;
+        return Ok(());
+    };
+
+    let user_id = member.user.id;
+
+    if let Some(ticket) = select_open_ticket(&member.guild_id, &user_id, pool).await? {
+        let response = messages::error_response(
+            format!(
+                "You already have an open ticket: {}",
+                ticket.thread_id.mention()
+            ),
+            true,
+        )
+        .await;
+        interaction.create_response(context, response).await?;
+        return Ok(());
+    }
+
+    interaction.defer_ephemeral(context).await?;
+
+    let name = format!("ticket-{}", member.user.name)
+        .chars()
+        .take(100)
+        .collect::<String>();
+    let thread = interaction
+        .channel_id
+        .create_thread(
+            context,
+            CreateThread::new(name)
+                .kind(ChannelType::PrivateThread)
+                .invitable(false)
+                .auto_archive_duration(AutoArchiveDuration::OneWeek),
+        )
+        .await?;
+
+    let ticket_id = insert_ticket(&member.guild_id, &thread.id, &user_id, pool).await?;
+    thread.id.add_thread_member(context, user_id).await?;
+
+    let embed = CreateEmbed::new()
+        .color(0x008b_0000)
+        .title(format!("Ticket #{ticket_id}"))
+        .description("Describe what you need help with and staff will be with you shortly.");
+
+    // Mentioning the staff role adds its members to the private thread.
+    let message = CreateMessage::new()
+        .content(format!("{} {}", user_id.mention(), staff_role_id.mention()))
+        .embed(embed)
+        .components(vec![controls(ticket_id)])
+        .allowed_mentions(
+            CreateAllowedMentions::new()
+                .users([user_id])
+                .roles([staff_role_id]),
+        );
+    thread.id.send_message(context, message).await?;
+
+    info!(
+        "Opened ticket {ticket_id} for {user_id} in guild {}",
+        member.guild_id
+    );
+
+    let embed =
+        crate::embeds::info_message_embed(&format!("Your ticket is open: {}", thread.id.mention()));
+    interaction
+        .edit_response(context, EditInteractionResponse::new().embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+async fn claim(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    interaction: &ComponentInteraction,
+    ticket: &Ticket,
+) -> Result<(), Error> {
+    if let Some(claimed_by) = ticket.claimed_by {
+        let response = messages::error_response(
+            format!(
+                "This ticket is already claimed by {}.",
+                claimed_by.mention()
+            ),
+            true,
+        )
+        .await;
+        interaction.create_response(context, response).await?;
+        return Ok(());
+    }
+
+    let user_id = interaction.user.id;
+    update_ticket_claim(ticket.id, &user_id, pool).await?;
+
+    let response =
+        messages::info_response(format!("{} claimed this ticket.", user_id.mention()), false).await;
+    interaction.create_response(context, response).await?;
+
+    Ok(())
+}
+
+async fn close(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    interaction: &ComponentInteraction,
+    ticket: &Ticket,
+    config: &TicketConfig,
+) -> Result<(), Error> {
+    if !ticket.open {
+        let response = messages::error_response("This ticket is already closed.", true).await;
+        interaction.create_response(context, response).await?;
+        return Ok(());
+    }
+
+    // Reading the transcript can take longer than an interaction may go unanswered.
+    interaction.defer(context).await?;
+
+    update_ticket_status(ticket.id, false, pool).await?;
+
+    if let Some(log_channel_id) = config.log_channel_id {
+        let transcript = transcript(context, ticket.thread_id).await?;
+        let attachment = CreateAttachment::bytes(transcript, format!("ticket-{}.txt", ticket.id));
+
+        let embed = CreateEmbed::new()
+            .color(0x008b_0000)
+            .title(format!("Ticket #{} closed", ticket.id))
+            .field("Opened by", ticket.user_id.mention().to_string(), true)
+            .field(
+                "Claimed by",
+                ticket.claimed_by.map_or("Nobody".to_string(), |user_id| {
+                    user_id.mention().to_string()
+                }),
+                true,
+            )
+            .field("Closed by", interaction.user.id.mention().to_string(), true)
+            .field("Thread", ticket.thread_id.mention().to_string(), false);
+
+        let message = CreateMessage::new().embed(embed).add_file(attachment);
+        if let Err(why) = log_channel_id.send_message(context, message).await {
+            error!(
+                "Couldn't save the transcript of ticket {}: {why:?}",
+                ticket.id
+            );
+        }
+    }
+
+    let reopen = CreateButton::new(custom_id(TicketAction::Reopen(ticket.id)))
+        .label("Reopen")
+        .style(ButtonStyle::Secondary);
+    let embed = crate::embeds::info_message_embed(&format!(
+        "{} closed this ticket.",
+        interaction.user.id.mention()
+    ));
+    let message = CreateMessage::new()
+        .embed(embed)
+        .components(vec![CreateActionRow::Buttons(vec![reopen])]);
+    ticket.thread_id.send_message(context, message).await?;
+
+    ticket
+        .thread_id
+        .edit_thread(context, EditThread::new().archived(true).locked(true))
+        .await?;
+
+    info!("Closed ticket {} in guild {}", ticket.id, ticket.guild_id);
+
+    Ok(())
+}
+
+async fn reopen(
+    context: &serenity::Context,
+    pool: &SqlitePool,
+    interaction: &ComponentInteraction,
+    ticket: &Ticket,
+) -> Result<(), Error> {
+    if ticket.open {
+        let response = messages::error_response("This ticket is already open.", true).await;
+        interaction.create_response(context, response).await?;
+        return Ok(());
+    }
+
+    ticket
+        .thread_id
+        .edit_thread(context, EditThread::new().archived(false).locked(false))
+        .await?;
+
+    update_ticket_status(ticket.id, true, pool).await?;
+
+    let response = messages::info_response(
+        format!("{} reopened this ticket.", interaction.user.id.mention()),
+        false,
+    )
+    .await;
+    interaction.create_response(context, response).await?;
+
+    info!("Reopened ticket {} in guild {}", ticket.id, ticket.guild_id);
+
+    Ok(())
+}
+
+const TICKET_COLUMNS: &str = "id, guild_id, thread_id, user_id, claimed_by, status";
+
+fn ticket_from_row(row: &sqlx::sqlite::SqliteRow) -> Ticket {
+    Ticket {
+        id: row.get(0),
+        guild_id: GuildId::new(row.get::<i64, _>(1) as u64),
+        thread_id: ChannelId::new(row.get::<i64, _>(2) as u64),
+        user_id: UserId::new(row.get::<i64, _>(3) as u64),
+        claimed_by: row
+            .get::<Option<i64>, _>(4)
+            .map(|user_id| UserId::new(user_id as u64)),
+        open: row.get::<String, _>(5) == "open",
+    }
+}
+
+pub async fn select_ticket_config(
+    guild_id: &GuildId,
+    pool: &SqlitePool,
+) -> Result<Option<TicketConfig>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let row =
+        sqlx::query("SELECT staff_role_id, log_channel_id FROM ticket_config WHERE guild_id = ?")
+            .bind(i64::from(*guild_id))
+            .fetch_optional(pool)
+            .await?;
+
+    let config = row.map(|row| TicketConfig {
+        staff_role_id: row
+            .get::<Option<i64>, _>(0)
+            .map(|role_id| RoleId::new(role_id as u64)),
+        log_channel_id: row
+            .get::<Option<i64>, _>(1)
+            .map(|channel_id| ChannelId::new(channel_id as u64)),
+    });
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "ticket_config", elapsed_time);
+    info!("Selected from Ticket Config in {elapsed_time:.2?}");
+
+    Ok(config)
+}
+
+pub async fn upsert_ticket_config(
+    guild_id: &GuildId,
+    config: &TicketConfig,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO ticket_config (guild_id, staff_role_id, log_channel_id) VALUES (?, ?, ?) ON CONFLICT(guild_id) DO UPDATE SET staff_role_id = excluded.staff_role_id, log_channel_id = excluded.log_channel_id",
+    )
+    .bind(i64::from(*guild_id))
+    .bind(config.staff_role_id.map(i64::from))
+    .bind(config.log_channel_id.map(i64::from));
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("upsert", "ticket_config", elapsed_time);
+    info!("Upserted into Ticket Config in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+pub async fn select_ticket(
+    guild_id: &GuildId,
+    ticket_id: i64,
+    pool: &SqlitePool,
+) -> Result<Option<Ticket>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let row = sqlx::query(&format!(
+        "SELECT {TICKET_COLUMNS} FROM ticket WHERE guild_id = ? AND id = ?"
+    ))
+    .bind(i64::from(*guild_id))
+    .bind(ticket_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "ticket", elapsed_time);
+    info!("Selected from Tickets in {elapsed_time:.2?}");
+
+    Ok(row.as_ref().map(ticket_from_row))
+}
+
+/// Selects the ticket held in a thread.
+pub async fn select_ticket_by_thread(
+    thread_id: &ChannelId,
+    pool: &SqlitePool,
+) -> Result<Option<Ticket>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let row = sqlx::query(&format!(
+        "SELECT {TICKET_COLUMNS} FROM ticket WHERE thread_id = ?"
+    ))
+    .bind(i64::from(*thread_id))
+    .fetch_optional(pool)
+    .await?;
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "ticket", elapsed_time);
+    info!("Selected from Tickets in {elapsed_time:.2?}");
+
+    Ok(row.as_ref().map(ticket_from_row))
+}
+
+/// Selects the ticket a member has open, if any.
+pub async fn select_open_ticket(
+    guild_id: &GuildId,
+    user_id: &UserId,
+    pool: &SqlitePool,
+) -> Result<Option<Ticket>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let row = sqlx::query(&format!(
+        "SELECT {TICKET_COLUMNS} FROM ticket WHERE guild_id = ? AND user_id = ? AND status = 'open'"
+    ))
+    .bind(i64::from(*guild_id))
+    .bind(i64::from(*user_id))
+    .fetch_optional(pool)
+    .await?;
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "ticket", elapsed_time);
+    info!("Selected from Tickets in {elapsed_time:.2?}");
+
+    Ok(row.as_ref().map(ticket_from_row))
+}
+
+/// Inserts an open ticket, returning its ID.
+pub async fn insert_ticket(
+    guild_id: &GuildId,
+    thread_id: &ChannelId,
+    user_id: &UserId,
+    pool: &SqlitePool,
+) -> Result<i64, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query(
+        "INSERT INTO ticket (guild_id, thread_id, user_id, created_at) VALUES (?, ?, ?, ?)",
+    )
+    .bind(i64::from(*guild_id))
+    .bind(i64::from(*thread_id))
+    .bind(i64::from(*user_id))
+    .bind(Utc::now().timestamp());
+
+    let result = match query.execute(pool).await {
+        Ok(result) => result,
+        Err(why) => {
+            error!("Failed to execute query: {:?}", why);
+            return Err(why);
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("insert", "ticket", elapsed_time);
+    info!("Inserted into Tickets in {elapsed_time:.2?}");
+
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn update_ticket_claim(
+    ticket_id: i64,
+    user_id: &UserId,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let query = sqlx::query("UPDATE ticket SET claimed_by = ? WHERE id = ?")
+        .bind(i64::from(*user_id))
+        .bind(ticket_id);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("update", "ticket", elapsed_time);
+    info!("Updated Tickets in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+/// Opens or closes a ticket, recording when it was closed.
+pub async fn update_ticket_status(
+    ticket_id: i64,
+    open: bool,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    let start_time = Instant::now();
+
+    let (status, closed_at) = if open {
+        ("open", None)
+    } else {
+        ("closed", Some(Utc::now().timestamp()))
+    };
+
+    let query = sqlx::query("UPDATE ticket SET status = ?, closed_at = ? WHERE id = ?")
+        .bind(status)
+        .bind(closed_at)
+        .bind(ticket_id);
+
+    if let Err(why) = query.execute(pool).await {
+        error!("Failed to execute query: {:?}", why);
+        return Err(why);
+    }
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("update", "ticket", elapsed_time);
+    info!("Updated Tickets in {elapsed_time:.2?}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod ticket_tests {
+    use super::*;
+
+    #[test]
+    fn custom_id_test() {
+        for action in [
+            TicketAction::Open,
+            TicketAction::Claim(4),
+            TicketAction::Close(4),
+            TicketAction::Reopen(4),
+        ] {
+            assert_eq!(parse_custom_id(&custom_id(action)), Some(action));
+        }
+
+        assert_eq!(custom_id(TicketAction::Close(12)), "ticket:close:12");
+        assert_eq!(parse_custom_id("ticket:close"), None);
+        assert_eq!(parse_custom_id("rolemenu:1:2"), None);
+    }
+
+    #[test]
+    fn transcript_line_test() {
+        let mut message = Message::default();
+        message.author.name = "member".to_string();
+        message.content = "Hello".to_string();
+        message.timestamp = serenity::Timestamp::from_unix_timestamp(0).unwrap();
+
+        assert_eq!(
+            transcript_line(&message),
+            "[1970-01-01 00:00:00 UTC] member: Hello"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use poise::serenity_prelude::GuildId;
+use sqlx::{Row, SqlitePool};
+use tokio::time::Instant;
+use tracing::info;
+
+use bismarck_core::{counters::CommandCounters, metrics};
+
+/// How a command has been used over a period.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandStats {
+    pub command: String,
+    pub uses: u64,
+    pub failures: u64,
+    /// Median latency, in milliseconds.
+    pub p50: u64,
+    pub p95: u64,
+}
+
+impl CommandStats {
+    /// Share of runs that failed, as a percentage.
+    pub fn error_rate(&self) -> f64 {
+        if self.uses == 0 {
+            return 0.0;
+        }
+
+        self.failures as f64 / self.uses as f64 * 100.0
+    }
+}
+
+/// The nearest-rank percentile of sorted values.
+pub fn percentile(sorted: &[u64], percentile: u64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+
+    let rank = (percentile as usize * sorted.len()).div_ceil(100).max(1);
+    sorted[rank.min(sorted.len()) - 1]
+}
+
+/// Sums up runs of commands as `(command, success, latency)`, most used first.
+pub fn summarise(runs: Vec<(String, bool, u64)>) -> Vec<CommandStats> {
+    let mut by_command: HashMap<String, (u64, Vec<u64>)> = HashMap::new();
+
+    for (command, success, latency) in runs {
+        let (failures, latencies) = by_command.entry(command).or_default();
+
+        if !success {
+            *failures += 1;
+        }
+        latencies.push(latency);
+    }
+
+    let mut stats = by_command
+        .into_iter()
+        .map(|(command, (failures, mut latencies))| {
+            latencies.sort_unstable();
+
+            CommandStats {
+                command,
+                uses: latencies.len() as u64,
+                failures,
+                p50: percentile(&latencies, 50),
+                p95: percentile(&latencies, 95),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    stats.sort_by(|a, b| b.uses.cmp(&a.uses).then_with(|| a.command.cmp(&b.command)));
+    stats
+}
+
+/// Selects usage of every command since `since`, in a guild or across all of them, along with
+/// the runs not yet flushed from `counters`.
+pub async fn select_command_stats(
+    guild_id: Option<&GuildId>,
+    since: i64,
+    counters: &CommandCounters,
+    pool: &SqlitePool,
+) -> Result<Vec<CommandStats>, sqlx::Error> {
+    let start_time = Instant::now();
+
+    let rows = match guild_id {
+        Some(guild_id) => {
+            sqlx::query(
+                "SELECT command, success, latency_ms FROM command_log WHERE guild_id = ? AND created_at >= ?",
+            )
+            .bind(i64::from(*guild_id))
+            .bind(since)
+            .fetch_all(pool)
+            .await?
+        }
+        None => {
+            sqlx::query(
+                "SELECT command, success, latency_ms FROM command_log WHERE created_at >= ?",
+            )
+            .bind(since)
+            .fetch_all(pool)
+            .await?
+        }
+    };
+
+    let mut runs: Vec<_> = rows
+        .iter()
+        .map(|row| {
+            (
+                row.get::<String, _>(0),
+                row.get::<bool, _>(1),
+                row.get::<i64, _>(2).max(0) as u64,
+            )
+        })
+        .collect();
+
+    let elapsed_time = start_time.elapsed();
+    metrics::query("select", "command_log", elapsed_time);
+    info!("Selected from Command Log in {elapsed_time:.2?}");
+
+    runs.extend(counters.pending_usage(guild_id.map(|guild_id| guild_id.get()), since));
+
+    Ok(summarise(runs))
+}
+
+#[cfg(test)]
+mod usage_tests {
+    use super::*;
+
+    #[test]
+    fn percentile_test() {
+        let latencies = (1..=20).collect::<Vec<u64>>();
+
+        assert_eq!(percentile(&latencies, 50), 10);
+        assert_eq!(percentile(&latencies, 95), 19);
+        assert_eq!(percentile(&latencies, 100), 20);
+        assert_eq!(percentile(&[7], 95), 7);
+        assert_eq!(percentile(&[], 50), 0);
+    }
+
+    #[test]
+    fn summarise_test() {
+        let runs = vec![
+            ("ping".to_string(), true, 30),
+            ("remind in".to_string(), false, 200),
+            ("ping".to_string(), false, 10),
+            ("ping".to_string(), true, 20),
+        ];
+
+        let stats = summarise(runs);
+
+        assert_eq!(
+            stats[0],
+            CommandStats {
+                command: "ping".to_string(),
+                uses: 3,
+                failures: 1,
+                p50: 20,
+                p95: 30,
+            }
+        );
+        assert_eq!(stats[1].command, "remind in");
+        assert_eq!(stats[1].error_rate(), 100.0);
+    }
+}
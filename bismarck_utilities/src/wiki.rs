@@ -0,0 +1,79 @@
+use poise::serenity_prelude as serenity;
+use serenity::all::{
+    ComponentInteraction, ComponentInteractionDataKind, CreateEmbed, CreateMessage,
+};
+use tracing::debug;
+
+use bismarck_core::{
+    error::{BismarckError, Error},
+    metrics,
+    types::{Pages, QueryContainer},
+};
+
+/// Custom ID of the select menu of Wikipedia search results, whose values are article titles.
+pub const CUSTOM_ID_PREFIX: &str = "wiki";
+
+/// Fetches the introduction of a Wikipedia article from the API at `api_url`.
+pub async fn fetch_summary(
+    request: &reqwest::Client,
+    api_url: &str,
+    title: &str,
+) -> Result<Pages, Error> {
+    let url = format!("{api_url}?format=json&action=query&prop=extracts&exintro&explaintext&redirects=1&titles={title}");
+
+    debug!("URL: {}", url);
+
+    let res = match request.get(url).send().await {
+        Ok(res) => res.text().await?,
+        Err(_) => {
+            metrics::http_failure("wikipedia");
+            return Err(BismarckError::External(
+                "Failed to get data from Wikipedia.".to_string(),
+            ));
+        }
+    };
+
+    debug!("Response: {:?}", res);
+
+    let data: QueryContainer = serde_json::from_str(&res).map_err(|why| {
+        BismarckError::External(format!("Couldn't deserialize Wikipedia's response: {why}"))
+    })?;
+    debug!("{:?}", data);
+
+    Ok(data.query.pages)
+}
+
+/// Handles a pick from the search results, replying with the article's summary.
+pub async fn handle_component(
+    context: &serenity::Context,
+    request: &reqwest::Client,
+    api_url: &str,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let ComponentInteractionDataKind::StringSelect { values } = &interaction.data.kind else {
+        return Ok(());
+    };
+
+    let Some(title) = values.first() else {
+        return Ok(());
+    };
+
+    interaction.defer(context).await?;
+
+    let page = fetch_summary(request, api_url, title).await?;
+
+    let embed = CreateEmbed::new()
+        .title(page.title)
+        .description(page.extract);
+
+    let message = CreateMessage::new()
+        .embed(embed)
+        .reference_message(&*interaction.message);
+
+    interaction
+        .channel_id
+        .send_message(context, message)
+        .await?;
+
+    Ok(())
+}
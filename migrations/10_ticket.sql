@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS ticket_config (
+  guild_id BIGINT PRIMARY KEY NOT NULL,
+  staff_role_id BIGINT,
+  log_channel_id BIGINT,
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS ticket (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  guild_id BIGINT NOT NULL,
+  thread_id BIGINT NOT NULL UNIQUE,
+  user_id BIGINT NOT NULL,
+  claimed_by BIGINT,
+  status TEXT NOT NULL DEFAULT 'open' CHECK(status = 'open' OR status = 'closed'),
+  created_at BIGINT NOT NULL,
+  closed_at BIGINT,
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS ticket_user ON ticket (guild_id, user_id, status);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS reminder (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  user_id BIGINT NOT NULL,
+  channel_id BIGINT,
+  message TEXT NOT NULL,
+  remind_at BIGINT NOT NULL,
+  repeat_interval BIGINT,
+  created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS reminder_due ON reminder (remind_at);
+CREATE INDEX IF NOT EXISTS reminder_user ON reminder (user_id);
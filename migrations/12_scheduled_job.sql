@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS scheduled_job (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  key TEXT NOT NULL UNIQUE,
+  payload TEXT NOT NULL,
+  run_at BIGINT NOT NULL,
+  attempts INT NOT NULL DEFAULT 0,
+  locked_by TEXT,
+  locked_until BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS scheduled_job_due ON scheduled_job (run_at);
+
+INSERT INTO scheduled_job (key, payload, run_at)
+SELECT 'reminder:' || id, json_object('kind', 'reminder', 'reminder_id', id), remind_at
+FROM reminder
+WHERE true
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS command_log (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  command TEXT NOT NULL,
+  guild_id BIGINT,
+  user_id BIGINT NOT NULL,
+  success BOOLEAN NOT NULL,
+  latency_ms INT NOT NULL,
+  created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS command_log_guild ON command_log (guild_id, created_at);
+CREATE INDEX IF NOT EXISTS command_log_created ON command_log (created_at);
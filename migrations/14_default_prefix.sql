@@ -0,0 +1,27 @@
+-- SQLite can't change the default of a column, so the table is rebuilt with the bot's default
+-- prefix. Foreign keys must be off, as they are when the bot migrates, or dropping the old
+-- table would delete every row referencing a guild. The check below fails the migration if
+-- they are on while there are guilds to lose.
+SELECT CASE WHEN foreign_keys AND EXISTS (SELECT 1 FROM guild) THEN json('foreign keys must be off') END
+FROM pragma_foreign_keys;
+
+CREATE TABLE guild_new (
+  id BIGINT PRIMARY KEY NOT NULL,
+  mod_log_channel BIGINT,
+  message_log_channel BIGINT,
+  owner BIGINT NOT NULL,
+  commands_ran BIGINT NOT NULL,
+  songs_played INT NOT NULL,
+  mute_role BIGINT,
+  mute_style TEXT NOT NULL DEFAULT "timeout",
+  mute_duration BIGINT NOT NULL DEFAULT 3600,
+  prefix TEXT NOT NULL DEFAULT '+',
+  FOREIGN KEY (owner) REFERENCES user(id)
+);
+
+INSERT INTO guild_new (id, mod_log_channel, message_log_channel, owner, commands_ran, songs_played, mute_role, mute_style, mute_duration, prefix)
+SELECT id, mod_log_channel, message_log_channel, owner, commands_ran, songs_played, mute_role, mute_style, mute_duration, prefix FROM guild;
+
+DROP TABLE guild;
+
+ALTER TABLE guild_new RENAME TO guild;
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS channel_lockdown (
+  guild_id BIGINT NOT NULL,
+  channel_id BIGINT NOT NULL,
+  had_overwrite INT NOT NULL CHECK(had_overwrite = 0 OR had_overwrite = 1),
+  allow BIGINT NOT NULL DEFAULT 0,
+  deny BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY (guild_id, channel_id),
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
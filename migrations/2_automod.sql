@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS automod_config (
+  guild_id BIGINT PRIMARY KEY NOT NULL,
+  enabled INT NOT NULL DEFAULT 0 CHECK(enabled = 0 OR enabled = 1),
+  strictness TEXT NOT NULL DEFAULT 'medium',
+  action TEXT NOT NULL DEFAULT 'delete',
+  timeout_duration BIGINT NOT NULL DEFAULT 600,
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS automod_word (
+  guild_id BIGINT NOT NULL,
+  word TEXT NOT NULL,
+  allowed INT NOT NULL CHECK(allowed = 0 OR allowed = 1),
+  PRIMARY KEY (guild_id, word),
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS automod_exemption (
+  guild_id BIGINT NOT NULL,
+  target_id BIGINT NOT NULL,
+  kind TEXT NOT NULL CHECK(kind = 'role' OR kind = 'channel'),
+  PRIMARY KEY (guild_id, target_id),
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
@@ -0,0 +1,13 @@
+ALTER TABLE automod_config ADD COLUMN message_limit INT NOT NULL DEFAULT 5;
+ALTER TABLE automod_config ADD COLUMN message_window BIGINT NOT NULL DEFAULT 5;
+ALTER TABLE automod_config ADD COLUMN duplicate_limit INT NOT NULL DEFAULT 3;
+ALTER TABLE automod_config ADD COLUMN mention_limit INT NOT NULL DEFAULT 5;
+ALTER TABLE automod_config ADD COLUMN filter_invites INT NOT NULL DEFAULT 0 CHECK(filter_invites = 0 OR filter_invites = 1);
+ALTER TABLE automod_config ADD COLUMN filter_links INT NOT NULL DEFAULT 0 CHECK(filter_links = 0 OR filter_links = 1);
+
+CREATE TABLE IF NOT EXISTS automod_domain (
+  guild_id BIGINT NOT NULL,
+  domain TEXT NOT NULL,
+  PRIMARY KEY (guild_id, domain),
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS raid_config (
+  guild_id BIGINT PRIMARY KEY NOT NULL,
+  enabled INT NOT NULL DEFAULT 0 CHECK(enabled = 0 OR enabled = 1),
+  join_limit INT NOT NULL DEFAULT 10,
+  join_window BIGINT NOT NULL DEFAULT 10,
+  new_account_limit INT NOT NULL DEFAULT 5,
+  new_account_age BIGINT NOT NULL DEFAULT 604800,
+  action TEXT NOT NULL DEFAULT 'kick',
+  cooldown BIGINT NOT NULL DEFAULT 900,
+  raid_until BIGINT,
+  previous_verification INT,
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS greeting (
+  guild_id BIGINT NOT NULL,
+  kind TEXT NOT NULL CHECK(kind = 'welcome' OR kind = 'goodbye'),
+  channel_id BIGINT,
+  template TEXT NOT NULL,
+  embed INT NOT NULL DEFAULT 0 CHECK(embed = 0 OR embed = 1),
+  dm INT NOT NULL DEFAULT 0 CHECK(dm = 0 OR dm = 1),
+  PRIMARY KEY (guild_id, kind),
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS role_config (
+  guild_id BIGINT PRIMARY KEY NOT NULL,
+  autorole_delay BIGINT NOT NULL DEFAULT 0,
+  persist_roles INT NOT NULL DEFAULT 0 CHECK(persist_roles = 0 OR persist_roles = 1),
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS autorole (
+  guild_id BIGINT NOT NULL,
+  role_id BIGINT NOT NULL,
+  bots INT NOT NULL CHECK(bots = 0 OR bots = 1),
+  PRIMARY KEY (guild_id, role_id, bots),
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS persisted_role (
+  guild_id BIGINT NOT NULL,
+  user_id BIGINT NOT NULL,
+  role_id BIGINT NOT NULL,
+  PRIMARY KEY (guild_id, user_id, role_id),
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS role_menu (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  guild_id BIGINT NOT NULL,
+  channel_id BIGINT,
+  message_id BIGINT,
+  kind TEXT NOT NULL CHECK(kind = 'reactions' OR kind = 'buttons' OR kind = 'select'),
+  max_roles INT NOT NULL DEFAULT 0,
+  title TEXT NOT NULL,
+  description TEXT,
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS role_menu_message ON role_menu (message_id);
+
+CREATE TABLE IF NOT EXISTS role_menu_option (
+  menu_id INTEGER NOT NULL,
+  role_id BIGINT NOT NULL,
+  emoji TEXT,
+  label TEXT NOT NULL,
+  position INT NOT NULL,
+  PRIMARY KEY (menu_id, role_id),
+  FOREIGN KEY (menu_id) REFERENCES role_menu(id) ON DELETE CASCADE
+);
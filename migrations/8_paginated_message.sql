@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS paginated_message (
+  id TEXT PRIMARY KEY NOT NULL,
+  pages TEXT NOT NULL,
+  created_at BIGINT NOT NULL
+);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS starboard_config (
+  guild_id BIGINT PRIMARY KEY NOT NULL,
+  channel_id BIGINT,
+  emoji TEXT NOT NULL DEFAULT '⭐',
+  threshold INT NOT NULL DEFAULT 3,
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS starboard_message (
+  message_id BIGINT PRIMARY KEY NOT NULL,
+  guild_id BIGINT NOT NULL,
+  channel_id BIGINT NOT NULL,
+  starboard_message_id BIGINT NOT NULL,
+  stars INT NOT NULL,
+  FOREIGN KEY (guild_id) REFERENCES guild(id) ON DELETE CASCADE
+);
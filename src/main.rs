@@ -1,23 +1,45 @@
+use bismarck_core::config::Config;
 use bismarck_core::context::PartialContext;
+use bismarck_core::counters::CommandCounters;
 use bismarck_core::data::Data;
+use bismarck_core::health::Health;
+use bismarck_core::metrics;
+use bismarck_core::scheduler::Scheduler;
+use bismarck_core::shutdown::Shutdown;
 use bismarck_core::types::{GuildSettings, User};
 use bismarck_events::event_handler::event_handler;
 use bismarck_events::on_error::on_error;
 use dashmap::DashMap;
 use poise::serenity_prelude as serenity;
+use sqlx::SqlitePool;
 use std::env;
 use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::time::Instant;
 use std::{sync::Arc, time::Duration};
-use tokio::time::sleep;
-use tracing::{error, info};
+use tokio::time::{self, sleep};
+use tracing::{error, info, warn};
+
+mod server;
 
 use bismarck_commands::{
-    info::*, moderation::*, neko::*, owner::*, setup::*, utilities::*, wiki::*,
+    automod::*, greeting::*, info::*, moderation::*, neko::*, owner::*, raid::*, reminder::*,
+    role_menu::*, roles::*, setup::*, starboard::*, ticket::*, utilities::*, wiki::*,
 };
 
+/// How long running commands get to finish when shutting down. Docker kills the bot 10 seconds
+/// after asking it to stop, so this and `JOB_DEADLINE` leave time to disconnect.
+const COMMAND_DEADLINE: Duration = Duration::from_secs(5);
+
+/// How long running scheduled jobs get to finish when shutting down.
+const JOB_DEADLINE: Duration = Duration::from_secs(3);
+
+/// How often command counts are written to the database.
+const COUNTER_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
 #[tokio::main]
 async fn main() {
-    dotenv::dotenv().expect("Failed to load .env file");
+    // The environment may also be set without a .env file, as in containers
+    dotenv::dotenv().ok();
     // gets token, exits if no token
     let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
     let intents = bismarck_core::gateway_intents().await;
@@ -27,22 +49,27 @@ async fn main() {
     // In this case, a good default is setting the environment variable `RUST_LOG` to `debug`.
     tracing_subscriber::fmt::init();
 
-    let database = sqlx::sqlite::SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect_with(
-            sqlx::sqlite::SqliteConnectOptions::new()
-                .filename("database.sqlite")
-                .create_if_missing(true),
-        )
-        .await
-        .expect("Couldn't connect to database");
+    let config = Config::load().expect("Invalid configuration");
+
+    // Installed before anything is recorded, so nothing is missed.
+    #[cfg(feature = "metrics")]
+    let metrics_handle = metrics::install().expect("Couldn't install the metrics recorder");
+
+    let database_options = sqlx::sqlite::SqliteConnectOptions::new()
+        .filename(&config.database.path)
+        .create_if_missing(true);
 
     // Run migrations, which updates the database's schema to the latest version.
-    sqlx::migrate!("./migrations")
-        .run(&database)
+    bismarck_core::migrate(&database_options)
         .await
         .expect("Couldn't run database migrations");
 
+    let database = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(config.database.max_connections)
+        .connect_with(database_options)
+        .await
+        .expect("Couldn't connect to database");
+
     // Initiate guild settings
     let guild_settings = sqlx::query!("SELECT * FROM guild")
         .fetch_all(&database)
@@ -59,11 +86,32 @@ async fn main() {
             mute_type: guild_setting.mute_style,
             mute_role: guild_setting.mute_role.unwrap_or_default() as u64,
             default_mute_duration: guild_setting.mute_duration as u64,
+            mod_log_channel: guild_setting.mod_log_channel.map(|id| id as u64),
         };
 
         guild_settings_map.insert(guild_id, guild_settings);
     }
 
+    let automod_settings = bismarck_utilities::automod::select_automod_settings(&database)
+        .await
+        .expect("Couldn't fetch automod settings")
+        .into_iter()
+        .collect::<DashMap<_, _>>();
+
+    let raid_settings = bismarck_utilities::raid::select_raid_settings(&database)
+        .await
+        .expect("Couldn't fetch raid settings")
+        .into_iter()
+        .collect::<DashMap<_, _>>();
+
+    let starboard_settings = bismarck_utilities::starboard::select_starboard_settings(&database)
+        .await
+        .expect("Couldn't fetch starboard settings")
+        .into_iter()
+        .collect::<DashMap<_, _>>();
+
+    let scheduler = Scheduler::new(database.clone());
+
     let users = DashMap::new();
     let commands_ran_user_map = DashMap::new();
     let users_map = sqlx::query!("SELECT * FROM user")
@@ -106,10 +154,22 @@ async fn main() {
         songs_played.insert(guild_id, AtomicU64::new(sp));
     }
 
+    let framework_scheduler = scheduler.clone();
+    let command_counters = CommandCounters::new();
+    let framework_counters = command_counters.clone();
+    let bot_shutdown = Shutdown::new();
+    let framework_shutdown = bot_shutdown.clone();
+    let health = Health::new();
+    let framework_config = config.clone();
+    let framework_health = health.clone();
+    let pool = database.clone();
+    let recent_messages = Arc::new(DashMap::new());
+    let framework_recent_messages = recent_messages.clone();
+
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
             prefix_options: poise::PrefixFrameworkOptions {
-                prefix: Some("+".to_string()),
+                prefix: Some(config.bot.default_prefix.clone()),
                 // tracks edits for 60 seconds
                 edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
                     std::time::Duration::from_secs(60),
@@ -129,6 +189,7 @@ async fn main() {
                 user_info(),
                 user_avatars(),
                 bot_stat(),
+                stats(),
                 // Math commands
                 // TODO: math(),
                 // Moderation commands
@@ -139,6 +200,13 @@ async fn main() {
                 untimeout(),
                 warn(),
                 warnings(),
+                massban(),
+                purge(),
+                lockdown(),
+                unlock(),
+                slowmode(),
+                automod(),
+                raid(),
                 // Neko commands
                 neko(),
                 // Wiki commands
@@ -147,7 +215,15 @@ async fn main() {
                 help(),
                 ping(),
                 servers(),
+                remind(),
                 prefix(),
+                mod_log_channel(),
+                greeting(),
+                autorole(),
+                persistroles(),
+                rolemenu(),
+                starboard(),
+                ticket(),
                 status(),
                 // Owner commands
                 shutdown(),
@@ -160,6 +236,9 @@ async fn main() {
             pre_command: |context| {
                 Box::pin(async move { bismarck_utilities::command::pre_command(context).await })
             },
+            post_command: |context| {
+                Box::pin(async move { bismarck_utilities::command::post_command(context).await })
+            },
             on_error: |error| Box::pin(on_error(error)),
             ..Default::default()
         })
@@ -167,14 +246,24 @@ async fn main() {
             Box::pin(async move {
                 poise::builtins::register_globally(context, &framework.options().commands).await?;
                 Ok(Data {
+                    config: framework_config,
                     reqwest: reqwest::Client::new(),
                     sqlite: database,
                     commands_ran,
                     users,
                     commands_ran_users: commands_ran_user_map,
+                    command_counters: framework_counters,
                     songs_played,
                     guild_data: guild_settings_map,
+                    automod: automod_settings,
+                    recent_messages: framework_recent_messages,
+                    raid: Arc::new(raid_settings),
+                    recent_joins: DashMap::new(),
+                    starboard: starboard_settings,
+                    scheduler: framework_scheduler,
                     shard_manager: framework.shard_manager().clone(),
+                    shutdown: framework_shutdown,
+                    health: framework_health,
                     is_loop_running: AtomicBool::new(false),
                 })
             })
@@ -186,37 +275,180 @@ async fn main() {
         .await
         .unwrap();
 
-    // Setup shard manager
-    let shard_manager = client.shard_manager.clone();
+    // Shut down on Ctrl-C, on SIGTERM (as sent by `docker stop`) or when the owner asks to
+    let coordinator = {
+        let shard_manager = client.shard_manager.clone();
+        let shutdown = bot_shutdown.clone();
+        let command_counters = command_counters.clone();
+        let pool = pool.clone();
+
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = shutdown_signal() => {}
+                _ = shutdown.triggered() => {}
+            }
+
+            shut_down(
+                &shutdown,
+                &scheduler,
+                &command_counters,
+                &shard_manager,
+                &pool,
+            )
+            .await;
+        })
+    };
+
+    // Probed by Docker and Kubernetes, and scraped by Prometheus with the `metrics` feature
+    let address = format!("{}:{}", config.http.host, config.http.port);
+    let state = server::ServerState {
+        pool: pool.clone(),
+        shard_manager: client.shard_manager.clone(),
+        health,
+    };
+    let shutdown = bot_shutdown.clone();
 
-    // Start shard manager
     tokio::spawn(async move {
-        tokio::signal::ctrl_c()
-            .await
-            .expect("Could not register ctrl+c handler");
+        let served = server::serve(
+            &address,
+            state,
+            #[cfg(feature = "metrics")]
+            metrics_handle,
+            shutdown,
+        );
+
+        if let Err(why) = served.await {
+            error!("Couldn't serve HTTP: {:?}", why);
+        }
+    });
+
+    let token = bot_shutdown.token();
 
-        info!("Gracefully shutting down...");
-        shard_manager.shutdown_all().await;
+    tokio::spawn(async move {
+        token
+            .run_until_cancelled(async move {
+                let mut interval = time::interval(COUNTER_FLUSH_INTERVAL);
+
+                loop {
+                    interval.tick().await;
+                    // Failed flushes keep their counts for the next one
+                    let _ = command_counters.flush(&pool).await;
+                }
+            })
+            .await;
+    });
+
+    let token = bot_shutdown.token();
+
+    tokio::spawn(async move {
+        token
+            .run_until_cancelled(async move {
+                let mut interval = time::interval(bismarck_utilities::automod::HISTORY_WINDOW);
+
+                loop {
+                    interval.tick().await;
+                    bismarck_utilities::automod::prune_recent_messages(
+                        &recent_messages,
+                        Instant::now(),
+                    );
+                }
+            })
+            .await;
     });
 
     let manager = client.shard_manager.clone();
+    let cache = client.cache.clone();
+    let token = bot_shutdown.token();
 
     tokio::spawn(async move {
-        loop {
-            sleep(Duration::from_secs(30)).await;
+        token
+            .run_until_cancelled(async move {
+                loop {
+                    sleep(Duration::from_secs(30)).await;
 
-            let shard_runners = manager.runners.lock().await;
+                    let shard_runners = manager.runners.lock().await;
 
-            for (id, runner) in shard_runners.iter() {
-                info!(
-                    "Shard ID {} is {} with a latency of {:?}",
-                    id, runner.stage, runner.latency,
-                );
-            }
-        }
+                    for (id, runner) in shard_runners.iter() {
+                        info!(
+                            "Shard ID {} is {} with a latency of {:?}",
+                            id, runner.stage, runner.latency,
+                        );
+                        metrics::shard_status(id.0, runner.stage, runner.latency);
+                    }
+
+                    metrics::guild_count(cache.guilds().len());
+                }
+            })
+            .await;
     });
 
     if let Err(why) = client.start_autosharded().await {
         error!("Client error: {:?}", why);
     }
+
+    // The shards may also stop on their own, in which case everything else has to stop too
+    bot_shutdown.trigger();
+    if let Err(why) = coordinator.await {
+        error!("Shutdown failed: {:?}", why);
+    }
+}
+
+/// Completes when the process is asked to stop.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Could not register ctrl+c handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Could not register SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Stops background tasks, lets running commands and jobs finish within their deadlines, then
+/// disconnects, writes the last command counts and closes the database.
+async fn shut_down(
+    shutdown: &Shutdown,
+    scheduler: &Scheduler,
+    command_counters: &CommandCounters,
+    shard_manager: &serenity::ShardManager,
+    pool: &SqlitePool,
+) {
+    info!("Gracefully shutting down...");
+    shutdown.trigger();
+
+    if !shutdown.wait_for_commands(COMMAND_DEADLINE).await {
+        warn!(
+            "Shutting down with {} command(s) still running",
+            shutdown.running_commands()
+        );
+    }
+
+    if time::timeout(JOB_DEADLINE, scheduler.shutdown())
+        .await
+        .is_err()
+    {
+        warn!("Shutting down with scheduled jobs still running");
+    }
+
+    shard_manager.shutdown_all().await;
+
+    // Once no more commands can run, so none are left uncounted
+    let _ = command_counters.flush(pool).await;
+    pool.close().await;
+
+    info!("Shut down");
 }
@@ -0,0 +1,87 @@
+//! Serves health and readiness probes over HTTP, along with metrics for Prometheus to scrape
+//! when built with the `metrics` feature.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{extract::State, http::StatusCode, routing::get, Router};
+use bismarck_core::{
+    health::{self, Health},
+    shutdown::Shutdown,
+};
+use poise::serenity_prelude as serenity;
+use sqlx::SqlitePool;
+use tokio::{net::TcpListener, time};
+use tracing::{info, warn};
+
+/// How long the database gets to answer a health check.
+const DATABASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+pub struct ServerState {
+    pub pool: SqlitePool,
+    pub shard_manager: Arc<serenity::ShardManager>,
+    pub health: Health,
+}
+
+/// The process is up and the database can be queried.
+async fn healthz(State(state): State<ServerState>) -> (StatusCode, String) {
+    match time::timeout(DATABASE_TIMEOUT, health::check_database(&state.pool)).await {
+        Ok(Ok(())) => (StatusCode::OK, "OK".to_string()),
+        Ok(Err(why)) => {
+            warn!("Health check failed: {:?}", why);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Database is unreachable".to_string(),
+            )
+        }
+        Err(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Database timed out".to_string(),
+        ),
+    }
+}
+
+/// Every shard is connected and the cache is ready, so events and commands are handled.
+async fn readyz(State(state): State<ServerState>) -> (StatusCode, String) {
+    if let Err(why) = health::check_shards(&state.shard_manager).await {
+        return (StatusCode::SERVICE_UNAVAILABLE, why);
+    }
+
+    if !state.health.is_cache_ready() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Cache is not ready".to_string(),
+        );
+    }
+
+    (StatusCode::OK, "OK".to_string())
+}
+
+/// Serves `/healthz`, `/readyz` and, with the `metrics` feature, `/metrics` on `address` until
+/// shutting down.
+pub async fn serve(
+    address: &str,
+    state: ServerState,
+    #[cfg(feature = "metrics")] metrics_handle: bismarck_core::metrics::PrometheusHandle,
+    shutdown: Shutdown,
+) -> std::io::Result<()> {
+    let app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz));
+
+    #[cfg(feature = "metrics")]
+    let app = app.route(
+        "/metrics",
+        get(move || async move { metrics_handle.render() }),
+    );
+
+    let app = app.with_state(state);
+
+    let listener = TcpListener::bind(address).await?;
+    info!("Serving HTTP on {}", listener.local_addr()?);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { shutdown.triggered().await })
+        .await
+}